@@ -0,0 +1,350 @@
+// benches/attendance_queries.rs
+//
+// Times the attendance queries behind the records view and the kiosks over synthetic data, against
+// the integer timestamp schema and, for comparison, the RFC3339 text layout it replaced.
+//
+//     cargo bench --bench attendance_queries
+//
+// ATTENDANCE_BENCH_ROWS overrides the default of a million rows.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Duration as ChronoDuration, TimeZone, Utc};
+use rusqlite::{params, Connection, Result};
+use uuid::Uuid;
+
+use sample2_lib::db::app_settings::AppSettingsDatabase;
+use sample2_lib::db::attendance::{
+    create_attendance_table,
+    AttendanceQuery,
+    AttendanceRepository,
+    SqliteAttendanceRepository,
+};
+use sample2_lib::db::school_accounts::create_school_accounts_table;
+use sample2_lib::db::search::create_search_tables;
+use sample2_lib::db::semester::create_semesters_table;
+
+const DEFAULT_ROWS: usize = 1_000_000;
+const STUDENTS: usize = 20_000;
+const ITERATIONS: usize = 20;
+
+const FIRST_NAMES: &[&str] = &["Juan", "María", "José", "Niña", "Ramón", "Lourdes", "Andrés", "Concepción", "Ma. Cristina", "Jericho"];
+const LAST_NAMES: &[&str] = &["Dela Cruz", "Santos", "Reyes", "Peña", "Bautista", "Ocampo", "Garcia", "Mendoza", "Villanueva", "Castañeda"];
+const PURPOSES: &[&str] = &["Study", "Borrow Books", "Return Books", "Research", "Printing", "Group Study"];
+
+// The layout before timestamps were stored as integers: RFC3339 text and no time index
+const LEGACY_TABLE_SQL: &str = "
+    CREATE TABLE attendance (
+        id TEXT PRIMARY KEY,
+        school_id TEXT NOT NULL,
+        full_name TEXT NOT NULL,
+        time_in_date TEXT NOT NULL,
+        classification TEXT NOT NULL,
+        purpose_label TEXT,
+        time_out_date TEXT,
+        duration_minutes INTEGER,
+        is_auto_closed INTEGER NOT NULL DEFAULT 0,
+        semester_id TEXT,
+        visitor_id TEXT,
+        location_id TEXT,
+        device_id TEXT,
+        group_id TEXT,
+        event_id TEXT,
+        chain_hash TEXT
+    )";
+
+struct SyntheticVisit {
+    id: String,
+    school_id: String,
+    full_name: String,
+    time_in: DateTime<Utc>,
+    purpose_label: &'static str,
+    time_out: Option<DateTime<Utc>>,
+}
+
+// Small deterministic generator, so both layouts get the same rows on every run
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+fn school_id(student: usize) -> String {
+    format!("2024-{:05}", student)
+}
+
+fn full_name(student: usize) -> String {
+    format!(
+        "{} {}",
+        FIRST_NAMES[student % FIRST_NAMES.len()],
+        LAST_NAMES[(student / FIRST_NAMES.len()) % LAST_NAMES.len()]
+    )
+}
+
+// Visits spread over the two years before `end`, most of them checked out
+fn synthetic_visits(rows: usize, end: DateTime<Utc>) -> impl Iterator<Item = SyntheticVisit> {
+    let mut rng = Lcg(42);
+    let span_seconds = 2 * 365 * 24 * 60 * 60;
+
+    (0..rows).map(move |_| {
+        let student = rng.below(STUDENTS);
+        let time_in = end - ChronoDuration::seconds(rng.below(span_seconds) as i64);
+        let time_out = match rng.below(20) {
+            0 => None,
+            _ => Some(time_in + ChronoDuration::minutes(10 + rng.below(240) as i64)),
+        };
+
+        SyntheticVisit {
+            id: Uuid::new_v4().to_string(),
+            school_id: school_id(student),
+            full_name: full_name(student),
+            time_in,
+            purpose_label: PURPOSES[rng.below(PURPOSES.len())],
+            time_out,
+        }
+    })
+}
+
+fn nanos(time: DateTime<Utc>) -> i64 {
+    time.timestamp_nanos_opt().expect("synthetic times fit in i64 nanoseconds")
+}
+
+fn open_fresh(name: &str) -> Result<(Connection, PathBuf)> {
+    let path = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_file(&path);
+    let conn = Connection::open(&path)?;
+    conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")?;
+    Ok((conn, path))
+}
+
+fn populate_current(conn: &Connection, rows: usize, end: DateTime<Utc>) -> Result<()> {
+    // Per-day filters read the institution timezone from the settings
+    AppSettingsDatabase::init(conn)?;
+    create_semesters_table(conn)?;
+    create_school_accounts_table(conn)?;
+    create_attendance_table(conn)?;
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO attendance (id, school_id, full_name, time_in_date, classification, purpose_label,
+                time_out_date, duration_minutes, device_id)
+             VALUES (?1, ?2, ?3, ?4, 'Student', ?5, ?6, ?7, 'bench')"
+        )?;
+        for visit in synthetic_visits(rows, end) {
+            insert.execute(params![
+                visit.id,
+                visit.school_id,
+                visit.full_name,
+                nanos(visit.time_in),
+                visit.purpose_label,
+                visit.time_out.map(nanos),
+                visit.time_out.map(|t| (t - visit.time_in).num_minutes())
+            ])?;
+        }
+    }
+    tx.commit()?;
+
+    // Indexing after the bulk insert is much faster than going through the triggers row by row
+    create_search_tables(conn)?;
+    conn.execute_batch("ANALYZE")
+}
+
+fn populate_legacy(conn: &Connection, rows: usize, end: DateTime<Utc>) -> Result<()> {
+    create_school_accounts_table(conn)?;
+    conn.execute_batch(LEGACY_TABLE_SQL)?;
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT INTO attendance (id, school_id, full_name, time_in_date, classification, purpose_label,
+                time_out_date, duration_minutes, device_id)
+             VALUES (?1, ?2, ?3, ?4, 'Student', ?5, ?6, ?7, 'bench')"
+        )?;
+        for visit in synthetic_visits(rows, end) {
+            insert.execute(params![
+                visit.id,
+                visit.school_id,
+                visit.full_name,
+                visit.time_in.to_rfc3339(),
+                visit.purpose_label,
+                visit.time_out.map(|t| t.to_rfc3339()),
+                visit.time_out.map(|t| (t - visit.time_in).num_minutes())
+            ])?;
+        }
+    }
+    tx.commit()?;
+    conn.execute_batch("ANALYZE")
+}
+
+// Runs `f` ITERATIONS times after one warm-up call and prints the median and slowest run
+fn bench<T>(name: &str, mut f: impl FnMut() -> Result<T>) -> Result<()> {
+    f()?;
+    let mut timings: Vec<Duration> = Vec::with_capacity(ITERATIONS);
+    for _ in 0..ITERATIONS {
+        let started = Instant::now();
+        f()?;
+        timings.push(started.elapsed());
+    }
+    timings.sort();
+
+    println!(
+        "{:<48} median {:>10.3?}   max {:>10.3?}",
+        name,
+        timings[timings.len() / 2],
+        timings[timings.len() - 1]
+    );
+    Ok(())
+}
+
+fn bench_current(conn: &Connection, end: DateTime<Utc>) -> Result<()> {
+    let repo = SqliteAttendanceRepository;
+    let week_ago = end - ChronoDuration::days(7);
+    let day = end - ChronoDuration::days(30);
+
+    println!("\ninteger timestamps");
+
+    bench("first page, newest first", || {
+        repo.query_attendances(conn, &AttendanceQuery::default())
+    })?;
+
+    let first_page = repo.query_attendances(conn, &AttendanceQuery::default())?;
+    bench("next page by cursor", || {
+        repo.query_attendances(conn, &AttendanceQuery {
+            cursor: first_page.next_cursor.clone(),
+            ..AttendanceQuery::default()
+        })
+    })?;
+
+    bench("page filtered to the last week", || {
+        repo.query_attendances(conn, &AttendanceQuery {
+            date_from: Some(week_ago),
+            date_to: Some(end),
+            ..AttendanceQuery::default()
+        })
+    })?;
+
+    bench("page filtered by purpose", || {
+        repo.query_attendances(conn, &AttendanceQuery {
+            purposes: vec!["Printing".to_string()],
+            ..AttendanceQuery::default()
+        })
+    })?;
+
+    bench("visits of one school ID", || {
+        repo.get_attendances_by_school_id(conn, &school_id(1234))
+    })?;
+
+    bench("duplicate check on import", || {
+        repo.find_duplicate_attendance(conn, &school_id(1234), day, 60)
+    })?;
+
+    bench("visits on one day", || {
+        repo.get_filtered_attendances(conn, None, Some(day))
+    })?;
+
+    bench("full-text search \"pena dela\"", || {
+        repo.search_attendances(conn, "pena dela", 50)
+    })?;
+
+    Ok(())
+}
+
+// The statements the repository ran against the text layout
+fn bench_legacy(conn: &Connection, end: DateTime<Utc>) -> Result<()> {
+    let week_ago = (end - ChronoDuration::days(7)).to_rfc3339();
+    let day = (end - ChronoDuration::days(30)).to_rfc3339();
+    let count_rows = |sql: &str, params: &[&dyn rusqlite::ToSql]| -> Result<usize> {
+        let mut stmt = conn.prepare_cached(sql)?;
+        let mut rows = stmt.query(params)?;
+        let mut count = 0;
+        while let Some(row) = rows.next()? {
+            // Every row used to be parsed back from RFC3339
+            let time_in: String = row.get(0)?;
+            DateTime::parse_from_rfc3339(&time_in).expect("synthetic times are valid RFC3339");
+            count += 1;
+        }
+        Ok(count)
+    };
+
+    println!("\nRFC3339 text timestamps");
+
+    bench("first page, newest first", || {
+        count_rows("SELECT time_in_date FROM attendance ORDER BY time_in_date DESC, id DESC LIMIT 50", &[])
+    })?;
+
+    bench("page filtered to the last week", || {
+        count_rows(
+            "SELECT time_in_date FROM attendance WHERE time_in_date >= ?1 AND time_in_date <= ?2
+             ORDER BY time_in_date DESC, id DESC LIMIT 50",
+            &[&week_ago, &end.to_rfc3339()],
+        )
+    })?;
+
+    bench("page filtered by purpose", || {
+        count_rows(
+            "SELECT time_in_date FROM attendance WHERE purpose_label IN (?1)
+             ORDER BY time_in_date DESC, id DESC LIMIT 50",
+            &[&"Printing"],
+        )
+    })?;
+
+    bench("visits of one school ID", || {
+        count_rows(
+            "SELECT time_in_date FROM attendance WHERE school_id = ?1 ORDER BY time_in_date DESC",
+            &[&school_id(1234)],
+        )
+    })?;
+
+    bench("visits on one day", || {
+        count_rows(
+            "SELECT time_in_date FROM attendance WHERE date(time_in_date) = date(?1) ORDER BY time_in_date DESC",
+            &[&day],
+        )
+    })?;
+
+    bench("LIKE search \"pena\"", || {
+        count_rows(
+            "SELECT time_in_date FROM attendance WHERE school_id LIKE ?1 OR full_name LIKE ?1 OR purpose_label LIKE ?1
+             ORDER BY time_in_date DESC",
+            &[&"%pena%"],
+        )
+    })?;
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let rows = std::env::var("ATTENDANCE_BENCH_ROWS")
+        .ok()
+        .and_then(|rows| rows.parse().ok())
+        .unwrap_or(DEFAULT_ROWS);
+    let end = Utc.with_ymd_and_hms(2025, 6, 30, 12, 0, 0).unwrap();
+
+    println!("Generating {} synthetic attendance rows", rows);
+
+    let (current, current_path) = open_fresh("attendance_bench_current.db")?;
+    populate_current(&current, rows, end)?;
+    bench_current(&current, end)?;
+    drop(current);
+
+    let (legacy, legacy_path) = open_fresh("attendance_bench_legacy.db")?;
+    populate_legacy(&legacy, rows, end)?;
+    bench_legacy(&legacy, end)?;
+    drop(legacy);
+
+    for path in [current_path, legacy_path] {
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(path.with_extension("db-wal"));
+        let _ = std::fs::remove_file(path.with_extension("db-shm"));
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+// src/app_settings_commands.rs
+
+use tauri::State;
+use chrono::NaiveTime;
+use chrono_tz::Tz;
+use crate::DbState;
+use crate::db::app_settings::{
+    AppSetting,
+    ATTENDANCE_CLOSING_TIME,
+    ATTENDANCE_DUPLICATE_COOLDOWN_SECONDS,
+    INSTITUTION_TIMEZONE,
+};
+use rusqlite::{Result, Error as RusqliteError};
+
+// Rejects values the background tasks would not be able to parse
+fn validate_setting(key: &str, value: &str) -> Result<(), String> {
+    match key {
+        ATTENDANCE_CLOSING_TIME => NaiveTime::parse_from_str(value.trim(), "%H:%M")
+            .map(|_| ())
+            .map_err(|_| format!("Invalid time for {}: expected HH:MM", key)),
+        ATTENDANCE_DUPLICATE_COOLDOWN_SECONDS => match value.trim().parse::<i64>() {
+            Ok(seconds) if seconds >= 0 => Ok(()),
+            _ => Err(format!("Invalid value for {}: expected a non-negative number of seconds", key)),
+        },
+        INSTITUTION_TIMEZONE => value.trim().parse::<Tz>()
+            .map(|_| ())
+            .map_err(|_| format!("Invalid timezone for {}: expected an IANA name such as Asia/Manila", key)),
+        _ => Ok(()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_all_app_settings(
+    state: State<'_, DbState>
+) -> Result<Vec<AppSetting>, String> {
+    let db = state.0.clone();
+    let app_settings = db.app_settings.clone();
+    db.with_connection(move |conn| {
+        app_settings.get_all_settings(conn)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_app_setting(
+    state: State<'_, DbState>,
+    key: String
+) -> Result<Option<AppSetting>, String> {
+    let db = state.0.clone();
+    let app_settings = db.app_settings.clone();
+    db.with_connection(move |conn| {
+        app_settings.get_setting(conn, &key)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_app_setting(
+    state: State<'_, DbState>,
+    key: String,
+    value: String,
+    username: String,
+    password: String
+) -> Result<AppSetting, String> {
+    validate_setting(&key, &value)?;
+
+    let db = state.0.clone();
+    let app_settings = db.app_settings.clone();
+    let auth = db.auth.clone();
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            app_settings.set_setting(conn, &key, value.trim())
+        } else {
+            Err(RusqliteError::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
@@ -0,0 +1,38 @@
+// src/archive_commands.rs
+
+use tauri::State;
+use crate::DbState;
+use crate::db::attendance_archive::{get_archive_status as read_archive_status, ArchiveRunResult, ArchiveStatus};
+use crate::attendance_retention::run_attendance_retention;
+
+#[tauri::command]
+pub async fn get_archive_status(
+    state: State<'_, DbState>
+) -> Result<ArchiveStatus, String> {
+    let db = state.0.clone();
+
+    db.with_connection(move |conn| {
+        read_archive_status(conn)
+    }).await.map_err(|e| e.to_string())
+}
+
+// Applies the retention settings now instead of waiting for the daily run
+#[tauri::command]
+pub async fn run_retention_now(
+    state: State<'_, DbState>,
+    username: String,
+    password: String
+) -> Result<ArchiveRunResult, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+
+    let authenticated = db.with_connection(move |conn| {
+        auth.authenticate(conn, &username, &password)
+    }).await.map_err(|e| format!("Authentication failed: {}", e))?;
+
+    if !authenticated {
+        return Err("Authentication failed: invalid credentials".to_string());
+    }
+
+    run_attendance_retention(&db).await
+}
@@ -0,0 +1,67 @@
+// src/attendance_analytics_commands.rs
+use tauri::State;
+use std::sync::Arc;
+use crate::DbState;
+use crate::db::attendance::AttendanceQuery;
+use crate::db::attendance_analytics::{
+    AttendanceSummary,
+    BreakdownCount,
+    BreakdownDimension,
+    HeatmapCell,
+    TimeBucket,
+    TimeBucketCount
+};
+
+#[tauri::command]
+pub async fn get_attendance_heatmap(
+    state: State<'_, DbState>,
+    query: AttendanceQuery
+) -> Result<Vec<HeatmapCell>, String> {
+    let db = state.0.clone();
+    let analytics_repo = Arc::clone(&db.attendance_analytics_repository);
+
+    db.with_connection(move |conn| {
+        analytics_repo.get_hourly_heatmap(conn, &query)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_attendance_time_series(
+    state: State<'_, DbState>,
+    query: AttendanceQuery,
+    bucket: TimeBucket
+) -> Result<Vec<TimeBucketCount>, String> {
+    let db = state.0.clone();
+    let analytics_repo = Arc::clone(&db.attendance_analytics_repository);
+
+    db.with_connection(move |conn| {
+        analytics_repo.get_time_series(conn, &query, bucket)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_attendance_breakdown(
+    state: State<'_, DbState>,
+    query: AttendanceQuery,
+    dimension: BreakdownDimension
+) -> Result<Vec<BreakdownCount>, String> {
+    let db = state.0.clone();
+    let analytics_repo = Arc::clone(&db.attendance_analytics_repository);
+
+    db.with_connection(move |conn| {
+        analytics_repo.get_breakdown(conn, &query, dimension)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_attendance_summary(
+    state: State<'_, DbState>,
+    query: AttendanceQuery
+) -> Result<AttendanceSummary, String> {
+    let db = state.0.clone();
+    let analytics_repo = Arc::clone(&db.attendance_analytics_repository);
+
+    db.with_connection(move |conn| {
+        analytics_repo.get_summary(conn, &query)
+    }).await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,38 @@
+// src/attendance_auto_close.rs
+
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{NaiveTime, Utc};
+use log::{info, error};
+use crate::db::Database;
+use crate::db::app_settings::ATTENDANCE_CLOSING_TIME;
+
+// How often open visits are checked against the closing time
+const AUTO_CLOSE_INTERVAL: Duration = Duration::from_secs(300);
+
+pub fn default_closing_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(20, 0, 0).unwrap()
+}
+
+// Closes visits left open past closing time, including ones from days the app was not running
+pub async fn run_auto_close(db: &Database) -> Result<usize, Box<dyn std::error::Error>> {
+    let app_settings = db.app_settings.clone();
+    let attendance_repo = Arc::clone(&db.attendance_repository);
+
+    db.with_connection(move |conn| {
+        let closing_time = app_settings.get_time(conn, ATTENDANCE_CLOSING_TIME, default_closing_time())?;
+        attendance_repo.auto_close_open_attendances(conn, closing_time, Utc::now())
+    }).await
+}
+
+pub async fn start_auto_close_task(db: Database) {
+    loop {
+        match run_auto_close(&db).await {
+            Ok(0) => {},
+            Ok(closed) => info!("Auto-closed {} open attendance visits", closed),
+            Err(e) => error!("Failed to auto-close open attendance visits: {}", e),
+        }
+
+        tokio::time::sleep(AUTO_CLOSE_INTERVAL).await;
+    }
+}
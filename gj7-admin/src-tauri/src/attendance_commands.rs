@@ -1,70 +1,287 @@
 // src/attendance_commands.rs
-use tauri::State;
+use tauri::{AppHandle, State};
+use tauri_plugin_dialog::DialogExt;
 use uuid::Uuid;
 use crate::DbState;
-use crate::db::attendance::{Attendance, CreateAttendanceRequest, UpdateAttendanceRequest, AttendanceExportError};
+use crate::db::attendance::{
+    Attendance,
+    AttendanceQuery,
+    PaginatedAttendances,
+    CreateAttendanceRequest,
+    UpdateAttendanceRequest,
+    ManualAttendanceRequest,
+    ManualAttendanceResult,
+    AttendanceExportError
+};
+use crate::db::search::{AttendanceSearchHit, DEFAULT_SEARCH_LIMIT};
 use rusqlite::Result;
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
-use chrono::{DateTime, Utc};
-use std::path::PathBuf;
-use std::env;
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+use crate::db::attendance_audit::AttendanceAuditEntry;
+use crate::db::attendance_integrity::{csv_digest_path, ChainVerificationReport, ExportDigestVerification};
+use crate::attendance_auto_close::run_auto_close;
+use crate::attendance_export_scheduler::run_export_now;
+use crate::db::export_jobs::{ExportJob, ExportJobType};
+use crate::db::export_templates::{
+    resolve_template,
+    CreateExportTemplateRequest,
+    ExportTemplate,
+};
+use crate::db::institution_time::{institution_timezone, local_date};
+use crate::storage::get_downloads_dir;
+use crate::xlsx_export::XlsxSheetGrouping;
+use crate::pdf_report::{write_attendance_report, AttendanceReport};
+
+// Export filename with timestamp, e.g. attendance_BSIT_20240101_093000.csv, dated on the institution's calendar
+fn export_filename(
+    tz: Tz,
+    course: &Option<String>,
+    date: Option<DateTime<Utc>>,
+    filtered: bool,
+    extension: &str
+) -> String {
+    let timestamp = Utc::now().with_timezone(&tz).format("%Y%m%d_%H%M%S");
+    match (course, date.map(|d| local_date(tz, d))) {
+        _ if filtered => format!("attendance_filtered_{}.{}", timestamp, extension),
+        (Some(c), Some(d)) => format!("attendance_{}_{}_{}.{}", c, d.format("%Y%m%d"), timestamp, extension),
+        (Some(c), None) => format!("attendance_{}_{}.{}", c, timestamp, extension),
+        (None, Some(d)) => format!("attendance_{}_{}.{}", d.format("%Y%m%d"), timestamp, extension),
+        (None, None) => format!("attendance_{}.{}", timestamp, extension),
+    }
+}
 
 #[tauri::command]
 pub async fn export_attendances_to_csv(
     state: State<'_, DbState>,
     course: Option<String>,
     date: Option<DateTime<Utc>>,
+    filter: Option<AttendanceQuery>,
+    template_id: Option<Uuid>,
 ) -> Result<String, String> {
+    let downloads_dir = get_downloads_dir()?;
     let db = state.0.clone();
     let attendance_repo = Arc::clone(&db.attendance_repository);
 
     db.with_connection(move |conn| {
-        // Get the attendances based on filters
-        let attendances = attendance_repo.get_filtered_attendances(conn, course.clone(), date)?;
-        
-        // Generate filename with timestamp
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let filename = match (course.clone(), date) {
-            (Some(c), Some(d)) => format!("attendance_{}_{}_{}.csv", c, d.format("%Y%m%d"), timestamp),
-            (Some(c), None) => format!("attendance_{}_{}.csv", c, timestamp),
-            (None, Some(d)) => format!("attendance_{}_{}.csv", d.format("%Y%m%d"), timestamp),
-            (None, None) => format!("attendance_{}.csv", timestamp),
-        };
+        let template = resolve_template(conn, template_id, ExportTemplate::builtin_csv)?;
 
-        // Get downloads directory
-        let downloads_dir = if cfg!(target_os = "windows") {
-            if let Some(home) = env::var_os("USERPROFILE") {
-                PathBuf::from(home).join("Downloads")
-            } else {
-                return Err(rusqlite::Error::InvalidParameterName("Could not find Downloads directory".to_string()));
-            }
-        } else {
-            if let Some(home) = env::var_os("HOME") {
-                PathBuf::from(home).join("Downloads")
-            } else {
-                return Err(rusqlite::Error::InvalidParameterName("Could not find Downloads directory".to_string()));
-            }
+        // Get the attendances based on filters, the records view filter takes precedence
+        let attendances = match &filter {
+            Some(query) => attendance_repo.get_all_matching_attendances(conn, query)?,
+            None => attendance_repo.get_filtered_attendances(conn, course.clone(), date)?,
         };
-        
-        // Ensure Downloads directory exists
-        if !downloads_dir.exists() {
-            return Err(rusqlite::Error::InvalidParameterName("Downloads directory does not exist".to_string()));
-        }
-            
-        let file_path = downloads_dir.join(filename);
+
+        let tz = institution_timezone(conn)?;
+        let file_path = downloads_dir.join(export_filename(tz, &course, date, filter.is_some(), "csv"));
 
         // Export to CSV
-        attendance_repo.export_attendances_to_csv(conn, file_path.clone(), attendances)
+        attendance_repo.export_attendances_to_csv(conn, file_path.clone(), attendances, &template)
+            .map_err(|e| match e {
+                AttendanceExportError::Sqlite(err) => err,
+                other => rusqlite::Error::InvalidParameterName(other.to_string()),
+            })?;
+
+        Ok(file_path.to_string_lossy().to_string())
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_attendances_to_xlsx(
+    state: State<'_, DbState>,
+    course: Option<String>,
+    date: Option<DateTime<Utc>>,
+    filter: Option<AttendanceQuery>,
+    grouping: Option<XlsxSheetGrouping>,
+    template_id: Option<Uuid>,
+) -> Result<String, String> {
+    let downloads_dir = get_downloads_dir()?;
+    let db = state.0.clone();
+    let attendance_repo = Arc::clone(&db.attendance_repository);
+
+    db.with_connection(move |conn| {
+        let template = resolve_template(conn, template_id, ExportTemplate::builtin_xlsx)?;
+
+        let attendances = match &filter {
+            Some(query) => attendance_repo.get_all_matching_attendances(conn, query)?,
+            None => attendance_repo.get_filtered_attendances(conn, course.clone(), date)?,
+        };
+
+        let tz = institution_timezone(conn)?;
+        let file_path = downloads_dir.join(export_filename(tz, &course, date, filter.is_some(), "xlsx"));
+
+        attendance_repo.export_attendances_to_xlsx(conn, file_path.clone(), attendances, &template, grouping.unwrap_or_default())
             .map_err(|e| match e {
-                AttendanceExportError::Csv(err) => rusqlite::Error::InvalidParameterName(format!("CSV Error: {}", err)),
                 AttendanceExportError::Sqlite(err) => err,
-                AttendanceExportError::Io(err) => rusqlite::Error::InvalidParameterName(format!("IO Error: {}", err)),
+                other => rusqlite::Error::InvalidParameterName(other.to_string()),
             })?;
 
         Ok(file_path.to_string_lossy().to_string())
     }).await.map_err(|e| e.to_string())
 }
 
+// Title and filter description printed at the top of the PDF report
+fn report_heading(
+    tz: Tz,
+    course: &Option<String>,
+    date: Option<DateTime<Utc>>,
+    filter: &Option<AttendanceQuery>,
+    location_names: &HashMap<Uuid, String>,
+    group_visit_labels: &HashMap<Uuid, String>,
+    event_names: &HashMap<Uuid, String>
+) -> (String, Option<String>) {
+    let local_day = |d: DateTime<Utc>| local_date(tz, d);
+    let mut parts = Vec::new();
+
+    let title = match filter {
+        Some(query) => {
+            if !query.courses.is_empty() {
+                parts.push(format!("Course: {}", query.courses.join(", ")));
+            }
+            if !query.classifications.is_empty() {
+                parts.push(format!("Classification: {}", query.classifications.join(", ")));
+            }
+            if !query.purposes.is_empty() {
+                parts.push(format!("Purpose: {}", query.purposes.join(", ")));
+            }
+            if !query.locations.is_empty() {
+                let names: Vec<String> = query.locations.iter()
+                    .filter_map(|id| location_names.get(id).cloned())
+                    .collect();
+                parts.push(format!("Location: {}", names.join(", ")));
+            }
+            if !query.devices.is_empty() {
+                parts.push(format!("Device: {}", query.devices.join(", ")));
+            }
+            if !query.group_visits.is_empty() {
+                let labels: Vec<String> = query.group_visits.iter()
+                    .filter_map(|id| group_visit_labels.get(id).cloned())
+                    .collect();
+                parts.push(format!("Group Visit: {}", labels.join(", ")));
+            }
+            if !query.events.is_empty() {
+                let names: Vec<String> = query.events.iter()
+                    .filter_map(|id| event_names.get(id).cloned())
+                    .collect();
+                parts.push(format!("Event: {}", names.join(", ")));
+            }
+
+            match (query.date_from.map(local_day), query.date_to.map(local_day)) {
+                (Some(from), Some(to)) if from == to => {
+                    parts.push(format!("Date: {}", from.format("%B %d, %Y")));
+                    "Daily Attendance Report"
+                },
+                (Some(from), Some(to)) if from.format("%Y%m").to_string() == to.format("%Y%m").to_string() => {
+                    parts.push(format!("Period: {} - {}", from.format("%B %d"), to.format("%d, %Y")));
+                    "Monthly Attendance Report"
+                },
+                (Some(from), Some(to)) => {
+                    parts.push(format!("Period: {} - {}", from.format("%B %d, %Y"), to.format("%B %d, %Y")));
+                    "Attendance Report"
+                },
+                (Some(from), None) => {
+                    parts.push(format!("From: {}", from.format("%B %d, %Y")));
+                    "Attendance Report"
+                },
+                (None, Some(to)) => {
+                    parts.push(format!("Until: {}", to.format("%B %d, %Y")));
+                    "Attendance Report"
+                },
+                (None, None) => "Attendance Report",
+            }
+        },
+        None => {
+            if let Some(c) = course {
+                parts.push(format!("Course: {}", c));
+            }
+            match date {
+                Some(d) => {
+                    parts.push(format!("Date: {}", local_day(d).format("%B %d, %Y")));
+                    "Daily Attendance Report"
+                },
+                None => "Attendance Report",
+            }
+        },
+    };
+
+    let subtitle = if parts.is_empty() { None } else { Some(parts.join("  |  ")) };
+    (title.to_string(), subtitle)
+}
+
+// Returns the saved path, or None when the save dialog was cancelled
+#[tauri::command]
+pub async fn generate_attendance_report_pdf(
+    app: AppHandle,
+    state: State<'_, DbState>,
+    course: Option<String>,
+    date: Option<DateTime<Utc>>,
+    filter: Option<AttendanceQuery>,
+) -> Result<Option<String>, String> {
+    let db = state.0.clone();
+    let attendance_repo = Arc::clone(&db.attendance_repository);
+    let integrity_repo = Arc::clone(&db.attendance_integrity_repository);
+    let location_repo = Arc::clone(&db.location_repository);
+    let group_visit_repo = Arc::clone(&db.group_visit_repository);
+    let event_repo = Arc::clone(&db.event_repository);
+
+    let query_filter = filter.clone();
+    let query_course = course.clone();
+    let (tz, attendances, digest, location_names, group_visit_labels, event_names) = db.with_connection(move |conn| {
+        let tz = institution_timezone(conn)?;
+        let attendances = match &query_filter {
+            Some(query) => attendance_repo.get_all_matching_attendances(conn, query)?,
+            None => attendance_repo.get_filtered_attendances(conn, query_course, date)?,
+        };
+        let digest = integrity_repo.create_export_digest(conn, &attendances, None, "pdf")?;
+        let location_names: HashMap<Uuid, String> = location_repo.get_all_locations(conn, true)?
+            .into_iter()
+            .map(|location| (location.id, location.name))
+            .collect();
+        let group_visit_labels: HashMap<Uuid, String> = group_visit_repo.get_group_visits(conn, None, None)?
+            .into_iter()
+            .map(|group| (group.id, group.label(tz)))
+            .collect();
+        let event_names: HashMap<Uuid, String> = event_repo.get_events(conn, true)?
+            .into_iter()
+            .map(|event| (event.id, event.name))
+            .collect();
+        Ok((tz, attendances, digest, location_names, group_visit_labels, event_names))
+    }).await.map_err(|e| e.to_string())?;
+    let default_name = export_filename(tz, &course, date, filter.is_some(), "pdf");
+    let (title, subtitle) = report_heading(tz, &course, date, &filter, &location_names, &group_visit_labels, &event_names);
+
+    let selected = app.dialog()
+        .file()
+        .set_title("Save Attendance Report")
+        .set_file_name(default_name)
+        .add_filter("PDF", &["pdf"])
+        .blocking_save_file();
+
+    let file_path = match selected {
+        Some(path) => path.into_path().map_err(|e| e.to_string())?,
+        None => return Ok(None),
+    };
+
+    let report = AttendanceReport {
+        title,
+        subtitle,
+        attendances,
+        location_names,
+        group_visit_labels,
+        event_names,
+        digest: Some(digest),
+        timezone: tz,
+    };
+    let output_path = file_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        write_attendance_report(&output_path, &report).map_err(|e| format!("PDF Error: {}", e))
+    }).await.map_err(|e| e.to_string())??;
+
+    Ok(Some(file_path.to_string_lossy().to_string()))
+}
+
 #[tauri::command]
 pub async fn get_filtered_attendances(
     state: State<'_, DbState>,
@@ -78,6 +295,18 @@ pub async fn get_filtered_attendances(
     }).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn query_attendances(
+    state: State<'_, DbState>,
+    query: AttendanceQuery
+) -> Result<PaginatedAttendances, String> {
+    let db = state.0.clone();
+    let attendance_repo = Arc::clone(&db.attendance_repository);
+    db.with_connection(move |conn| {
+        attendance_repo.query_attendances(conn, &query)
+    }).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_all_courses(
     state: State<'_, DbState>
@@ -90,6 +319,39 @@ pub async fn get_all_courses(
     }).await.map_err(|e| e.to_string())
 }
 
+// Backdated entry for one or more IDs, e.g. a missed scan or a class that visited as a group
+#[tauri::command]
+pub async fn record_manual_attendances(
+    state: State<'_, DbState>,
+    entry: ManualAttendanceRequest,
+    username: String,
+    password: String
+) -> Result<ManualAttendanceResult, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let attendance_repo = Arc::clone(&db.attendance_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            attendance_repo.record_manual_attendances(conn, entry, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_attendance_devices(
+    state: State<'_, DbState>
+) -> Result<Vec<String>, String> {
+    let db = state.0.clone();
+    let attendance_repo = Arc::clone(&db.attendance_repository);
+
+    db.with_connection(move |conn| {
+        attendance_repo.get_all_devices(conn)
+    }).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn create_attendance(
     state: State<'_, DbState>,
@@ -103,13 +365,53 @@ pub async fn create_attendance(
     
     db.with_connection(move |conn| {
         if auth.authenticate(conn, &username, &password)? {
-            attendance_repo.create_attendance(conn, attendance)
+            attendance_repo.create_attendance(conn, attendance, &username)
         } else {
             Err(rusqlite::Error::QueryReturnedNoRows)
         }
     }).await.map_err(|e| format!("Authentication failed: {}", e.to_string()))
 }
 
+#[tauri::command]
+pub async fn check_out_attendance(
+    state: State<'_, DbState>,
+    school_id: String,
+    username: String,
+    password: String
+) -> Result<Attendance, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let attendance_repo = Arc::clone(&db.attendance_repository);
+    
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            attendance_repo.check_out_attendance(conn, &school_id, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Check out failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn auto_close_open_attendances(
+    state: State<'_, DbState>,
+    username: String,
+    password: String
+) -> Result<usize, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+
+    let authenticated = db.with_connection(move |conn| {
+        auth.authenticate(conn, &username, &password)
+    }).await.map_err(|e| e.to_string())?;
+
+    if !authenticated {
+        return Err("Authentication failed".to_string());
+    }
+
+    run_auto_close(&db).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_all_attendances(
     state: State<'_, DbState>
@@ -122,6 +424,21 @@ pub async fn get_all_attendances(
     }).await.map_err(|e| e.to_string())
 }
 
+// Live records only; archived visits are not indexed
+#[tauri::command]
+pub async fn search_attendances(
+    state: State<'_, DbState>,
+    query: String,
+    limit: Option<usize>
+) -> Result<Vec<AttendanceSearchHit>, String> {
+    let db = state.0.clone();
+    let attendance_repo = Arc::clone(&db.attendance_repository);
+
+    db.with_connection(move |conn| {
+        attendance_repo.search_attendances(conn, &query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+    }).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_attendance(
     state: State<'_, DbState>,
@@ -149,7 +466,7 @@ pub async fn update_attendance(
     
     db.with_connection(move |conn| {
         if auth.authenticate(conn, &username, &password)? {
-            attendance_repo.update_attendance(conn, id, attendance)
+            attendance_repo.update_attendance(conn, id, attendance, &username)
         } else {
             Err(rusqlite::Error::QueryReturnedNoRows)
         }
@@ -169,13 +486,117 @@ pub async fn delete_attendance(
     
     db.with_connection(move |conn| {
         if auth.authenticate(conn, &username, &password)? {
-            attendance_repo.delete_attendance(conn, id)
+            attendance_repo.delete_attendance(conn, id, &username)
         } else {
             Err(rusqlite::Error::QueryReturnedNoRows)
         }
     }).await.map_err(|e| format!("Authentication failed: {}", e.to_string()))
 }
 
+#[tauri::command]
+pub async fn restore_attendance(
+    state: State<'_, DbState>,
+    id: Uuid,
+    username: String,
+    password: String
+) -> Result<Attendance, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let attendance_repo = Arc::clone(&db.attendance_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            attendance_repo.restore_attendance(conn, id, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_attendance_history(
+    state: State<'_, DbState>,
+    id: Uuid
+) -> Result<Vec<AttendanceAuditEntry>, String> {
+    let db = state.0.clone();
+    let audit_repo = Arc::clone(&db.attendance_audit_repository);
+
+    db.with_connection(move |conn| {
+        audit_repo.get_history(conn, id)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_recent_attendance_audit(
+    state: State<'_, DbState>,
+    limit: Option<usize>
+) -> Result<Vec<AttendanceAuditEntry>, String> {
+    let db = state.0.clone();
+    let audit_repo = Arc::clone(&db.attendance_audit_repository);
+
+    db.with_connection(move |conn| {
+        audit_repo.get_recent_entries(conn, limit.unwrap_or(100))
+    }).await.map_err(|e| e.to_string())
+}
+
+// Deleted records that can still be restored, newest deletion first
+#[tauri::command]
+pub async fn get_deleted_attendances(
+    state: State<'_, DbState>
+) -> Result<Vec<AttendanceAuditEntry>, String> {
+    let db = state.0.clone();
+    let audit_repo = Arc::clone(&db.attendance_audit_repository);
+
+    db.with_connection(move |conn| {
+        audit_repo.get_deleted_entries(conn)
+    }).await.map_err(|e| e.to_string())
+}
+
+// Checks every audit log link and every attendance row against the log
+#[tauri::command]
+pub async fn verify_attendance_chain(
+    state: State<'_, DbState>
+) -> Result<ChainVerificationReport, String> {
+    let db = state.0.clone();
+    let integrity_repo = Arc::clone(&db.attendance_integrity_repository);
+
+    db.with_connection(move |conn| {
+        integrity_repo.verify_chain(conn)
+    }).await.map_err(|e| e.to_string())
+}
+
+// For digests copied out of an XLSX Integrity sheet or a PDF report
+#[tauri::command]
+pub async fn verify_export_digest(
+    state: State<'_, DbState>,
+    digest: String
+) -> Result<ExportDigestVerification, String> {
+    let db = state.0.clone();
+    let integrity_repo = Arc::clone(&db.attendance_integrity_repository);
+
+    db.with_connection(move |conn| {
+        integrity_repo.verify_export_digest(conn, &digest, None)
+    }).await.map_err(|e| e.to_string())
+}
+
+// CSV exports carry their digest in a `.digest` file next to them, which also covers the file content
+#[tauri::command]
+pub async fn verify_export_file(
+    state: State<'_, DbState>,
+    path: String
+) -> Result<ExportDigestVerification, String> {
+    let bytes = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let digest_path = csv_digest_path(Path::new(&path));
+    let digest = std::fs::read_to_string(&digest_path)
+        .map_err(|e| format!("Failed to read integrity digest {}: {}", digest_path.display(), e))?;
+    let db = state.0.clone();
+    let integrity_repo = Arc::clone(&db.attendance_integrity_repository);
+
+    db.with_connection(move |conn| {
+        integrity_repo.verify_export_digest(conn, &digest, Some(&bytes))
+    }).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_attendances_by_semester(
     state: State<'_, DbState>,
@@ -200,4 +621,115 @@ pub async fn get_attendances_by_school_account(
     db.with_connection(move |conn| {
         attendance_repo.get_attendances_by_school_account(conn, school_account_id)
     }).await.map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+#[tauri::command]
+pub async fn get_export_job_history(
+    state: State<'_, DbState>,
+    limit: Option<usize>
+) -> Result<Vec<ExportJob>, String> {
+    let db = state.0.clone();
+    let export_job_repo = Arc::clone(&db.export_job_repository);
+
+    db.with_connection(move |conn| {
+        export_job_repo.get_recent_jobs(conn, limit.unwrap_or(100))
+    }).await.map_err(|e| e.to_string())
+}
+
+// Runs the export covering `date` (the institution's today when omitted) outside the schedule
+#[tauri::command]
+pub async fn run_attendance_export(
+    state: State<'_, DbState>,
+    job_type: ExportJobType,
+    date: Option<NaiveDate>,
+    username: String,
+    password: String
+) -> Result<ExportJob, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            let date = match date {
+                Some(date) => date,
+                None => local_date(institution_timezone(conn)?, Utc::now()),
+            };
+            run_export_now(conn, job_type, date, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_export_templates(
+    state: State<'_, DbState>
+) -> Result<Vec<ExportTemplate>, String> {
+    let db = state.0.clone();
+    let template_repo = Arc::clone(&db.export_template_repository);
+
+    db.with_connection(move |conn| {
+        template_repo.get_all_templates(conn)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_export_template(
+    state: State<'_, DbState>,
+    template: CreateExportTemplateRequest,
+    username: String,
+    password: String
+) -> Result<ExportTemplate, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let template_repo = Arc::clone(&db.export_template_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            template_repo.create_template(conn, template)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_export_template(
+    state: State<'_, DbState>,
+    id: Uuid,
+    template: CreateExportTemplateRequest,
+    username: String,
+    password: String
+) -> Result<ExportTemplate, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let template_repo = Arc::clone(&db.export_template_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            template_repo.update_template(conn, id, template)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_export_template(
+    state: State<'_, DbState>,
+    id: Uuid,
+    username: String,
+    password: String
+) -> Result<(), String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let template_repo = Arc::clone(&db.export_template_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            template_repo.delete_template(conn, id)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
@@ -0,0 +1,317 @@
+// src/attendance_export_scheduler.rs
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
+use log::{info, error, warn};
+use rusqlite::Connection;
+use uuid::Uuid;
+
+use crate::db::Database;
+use crate::db::app_settings::{
+    AppSettingsDatabase,
+    EXPORT_SCHEDULE_ENABLED,
+    EXPORT_DAILY_TIME,
+    EXPORT_FOLDER,
+    EXPORT_KEEP_DAILY,
+    EXPORT_KEEP_WEEKLY,
+    EXPORT_KEEP_MONTHLY,
+    EXPORT_TEMPLATE_ID,
+};
+use crate::db::attendance::{AttendanceQuery, AttendanceRepository, SqliteAttendanceRepository};
+use crate::db::attendance_audit::ACTOR_SYSTEM;
+use crate::db::attendance_integrity::csv_digest_path;
+use crate::db::institution_time::{institution_timezone, start_of_day};
+use crate::db::export_templates::{resolve_template, ExportTemplate};
+use crate::db::export_jobs::{
+    ExportJob,
+    ExportJobRepository,
+    ExportJobStatus,
+    ExportJobType,
+    SqliteExportJobRepository,
+};
+use crate::storage::get_downloads_dir;
+use crate::xlsx_export::XlsxSheetGrouping;
+
+// How often the scheduler checks for exports that are due
+const EXPORT_SCHEDULER_INTERVAL: Duration = Duration::from_secs(60);
+// Days missed while the app was closed are exported on the next start, up to this many
+const CATCH_UP_DAYS: u64 = 7;
+// A failed export is retried on a later tick, but not more often than this
+const RETRY_AFTER_MINUTES: i64 = 60;
+
+pub fn default_daily_export_time() -> NaiveTime {
+    NaiveTime::from_hms_opt(20, 30, 0).unwrap()
+}
+
+// The institution's calendar days one export covers
+#[derive(Debug, Clone, Copy)]
+struct ExportPeriod {
+    job_type: ExportJobType,
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+impl ExportPeriod {
+    // Snaps any date to the day, ISO week or month containing it
+    fn containing(job_type: ExportJobType, date: NaiveDate) -> Self {
+        let (start, end) = match job_type {
+            ExportJobType::Daily => (date, date),
+            ExportJobType::Weekly => {
+                let start = date - Days::new(date.weekday().num_days_from_monday() as u64);
+                (start, start + Days::new(6))
+            },
+            ExportJobType::Monthly => {
+                let start = date.with_day(1).unwrap();
+                (start, start + Months::new(1) - Days::new(1))
+            },
+        };
+
+        ExportPeriod { job_type, start, end }
+    }
+
+    fn sub_folder(&self) -> &'static str {
+        match self.job_type {
+            ExportJobType::Daily => "daily",
+            ExportJobType::Weekly => "weekly",
+            ExportJobType::Monthly => "monthly",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self.job_type {
+            ExportJobType::Daily => "csv",
+            _ => "xlsx",
+        }
+    }
+
+    // Names sort chronologically, which rotation relies on
+    fn file_name(&self) -> String {
+        let label = match self.job_type {
+            ExportJobType::Daily => self.start.format("%Y-%m-%d").to_string(),
+            ExportJobType::Weekly => self.start.format("%G-W%V").to_string(),
+            ExportJobType::Monthly => self.start.format("%Y-%m").to_string(),
+        };
+        format!("attendance_{}.{}", label, self.extension())
+    }
+
+    // Days are the institution's calendar days
+    fn query(&self, tz: Tz) -> AttendanceQuery {
+        let next_day = self.end + Days::new(1);
+        AttendanceQuery {
+            date_from: Some(start_of_day(tz, self.start)),
+            date_to: Some(start_of_day(tz, next_day) - chrono::Duration::nanoseconds(1)),
+            ..Default::default()
+        }
+    }
+}
+
+fn export_folder(conn: &Connection) -> Result<PathBuf, String> {
+    let folder = AppSettingsDatabase.get_string(conn, EXPORT_FOLDER, "").map_err(|e| e.to_string())?;
+    if folder.is_empty() {
+        Ok(get_downloads_dir()?.join("Attendance Exports"))
+    } else {
+        Ok(PathBuf::from(folder))
+    }
+}
+
+// Removes the oldest exports of one kind so only `keep` remain
+fn rotate_exports(dir: &Path, extension: &str, keep: usize) -> std::io::Result<()> {
+    if keep == 0 {
+        return Ok(());
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.extension().is_some_and(|ext| ext == extension)
+                && path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("attendance_"))
+        })
+        .collect();
+
+    files.sort();
+    let excess = files.len().saturating_sub(keep);
+    for path in files.into_iter().take(excess) {
+        info!("Removing rotated export {}", path.display());
+        let digest_path = csv_digest_path(&path);
+        if digest_path.exists() {
+            fs::remove_file(digest_path)?;
+        }
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn write_export(conn: &Connection, period: ExportPeriod) -> Result<(PathBuf, u64), String> {
+    let settings = AppSettingsDatabase;
+    let repo = SqliteAttendanceRepository;
+
+    let tz = institution_timezone(conn).map_err(|e| e.to_string())?;
+    let attendances = repo.get_all_matching_attendances(conn, &period.query(tz)).map_err(|e| e.to_string())?;
+    let record_count = attendances.len() as u64;
+
+    let dir = export_folder(conn)?.join(period.sub_folder());
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let file_path = dir.join(period.file_name());
+
+    // A template that was deleted or mistyped falls back to the built-in layout rather than failing
+    let template_id = settings.get_string(conn, EXPORT_TEMPLATE_ID, "").map_err(|e| e.to_string())?;
+    let template_id = Uuid::parse_str(&template_id).ok();
+
+    match period.job_type {
+        ExportJobType::Daily => {
+            let template = resolve_template(conn, template_id, ExportTemplate::builtin_csv)
+                .or_else(|_| resolve_template(conn, None, ExportTemplate::builtin_csv))
+                .map_err(|e| e.to_string())?;
+            repo.export_attendances_to_csv(conn, file_path.clone(), attendances, &template)
+        },
+        // Rollups get one sheet per day
+        _ => {
+            let template = resolve_template(conn, template_id, ExportTemplate::builtin_xlsx)
+                .or_else(|_| resolve_template(conn, None, ExportTemplate::builtin_xlsx))
+                .map_err(|e| e.to_string())?;
+            repo.export_attendances_to_xlsx(conn, file_path.clone(), attendances, &template, XlsxSheetGrouping::PerDay)
+        },
+    }.map_err(|e| e.to_string())?;
+
+    let (keep_key, keep_default) = match period.job_type {
+        ExportJobType::Daily => (EXPORT_KEEP_DAILY, 60),
+        ExportJobType::Weekly => (EXPORT_KEEP_WEEKLY, 26),
+        ExportJobType::Monthly => (EXPORT_KEEP_MONTHLY, 24),
+    };
+    let keep = settings.get_i64(conn, keep_key, keep_default).map_err(|e| e.to_string())?.max(0) as usize;
+    if let Err(e) = rotate_exports(&dir, period.extension(), keep) {
+        // The export itself succeeded, so a rotation problem is only logged
+        warn!("Failed to rotate exports in {}: {}", dir.display(), e);
+    }
+
+    Ok((file_path, record_count))
+}
+
+// Runs one export and records it in the job history, whether it worked or not
+fn run_export(conn: &Connection, period: ExportPeriod, triggered_by: &str) -> rusqlite::Result<ExportJob> {
+    let started_at = Utc::now();
+    let result = write_export(conn, period);
+
+    let job = ExportJob {
+        id: Uuid::new_v4(),
+        job_type: period.job_type,
+        period_start: period.start,
+        period_end: period.end,
+        started_at,
+        finished_at: Utc::now(),
+        status: if result.is_ok() { ExportJobStatus::Succeeded } else { ExportJobStatus::Failed },
+        file_path: result.as_ref().ok().map(|(path, _)| path.to_string_lossy().to_string()),
+        record_count: result.as_ref().ok().map(|(_, count)| *count),
+        error: result.err(),
+        triggered_by: triggered_by.to_string(),
+    };
+
+    SqliteExportJobRepository.record_job(conn, &job)?;
+    Ok(job)
+}
+
+// Only the scheduler's own runs count: a manual run may have exported a period that was still open
+fn is_due(conn: &Connection, period: ExportPeriod, now: DateTime<Utc>) -> rusqlite::Result<bool> {
+    Ok(match SqliteExportJobRepository.get_last_job(conn, period.job_type, period.start, ACTOR_SYSTEM)? {
+        None => true,
+        Some(job) if job.status == ExportJobStatus::Failed => {
+            job.finished_at + chrono::Duration::minutes(RETRY_AFTER_MINUTES) <= now
+        },
+        Some(_) => false,
+    })
+}
+
+// Days since the last successful daily export (at most CATCH_UP_DAYS), then the last full week and month.
+// `now` is on the institution's clock
+fn due_periods(conn: &Connection, now: DateTime<Tz>, daily_time: NaiveTime) -> rusqlite::Result<Vec<ExportPeriod>> {
+    let today = now.date_naive();
+    let mut periods = Vec::new();
+
+    let last_day = if now.time() >= daily_time { Some(today) } else { today.pred_opt() };
+    if let Some(last_day) = last_day {
+        let earliest = last_day - Days::new(CATCH_UP_DAYS - 1);
+        let mut day = SqliteExportJobRepository.get_last_succeeded_period(conn, ExportJobType::Daily, ACTOR_SYSTEM)?
+            .and_then(|d| d.succ_opt())
+            .unwrap_or(last_day)
+            .max(earliest);
+
+        while day <= last_day {
+            periods.push(ExportPeriod::containing(ExportJobType::Daily, day));
+            day = day + Days::new(1);
+        }
+    }
+
+    let this_week = ExportPeriod::containing(ExportJobType::Weekly, today);
+    periods.push(ExportPeriod::containing(ExportJobType::Weekly, this_week.start - Days::new(1)));
+
+    let this_month = ExportPeriod::containing(ExportJobType::Monthly, today);
+    periods.push(ExportPeriod::containing(ExportJobType::Monthly, this_month.start - Days::new(1)));
+
+    let now_utc = now.with_timezone(&Utc);
+    let mut due = Vec::new();
+    for period in periods {
+        if is_due(conn, period, now_utc)? {
+            due.push(period);
+        }
+    }
+    Ok(due)
+}
+
+// Exports the day, week or month containing `date` right away, e.g. to redo a failed run
+pub fn run_export_now(
+    conn: &Connection,
+    job_type: ExportJobType,
+    date: NaiveDate,
+    triggered_by: &str
+) -> rusqlite::Result<ExportJob> {
+    run_export(conn, ExportPeriod::containing(job_type, date), triggered_by)
+}
+
+pub async fn run_due_exports(db: &Database) -> Result<Vec<ExportJob>, Box<dyn std::error::Error>> {
+    let app_settings = db.app_settings.clone();
+
+    db.with_connection(move |conn| {
+        if !app_settings.get_bool(conn, EXPORT_SCHEDULE_ENABLED, true)? {
+            return Ok(Vec::new());
+        }
+
+        let daily_time = app_settings.get_time(conn, EXPORT_DAILY_TIME, default_daily_export_time())?;
+        let now = Utc::now().with_timezone(&institution_timezone(conn)?);
+        let mut jobs = Vec::new();
+        for period in due_periods(conn, now, daily_time)? {
+            jobs.push(run_export(conn, period, ACTOR_SYSTEM)?);
+        }
+        Ok(jobs)
+    }).await
+}
+
+pub async fn start_export_scheduler_task(db: Database) {
+    loop {
+        match run_due_exports(&db).await {
+            Ok(jobs) => {
+                for job in jobs {
+                    match job.status {
+                        ExportJobStatus::Succeeded => info!(
+                            "{} attendance export for {} written to {}",
+                            job.job_type.as_str(),
+                            job.period_start,
+                            job.file_path.unwrap_or_default()
+                        ),
+                        ExportJobStatus::Failed => error!(
+                            "{} attendance export for {} failed: {}",
+                            job.job_type.as_str(),
+                            job.period_start,
+                            job.error.unwrap_or_default()
+                        ),
+                    }
+                }
+            },
+            Err(e) => error!("Failed to run scheduled attendance exports: {}", e),
+        }
+
+        tokio::time::sleep(EXPORT_SCHEDULER_INTERVAL).await;
+    }
+}
@@ -0,0 +1,32 @@
+// src/attendance_retention.rs
+
+use std::time::Duration;
+use chrono::Utc;
+use log::{info, error};
+use crate::db::Database;
+use crate::db::attendance_archive::{run_retention, ArchiveRunResult};
+
+// Retention is measured in semesters and years, so once a day is plenty
+const RETENTION_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+// Moves attendance past the retention settings into the archive database
+pub async fn run_attendance_retention(db: &Database) -> Result<ArchiveRunResult, String> {
+    let db_path = db.get_db_path().clone();
+
+    tauri::async_runtime::spawn_blocking(move || run_retention(&db_path, Utc::now()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+pub async fn start_retention_task(db: Database) {
+    loop {
+        match run_attendance_retention(&db).await {
+            Ok(ArchiveRunResult { archived: 0, .. }) => {},
+            Ok(result) => info!("Archived {} attendance records ({} left for the next run)", result.archived, result.skipped),
+            Err(e) => error!("Failed to archive attendance: {}", e),
+        }
+
+        tokio::time::sleep(RETENTION_INTERVAL).await;
+    }
+}
@@ -0,0 +1,156 @@
+// src/clearance_commands.rs
+
+use tauri::State;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::DbState;
+use crate::db::clearance::{
+    clearance_semester,
+    Clearance,
+    ClearanceSignOffResult,
+    ClearanceStatus,
+    CourseClearanceSummary,
+    PlaceHoldRequest,
+};
+use crate::db::institution_time::institution_timezone;
+use crate::storage::get_downloads_dir;
+use crate::xlsx_export::{clearance_sheets, write_workbook};
+
+// Every command takes an optional semester_id and falls back to the active semester
+
+#[tauri::command]
+pub async fn get_clearance(
+    state: State<'_, DbState>,
+    school_id: String,
+    semester_id: Option<Uuid>
+) -> Result<Clearance, String> {
+    let db = state.0.clone();
+    let clearance_repo = Arc::clone(&db.clearance_repository);
+
+    db.with_connection(move |conn| {
+        let semester_id = clearance_semester(conn, semester_id)?;
+        clearance_repo.get_clearance(conn, &school_id, semester_id)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_clearances(
+    state: State<'_, DbState>,
+    semester_id: Option<Uuid>,
+    course: Option<String>,
+    status: Option<ClearanceStatus>
+) -> Result<Vec<Clearance>, String> {
+    let db = state.0.clone();
+    let clearance_repo = Arc::clone(&db.clearance_repository);
+
+    db.with_connection(move |conn| {
+        let semester_id = clearance_semester(conn, semester_id)?;
+        clearance_repo.get_clearances(conn, semester_id, course.as_deref(), status)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_clearance_report(
+    state: State<'_, DbState>,
+    semester_id: Option<Uuid>
+) -> Result<Vec<CourseClearanceSummary>, String> {
+    let db = state.0.clone();
+    let clearance_repo = Arc::clone(&db.clearance_repository);
+
+    db.with_connection(move |conn| {
+        let semester_id = clearance_semester(conn, semester_id)?;
+        clearance_repo.get_course_report(conn, semester_id)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_clearance_report_to_xlsx(
+    state: State<'_, DbState>,
+    semester_id: Option<Uuid>
+) -> Result<String, String> {
+    let downloads_dir = get_downloads_dir()?;
+    let db = state.0.clone();
+    let clearance_repo = Arc::clone(&db.clearance_repository);
+
+    let (tz, summaries, clearances) = db.with_connection(move |conn| {
+        let semester_id = clearance_semester(conn, semester_id)?;
+        Ok((
+            institution_timezone(conn)?,
+            clearance_repo.get_course_report(conn, semester_id)?,
+            clearance_repo.get_clearances(conn, semester_id, None, None)?,
+        ))
+    }).await.map_err(|e| e.to_string())?;
+
+    let timestamp = chrono::Utc::now().with_timezone(&tz).format("%Y%m%d_%H%M%S");
+    let file_path = downloads_dir.join(format!("clearance_report_{}.xlsx", timestamp));
+
+    let sheets = clearance_sheets(tz, summaries, clearances);
+    write_workbook(&file_path, &sheets).map_err(|e| format!("XLSX Error: {}", e))?;
+
+    Ok(file_path.to_string_lossy().to_string())
+}
+
+#[tauri::command]
+pub async fn place_clearance_hold(
+    state: State<'_, DbState>,
+    hold: PlaceHoldRequest,
+    semester_id: Option<Uuid>,
+    username: String,
+    password: String
+) -> Result<Clearance, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let clearance_repo = Arc::clone(&db.clearance_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            let semester_id = clearance_semester(conn, semester_id)?;
+            clearance_repo.place_hold(conn, semester_id, hold, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn resolve_clearance_hold(
+    state: State<'_, DbState>,
+    hold_id: Uuid,
+    username: String,
+    password: String
+) -> Result<Clearance, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let clearance_repo = Arc::clone(&db.clearance_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            clearance_repo.resolve_hold(conn, hold_id, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+// Students still on hold are returned in `skipped` with their hold reasons
+#[tauri::command]
+pub async fn sign_off_clearances(
+    state: State<'_, DbState>,
+    school_ids: Vec<String>,
+    semester_id: Option<Uuid>,
+    username: String,
+    password: String
+) -> Result<ClearanceSignOffResult, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let clearance_repo = Arc::clone(&db.clearance_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            let semester_id = clearance_semester(conn, semester_id)?;
+            clearance_repo.sign_off_clearances(conn, semester_id, school_ids, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
@@ -7,7 +7,8 @@ use crate::db::csv_import::CsvValidationResult;
 use crate::db::csv_transform::{CsvTransformer, batch_transform_records};
 use crate::db::school_accounts::SchoolAccount;
 use crate::redis_csv_processor::RedisCsvProcessor;
-use crate::db::csv_import::ValidationErrorType;
+use crate::db::csv_import::{ValidationError, ValidationErrorType};
+use crate::db::attendance_csv_import::{AttendanceCsvImporter, AttendanceCsvValidationResult, AttendanceCsvImportResult};
 use crate::logger::{emit_log, LogMessage};
 use std::sync::Arc;
 use csv::StringRecord;
@@ -483,3 +484,75 @@ pub async fn import_csv_file_parallel(
     
     Ok(import_response)
 }
+
+// Validation and preview of a paper logbook CSV; nothing is written
+#[command]
+pub async fn validate_attendance_csv(
+    state: State<'_, DbState>,
+    file_path: String
+) -> Result<AttendanceCsvValidationResult, Vec<ValidationErrorDetails>> {
+    let path = Path::new(&file_path);
+    let importer = AttendanceCsvImporter;
+
+    let to_details = |errors: Vec<ValidationError>| -> Vec<ValidationErrorDetails> {
+        errors.into_iter()
+            .map(|error| ValidationErrorDetails {
+                row_number: error.row_number,
+                field: error.field,
+                error_type: error.error_type,
+                error_message: error.error_message,
+            })
+            .collect()
+    };
+
+    let (headers, records) = importer.read_file(path).map_err(to_details)?;
+    let file_name = path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    state.0.with_connection(move |conn| {
+        importer.validate_records(conn, file_name, &headers, &records)
+    }).await.map_err(|e| vec![ValidationErrorDetails {
+        row_number: 0,
+        field: None,
+        error_type: ValidationErrorType::DataIntegrity,
+        error_message: format!("Database error: {}", e),
+    }])
+}
+
+// Re-validates the file and inserts the rows marked New, skipping duplicates and invalid rows
+#[command]
+pub async fn import_attendance_csv(
+    state: State<'_, DbState>,
+    file_path: String,
+    username: String,
+    password: String
+) -> Result<AttendanceCsvImportResult, String> {
+    let path = Path::new(&file_path);
+    let importer = AttendanceCsvImporter;
+
+    let (headers, records) = importer.read_file(path)
+        .map_err(|errors| format!("Validation failed: {:?}", errors))?;
+    let file_name = path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    let auth = state.0.auth.clone();
+    let result = state.0.with_connection(move |conn| {
+        if !auth.authenticate(conn, &username, &password)? {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+
+        let validation_result = importer.validate_records(conn, file_name, &headers, &records)?;
+        importer.import_rows(conn, validation_result, &username)
+    }).await.map_err(|e| format!("Import failed: {}", e))?;
+
+    info!(
+        "Attendance backfill: {} imported, {} duplicates skipped, {} invalid rows skipped",
+        result.imported, result.skipped_duplicates, result.skipped_invalid
+    );
+
+    Ok(result)
+}
@@ -21,6 +21,23 @@ pub mod attendance;
 pub mod purpose;
 pub mod settings_styles;
 pub mod classification;
+pub mod app_settings;
+pub mod attendance_analytics;
+pub mod attendance_csv_import;
+pub mod attendance_audit;
+pub mod visitors;
+pub mod account_status;
+pub mod export_jobs;
+pub mod export_templates;
+pub mod attendance_integrity;
+pub mod locations;
+pub mod group_visits;
+pub mod events;
+pub mod clearance;
+pub mod occupancy;
+pub mod attendance_archive;
+pub mod search;
+pub mod institution_time;
 
 use notes::NotesDatabase;
 use auth::AuthDatabase;
@@ -30,6 +47,19 @@ use attendance::{AttendanceRepository, SqliteAttendanceRepository};
 use purpose::{PurposeRepository, SqlitePurposeRepository};
 use settings_styles::SettingsStylesDatabase;
 use classification::{ClassificationRepository, SqliteClassificationRepository};
+use app_settings::AppSettingsDatabase;
+use attendance_analytics::{AttendanceAnalyticsRepository, SqliteAttendanceAnalyticsRepository};
+use attendance_audit::{AttendanceAuditRepository, SqliteAttendanceAuditRepository};
+use visitors::{VisitorRepository, SqliteVisitorRepository};
+use account_status::{AccountStatusRepository, SqliteAccountStatusRepository};
+use export_jobs::{ExportJobRepository, SqliteExportJobRepository};
+use export_templates::{ExportTemplateRepository, SqliteExportTemplateRepository};
+use attendance_integrity::{AttendanceIntegrityRepository, SqliteAttendanceIntegrityRepository};
+use locations::{LocationRepository, SqliteLocationRepository};
+use group_visits::{GroupVisitRepository, SqliteGroupVisitRepository};
+use events::{EventRepository, SqliteEventRepository};
+use clearance::{ClearanceRepository, SqliteClearanceRepository};
+use occupancy::{OccupancyRepository, SqliteOccupancyRepository};
 use std::sync::Arc;
 use crate::parallel_csv_validator::ParallelCsvValidator;
 
@@ -49,6 +79,19 @@ pub struct Database {
     pub purpose_repository: Arc<dyn PurposeRepository + Send + Sync>,
     pub settings_styles: SettingsStylesDatabase,
     pub classification_repository: Arc<dyn ClassificationRepository + Send + Sync>, 
+    pub app_settings: AppSettingsDatabase,
+    pub attendance_analytics_repository: Arc<dyn AttendanceAnalyticsRepository + Send + Sync>,
+    pub attendance_audit_repository: Arc<dyn AttendanceAuditRepository + Send + Sync>,
+    pub visitor_repository: Arc<dyn VisitorRepository + Send + Sync>,
+    pub account_status_repository: Arc<dyn AccountStatusRepository + Send + Sync>,
+    pub export_job_repository: Arc<dyn ExportJobRepository + Send + Sync>,
+    pub export_template_repository: Arc<dyn ExportTemplateRepository + Send + Sync>,
+    pub attendance_integrity_repository: Arc<dyn AttendanceIntegrityRepository + Send + Sync>,
+    pub location_repository: Arc<dyn LocationRepository + Send + Sync>,
+    pub group_visit_repository: Arc<dyn GroupVisitRepository + Send + Sync>,
+    pub event_repository: Arc<dyn EventRepository + Send + Sync>,
+    pub clearance_repository: Arc<dyn ClearanceRepository + Send + Sync>,
+    pub occupancy_repository: Arc<dyn OccupancyRepository + Send + Sync>,
     db_path: PathBuf,
 }
 
@@ -67,6 +110,19 @@ impl Clone for Database {
             purpose_repository: Arc::new(SqlitePurposeRepository),
             settings_styles: self.settings_styles.clone(),
             classification_repository: Arc::new(SqliteClassificationRepository),
+            app_settings: self.app_settings.clone(),
+            attendance_analytics_repository: Arc::new(SqliteAttendanceAnalyticsRepository),
+            attendance_audit_repository: Arc::new(SqliteAttendanceAuditRepository),
+            visitor_repository: Arc::new(SqliteVisitorRepository),
+            account_status_repository: Arc::new(SqliteAccountStatusRepository),
+            export_job_repository: Arc::new(SqliteExportJobRepository),
+            export_template_repository: Arc::new(SqliteExportTemplateRepository),
+            attendance_integrity_repository: Arc::new(SqliteAttendanceIntegrityRepository),
+            location_repository: Arc::new(SqliteLocationRepository),
+            group_visit_repository: Arc::new(SqliteGroupVisitRepository),
+            event_repository: Arc::new(SqliteEventRepository),
+            clearance_repository: Arc::new(SqliteClearanceRepository),
+            occupancy_repository: Arc::new(SqliteOccupancyRepository),
             db_path: self.db_path.clone(),
         }
     }
@@ -149,14 +205,33 @@ impl Database {
         // Initialize all tables
         info!("Creating database tables...");
         school_accounts::create_school_accounts_table(&conn)?;
+        account_status::create_account_restrictions_table(&conn)?;
         semester::create_semesters_table(&conn)?;
         purpose::create_purposes_table(&conn)?;
+        visitors::create_visitors_table(&conn)?;
+        locations::create_locations_tables(&conn)?;
         attendance::create_attendance_table(&conn)?;
+        group_visits::create_group_visits_table(&conn)?;
+        events::create_events_tables(&conn)?;
+        clearance::create_clearance_tables(&conn)?;
+        occupancy::create_occupancy_history_table(&conn)?;
+        attendance_audit::create_attendance_audit_table(&conn)?;
         classification::create_classifications_table(&conn)?; 
+        export_jobs::create_export_jobs_table(&conn)?;
+        export_templates::create_export_templates_table(&conn)?;
+        attendance_integrity::create_attendance_integrity_tables(&conn)?;
+        search::create_search_tables(&conn)?;
+
+        // These write through the audit log, so they run once it exists
+        attendance::backfill_attendance_semesters(&conn)?;
+        attendance::backfill_attendance_locations(&conn)?;
+        attendance_integrity::seal_unchained_attendances(&conn)?;
+        attendance_archive::create_archive_database(&conn)?;
         
         let notes_db = NotesDatabase::init(&conn)?;
         let auth_db = AuthDatabase::init(&conn)?;
         let settings_styles_db = SettingsStylesDatabase::init(&conn)?;
+        let app_settings_db = AppSettingsDatabase::init(&conn)?;
         
         info!("Database initialization completed successfully");
         Ok(Database {
@@ -169,6 +244,19 @@ impl Database {
             purpose_repository: Arc::new(SqlitePurposeRepository),
             classification_repository: Arc::new(SqliteClassificationRepository),
             settings_styles: settings_styles_db,
+            app_settings: app_settings_db,
+            attendance_analytics_repository: Arc::new(SqliteAttendanceAnalyticsRepository),
+            attendance_audit_repository: Arc::new(SqliteAttendanceAuditRepository),
+            visitor_repository: Arc::new(SqliteVisitorRepository),
+            account_status_repository: Arc::new(SqliteAccountStatusRepository),
+            export_job_repository: Arc::new(SqliteExportJobRepository),
+            export_template_repository: Arc::new(SqliteExportTemplateRepository),
+            attendance_integrity_repository: Arc::new(SqliteAttendanceIntegrityRepository),
+            location_repository: Arc::new(SqliteLocationRepository),
+            group_visit_repository: Arc::new(SqliteGroupVisitRepository),
+            event_repository: Arc::new(SqliteEventRepository),
+            clearance_repository: Arc::new(SqliteClearanceRepository),
+            occupancy_repository: Arc::new(SqliteOccupancyRepository),
             db_path,
         })
     }
@@ -184,6 +272,23 @@ impl Database {
     }
 }
 
+// Adds a column to an existing table, used when a newer schema meets an older database file
+pub fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<String>>>()?;
+
+    if !columns.iter().any(|name| name == column) {
+        info!("Adding column {}.{}", table, column);
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+
+    Ok(())
+}
+
 fn get_database_path(db_dir: &PathBuf) -> Result<PathBuf, String> {
     let db_name = config::load_database_name()
         .map_err(|e| format!("Failed to load database name: {}", e))?;
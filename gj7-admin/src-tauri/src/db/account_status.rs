@@ -0,0 +1,301 @@
+// src/db/account_status.rs
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Serialize, Deserialize};
+
+use crate::db::app_settings::{
+    AppSettingsDatabase,
+    ATTENDANCE_POLICY_INACTIVE,
+    ATTENDANCE_POLICY_SUSPENDED,
+    ATTENDANCE_POLICY_BLOCKED,
+};
+use crate::db::institution_time::institution_timezone;
+
+// Effective status of a school account at check-in. Inactive comes from `is_active`, which the
+// CSV imports maintain; Suspended and Blocked are restrictions an admin places on top of it
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AccountStatus {
+    Active,
+    Inactive,
+    Suspended,
+    Blocked,
+}
+
+impl AccountStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AccountStatus::Active => "Active",
+            AccountStatus::Inactive => "Inactive",
+            AccountStatus::Suspended => "Suspended",
+            AccountStatus::Blocked => "Blocked",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "Active" => Some(AccountStatus::Active),
+            "Inactive" => Some(AccountStatus::Inactive),
+            "Suspended" => Some(AccountStatus::Suspended),
+            "Blocked" => Some(AccountStatus::Blocked),
+            _ => None,
+        }
+    }
+}
+
+// What the kiosk does when an account in a given status scans in
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum KioskPolicy {
+    Allow,
+    Warn,
+    Refuse,
+}
+
+impl KioskPolicy {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "allow" => Some(KioskPolicy::Allow),
+            "warn" => Some(KioskPolicy::Warn),
+            "refuse" => Some(KioskPolicy::Refuse),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountStatusInfo {
+    pub school_id: String,
+    pub status: AccountStatus,
+    pub reason: Option<String>,
+    // Suspensions lapse on their own after this time
+    pub until: Option<DateTime<Utc>>,
+    pub policy: KioskPolicy,
+    // Text for the kiosk to show when the policy is Warn or Refuse
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AccountRestriction {
+    pub school_id: String,
+    pub status: AccountStatus,
+    pub reason: Option<String>,
+    pub until: Option<DateTime<Utc>>,
+    pub set_by: String,
+    pub set_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SetAccountRestrictionRequest {
+    // Only Suspended and Blocked can be set by hand
+    pub status: AccountStatus,
+    pub reason: Option<String>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+fn parse_datetime(idx: usize, value: Option<String>) -> Result<Option<DateTime<Utc>>> {
+    value.map(|v| DateTime::parse_from_rfc3339(&v)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))))
+        .transpose()
+}
+
+fn row_to_restriction(row: &Row) -> Result<AccountRestriction> {
+    let status: String = row.get(1)?;
+
+    Ok(AccountRestriction {
+        school_id: row.get(0)?,
+        status: AccountStatus::from_str(&status).ok_or_else(|| {
+            rusqlite::Error::InvalidColumnType(1, status.clone(), rusqlite::types::Type::Text)
+        })?,
+        reason: row.get(2)?,
+        until: parse_datetime(3, row.get(3)?)?,
+        set_by: row.get(4)?,
+        set_at: parse_datetime(5, row.get(5)?)?.unwrap_or_else(Utc::now),
+    })
+}
+
+fn policy_for(conn: &Connection, status: AccountStatus) -> Result<KioskPolicy> {
+    let (key, default) = match status {
+        AccountStatus::Active => return Ok(KioskPolicy::Allow),
+        AccountStatus::Inactive => (ATTENDANCE_POLICY_INACTIVE, KioskPolicy::Warn),
+        AccountStatus::Suspended => (ATTENDANCE_POLICY_SUSPENDED, KioskPolicy::Refuse),
+        AccountStatus::Blocked => (ATTENDANCE_POLICY_BLOCKED, KioskPolicy::Refuse),
+    };
+
+    Ok(AppSettingsDatabase.get_setting(conn, key)?
+        .and_then(|s| KioskPolicy::parse(&s.value))
+        .unwrap_or(default))
+}
+
+fn status_message(tz: Tz, status: AccountStatus, reason: Option<&str>, until: Option<DateTime<Utc>>) -> Option<String> {
+    let mut message = match status {
+        AccountStatus::Active => return None,
+        AccountStatus::Inactive => "Account is inactive".to_string(),
+        AccountStatus::Suspended => match until {
+            Some(until) => format!(
+                "Account is suspended until {}",
+                until.with_timezone(&tz).format("%Y-%m-%d %I:%M %p")
+            ),
+            None => "Account is suspended".to_string(),
+        },
+        AccountStatus::Blocked => "Account is blocked".to_string(),
+    };
+
+    if let Some(reason) = reason.filter(|r| !r.trim().is_empty()) {
+        message.push_str(&format!(": {}", reason.trim()));
+    }
+    Some(message)
+}
+
+pub trait AccountStatusRepository: Send + Sync {
+    // None when the school_id is not a school account
+    fn get_account_status(&self, conn: &Connection, school_id: &str) -> Result<Option<AccountStatusInfo>>;
+    fn set_restriction(
+        &self,
+        conn: &Connection,
+        school_id: &str,
+        restriction: SetAccountRestrictionRequest,
+        actor: &str
+    ) -> Result<AccountRestriction>;
+    fn clear_restriction(&self, conn: &Connection, school_id: &str) -> Result<()>;
+    // Includes suspensions that have already lapsed so they can be cleared
+    fn get_restrictions(&self, conn: &Connection) -> Result<Vec<AccountRestriction>>;
+}
+
+pub struct SqliteAccountStatusRepository;
+
+impl AccountStatusRepository for SqliteAccountStatusRepository {
+    fn get_account_status(&self, conn: &Connection, school_id: &str) -> Result<Option<AccountStatusInfo>> {
+        let is_active: Option<bool> = conn.query_row(
+            "SELECT is_active FROM school_accounts WHERE school_id = ?1",
+            params![school_id],
+            |row| row.get(0),
+        ).optional()?;
+
+        let is_active = match is_active {
+            Some(is_active) => is_active,
+            None => return Ok(None),
+        };
+
+        let restriction = conn.query_row(
+            "SELECT school_id, status, reason, until, set_by, set_at
+             FROM account_restrictions WHERE school_id = ?1",
+            params![school_id],
+            row_to_restriction,
+        ).optional()?;
+
+        let now = Utc::now();
+        let (status, reason, until) = match restriction {
+            Some(r) if r.status == AccountStatus::Blocked => (r.status, r.reason, None),
+            Some(r) if r.until.is_none_or(|until| until > now) => (r.status, r.reason, r.until),
+            _ if !is_active => (AccountStatus::Inactive, None, None),
+            _ => (AccountStatus::Active, None, None),
+        };
+
+        Ok(Some(AccountStatusInfo {
+            school_id: school_id.to_string(),
+            status,
+            message: status_message(institution_timezone(conn)?, status, reason.as_deref(), until),
+            reason,
+            until,
+            policy: policy_for(conn, status)?,
+        }))
+    }
+
+    fn set_restriction(
+        &self,
+        conn: &Connection,
+        school_id: &str,
+        restriction: SetAccountRestrictionRequest,
+        actor: &str
+    ) -> Result<AccountRestriction> {
+        if !matches!(restriction.status, AccountStatus::Suspended | AccountStatus::Blocked) {
+            return Err(rusqlite::Error::InvalidParameterName(
+                "Only Suspended or Blocked can be set on an account".to_string()
+            ));
+        }
+
+        if self.get_account_status(conn, school_id)?.is_none() {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+
+        let reason = restriction.reason
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty());
+        // A block has no end date
+        let until = match restriction.status {
+            AccountStatus::Suspended => restriction.until,
+            _ => None,
+        };
+        let set_at = Utc::now();
+
+        conn.execute(
+            "INSERT INTO account_restrictions (school_id, status, reason, until, set_by, set_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(school_id) DO UPDATE SET
+                status = excluded.status,
+                reason = excluded.reason,
+                until = excluded.until,
+                set_by = excluded.set_by,
+                set_at = excluded.set_at",
+            params![
+                school_id,
+                restriction.status.as_str(),
+                reason,
+                until.map(|u| u.to_rfc3339()),
+                actor,
+                set_at.to_rfc3339()
+            ],
+        )?;
+
+        info!("{} set {} on account {}", actor, restriction.status.as_str(), school_id);
+        Ok(AccountRestriction {
+            school_id: school_id.to_string(),
+            status: restriction.status,
+            reason,
+            until,
+            set_by: actor.to_string(),
+            set_at,
+        })
+    }
+
+    fn clear_restriction(&self, conn: &Connection, school_id: &str) -> Result<()> {
+        let cleared = conn.execute(
+            "DELETE FROM account_restrictions WHERE school_id = ?1",
+            params![school_id],
+        )?;
+
+        if cleared == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    fn get_restrictions(&self, conn: &Connection) -> Result<Vec<AccountRestriction>> {
+        let mut stmt = conn.prepare(
+            "SELECT school_id, status, reason, until, set_by, set_at
+             FROM account_restrictions ORDER BY set_at DESC"
+        )?;
+
+        let restrictions = stmt.query_map([], row_to_restriction)?;
+        restrictions.collect()
+    }
+}
+
+pub fn create_account_restrictions_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS account_restrictions (
+            school_id TEXT PRIMARY KEY,
+            status TEXT NOT NULL,
+            reason TEXT,
+            until TEXT,
+            set_by TEXT NOT NULL,
+            set_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
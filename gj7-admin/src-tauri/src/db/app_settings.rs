@@ -0,0 +1,180 @@
+// src/db/app_settings.rs
+
+use chrono::{DateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+use log::info;
+use rusqlite::{Connection, Result as SqliteResult, params, Row, OptionalExtension};
+use serde::{Serialize, Deserialize};
+
+// IANA name of the institution's timezone (e.g. "Asia/Manila"). Days in filters, reports, exports and
+// the closing time are counted in it, whatever timezone the computer is set to
+pub const INSTITUTION_TIMEZONE: &str = "institution.timezone";
+// Library closing time (institution time, HH:MM) used to auto-close visits without a time out
+pub const ATTENDANCE_CLOSING_TIME: &str = "attendance.closing_time";
+// When "true", a second scan of an open visit records the time out instead of a new time in
+pub const ATTENDANCE_CHECKOUT_ON_SECOND_SCAN: &str = "attendance.checkout_on_second_scan";
+// Repeat scans of the same school_id within this many seconds return the existing record (0 disables)
+pub const ATTENDANCE_DUPLICATE_COOLDOWN_SECONDS: &str = "attendance.duplicate_cooldown_seconds";
+// When "true", a scan with a different purpose is not treated as a duplicate
+pub const ATTENDANCE_DUPLICATE_COOLDOWN_PER_PURPOSE: &str = "attendance.duplicate_cooldown_per_purpose";
+// When "true", kiosk scans of IDs that are neither a school account nor a registered visitor are rejected
+pub const ATTENDANCE_REQUIRE_VISITOR_REGISTRATION: &str = "attendance.require_visitor_registration";
+// Kiosk policy ("allow", "warn" or "refuse") for accounts in each non-active status
+pub const ATTENDANCE_POLICY_INACTIVE: &str = "attendance.policy.inactive";
+pub const ATTENDANCE_POLICY_SUSPENDED: &str = "attendance.policy.suspended";
+pub const ATTENDANCE_POLICY_BLOCKED: &str = "attendance.policy.blocked";
+// When "true", the day's attendance is exported automatically at EXPORT_DAILY_TIME
+pub const EXPORT_SCHEDULE_ENABLED: &str = "export.schedule_enabled";
+// Institution time (HH:MM) of the daily export
+pub const EXPORT_DAILY_TIME: &str = "export.daily_time";
+// Folder scheduled exports are written to; empty means "Attendance Exports" in Downloads
+pub const EXPORT_FOLDER: &str = "export.folder";
+// How many files of each kind are kept before the oldest are removed (0 keeps everything)
+pub const EXPORT_KEEP_DAILY: &str = "export.keep_daily";
+pub const EXPORT_KEEP_WEEKLY: &str = "export.keep_weekly";
+pub const EXPORT_KEEP_MONTHLY: &str = "export.keep_monthly";
+// Export template id used by scheduled exports; empty uses the built-in layouts
+pub const EXPORT_TEMPLATE_ID: &str = "export.template_id";
+// Open visits older than this many minutes are counted as having left when estimating occupancy
+pub const OCCUPANCY_ESTIMATED_STAY_MINUTES: &str = "occupancy.estimated_stay_minutes";
+// Percentage of a location's capacity at which kiosks and the admin window are warned
+pub const OCCUPANCY_WARNING_PERCENT: &str = "occupancy.warning_percent";
+// Attendance outside the most recent N semesters, or older than N years, is moved to the archive
+// database (0 disables each rule)
+pub const RETENTION_KEEP_SEMESTERS: &str = "retention.keep_semesters";
+pub const RETENTION_KEEP_YEARS: &str = "retention.keep_years";
+
+const DEFAULT_SETTINGS: &[(&str, &str)] = &[
+    (INSTITUTION_TIMEZONE, "Asia/Manila"),
+    (ATTENDANCE_CLOSING_TIME, "20:00"),
+    (ATTENDANCE_CHECKOUT_ON_SECOND_SCAN, "true"),
+    (ATTENDANCE_DUPLICATE_COOLDOWN_SECONDS, "60"),
+    (ATTENDANCE_DUPLICATE_COOLDOWN_PER_PURPOSE, "false"),
+    (ATTENDANCE_REQUIRE_VISITOR_REGISTRATION, "false"),
+    (ATTENDANCE_POLICY_INACTIVE, "warn"),
+    (ATTENDANCE_POLICY_SUSPENDED, "refuse"),
+    (ATTENDANCE_POLICY_BLOCKED, "refuse"),
+    (EXPORT_SCHEDULE_ENABLED, "true"),
+    (EXPORT_DAILY_TIME, "20:30"),
+    (EXPORT_FOLDER, ""),
+    (EXPORT_KEEP_DAILY, "60"),
+    (EXPORT_KEEP_WEEKLY, "26"),
+    (EXPORT_KEEP_MONTHLY, "24"),
+    (EXPORT_TEMPLATE_ID, ""),
+    (OCCUPANCY_ESTIMATED_STAY_MINUTES, "180"),
+    (OCCUPANCY_WARNING_PERCENT, "90"),
+    (RETENTION_KEEP_SEMESTERS, "0"),
+    (RETENTION_KEEP_YEARS, "0"),
+];
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AppSetting {
+    pub key: String,
+    pub value: String,
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Clone)]
+pub struct AppSettingsDatabase;
+
+impl AppSettingsDatabase {
+    pub fn init(conn: &Connection) -> SqliteResult<Self> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS app_settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+            [],
+        )?;
+
+        // Seed defaults without overwriting values the admin already changed
+        let now = Utc::now().timestamp();
+        for (key, value) in DEFAULT_SETTINGS {
+            conn.execute(
+                "INSERT OR IGNORE INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+                params![key, value, now],
+            )?;
+        }
+
+        Ok(AppSettingsDatabase)
+    }
+
+    fn timestamp_to_datetime(timestamp: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(timestamp * 1000)
+            .expect("Invalid timestamp")
+    }
+
+    fn row_to_app_setting(row: &Row) -> SqliteResult<AppSetting> {
+        Ok(AppSetting {
+            key: row.get(0)?,
+            value: row.get(1)?,
+            updated_at: Self::timestamp_to_datetime(row.get(2)?),
+        })
+    }
+
+    pub fn get_all_settings(&self, conn: &Connection) -> SqliteResult<Vec<AppSetting>> {
+        let mut stmt = conn.prepare(
+            "SELECT key, value, updated_at FROM app_settings ORDER BY key"
+        )?;
+
+        let settings = stmt.query_map([], Self::row_to_app_setting)?;
+        settings.collect()
+    }
+
+    pub fn get_setting(&self, conn: &Connection, key: &str) -> SqliteResult<Option<AppSetting>> {
+        conn.query_row(
+            "SELECT key, value, updated_at FROM app_settings WHERE key = ?1",
+            params![key],
+            Self::row_to_app_setting,
+        ).optional()
+    }
+
+    pub fn set_setting(&self, conn: &Connection, key: &str, value: &str) -> SqliteResult<AppSetting> {
+        info!("Updating app setting {} = {}", key, value);
+        let now = Utc::now().timestamp();
+
+        conn.execute(
+            "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+            params![key, value, now],
+        )?;
+
+        Ok(AppSetting {
+            key: key.to_string(),
+            value: value.to_string(),
+            updated_at: Self::timestamp_to_datetime(now),
+        })
+    }
+
+    pub fn get_bool(&self, conn: &Connection, key: &str, default: bool) -> SqliteResult<bool> {
+        Ok(self.get_setting(conn, key)?
+            .map(|s| matches!(s.value.trim().to_lowercase().as_str(), "true" | "1" | "yes"))
+            .unwrap_or(default))
+    }
+
+    pub fn get_i64(&self, conn: &Connection, key: &str, default: i64) -> SqliteResult<i64> {
+        Ok(self.get_setting(conn, key)?
+            .and_then(|s| s.value.trim().parse::<i64>().ok())
+            .unwrap_or(default))
+    }
+
+    pub fn get_string(&self, conn: &Connection, key: &str, default: &str) -> SqliteResult<String> {
+        Ok(self.get_setting(conn, key)?
+            .map(|s| s.value.trim().to_string())
+            .unwrap_or_else(|| default.to_string()))
+    }
+
+    pub fn get_time(&self, conn: &Connection, key: &str, default: NaiveTime) -> SqliteResult<NaiveTime> {
+        Ok(self.get_setting(conn, key)?
+            .and_then(|s| NaiveTime::parse_from_str(s.value.trim(), "%H:%M").ok())
+            .unwrap_or(default))
+    }
+
+    pub fn get_timezone(&self, conn: &Connection, key: &str, default: Tz) -> SqliteResult<Tz> {
+        Ok(self.get_setting(conn, key)?
+            .and_then(|s| s.value.trim().parse::<Tz>().ok())
+            .unwrap_or(default))
+    }
+}
@@ -1,13 +1,72 @@
 // src/db/attendance.rs
 
 use uuid::Uuid;
-use rusqlite::{params, Connection, Result};
+use rusqlite::{params, Connection, Result, Row, OptionalExtension};
+use rusqlite::types::Value;
+use log::info;
 use serde::{Serialize, Deserialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use chrono_tz::Tz;
 use std::path::PathBuf;
 use std::io;
 use rusqlite::Error as SqliteError;
 
+use crate::db::add_column_if_missing;
+use crate::db::attendance_archive::attendance_source;
+use crate::db::institution_time::{at_local_time, day_range, institution_timezone, local_date, start_of_day};
+use crate::db::search::{highlight_markup, match_expression, AttendanceSearchHit, MATCH_END, MATCH_START};
+use crate::db::semester::{SemesterRepository, SqliteSemesterRepository};
+use crate::db::attendance_audit::{
+    AttendanceAuditAction,
+    AttendanceAuditRepository,
+    SqliteAttendanceAuditRepository,
+    ACTOR_KIOSK,
+    ACTOR_SYSTEM,
+};
+use crate::db::school_accounts::{SchoolAccountRepository, SqliteSchoolAccountRepository};
+use crate::db::attendance_csv_import::{compose_full_name, DUPLICATE_TOLERANCE_SECONDS};
+use crate::db::locations::{LocationRepository, SqliteLocationRepository};
+use crate::db::events::{EventRepository, SqliteEventRepository};
+use crate::db::export_templates::{lookups_for_template, ExportTemplate};
+use crate::db::account_status::{
+    AccountStatusInfo,
+    AccountStatusRepository,
+    KioskPolicy,
+    SqliteAccountStatusRepository,
+};
+use crate::db::visitors::{
+    VisitorRepository,
+    SqliteVisitorRepository,
+    VISITOR_CLASSIFICATION,
+    VISITOR_REGISTRATION_REQUIRED,
+};
+use crate::db::attendance_integrity::{
+    csv_digest_path,
+    AttendanceIntegrityRepository,
+    SqliteAttendanceIntegrityRepository,
+};
+use crate::xlsx_export::{attendance_sheets, integrity_sheet, write_workbook, XlsxSheetGrouping};
+use crate::db::app_settings::{
+    AppSettingsDatabase,
+    ATTENDANCE_CHECKOUT_ON_SECOND_SCAN,
+    ATTENDANCE_DUPLICATE_COOLDOWN_SECONDS,
+    ATTENDANCE_DUPLICATE_COOLDOWN_PER_PURPOSE,
+    ATTENDANCE_REQUIRE_VISITOR_REGISTRATION,
+};
+
+// Column list shared by every attendance query, always aliased as `a`
+const ATTENDANCE_COLUMNS: &str = "
+    a.id, a.school_id, a.full_name, a.time_in_date, a.classification, a.purpose_label,
+    a.time_out_date, a.duration_minutes, a.is_auto_closed, a.semester_id, a.visitor_id, a.location_id,
+    a.device_id, a.group_id, a.event_id
+";
+
+// Device recorded on rows entered through the admin app rather than a kiosk
+pub const ADMIN_APP_DEVICE: &str = "admin-app";
+
+// Accounts with a position but no course, as the kiosk lookup classifies them
+pub const FACULTY_CLASSIFICATION: &str = "Faculty";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Attendance {
     pub id: Uuid,
@@ -16,6 +75,20 @@ pub struct Attendance {
     pub time_in_date: DateTime<Utc>,
     pub classification: String,
     pub purpose_label: Option<String>,
+    pub time_out_date: Option<DateTime<Utc>>,
+    pub duration_minutes: Option<i64>,
+    pub is_auto_closed: bool,
+    // Semester that was active when the visit was recorded
+    pub semester_id: Option<Uuid>,
+    // Registered visitor the visit belongs to, for scans that are not a school account
+    pub visitor_id: Option<Uuid>,
+    pub location_id: Option<Uuid>,
+    // Kiosk id, or "address (user agent)" for clients that are not a registered kiosk
+    pub device_id: Option<String>,
+    // Group visit the row was checked in with, None for individual visits
+    pub group_id: Option<Uuid>,
+    // Event the row was scanned into by a kiosk in event mode
+    pub event_id: Option<Uuid>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -24,6 +97,74 @@ pub struct CreateAttendanceRequest {
     pub full_name: String,
     pub classification: Option<String>,
     pub purpose_label: Option<String>,
+    // Ignored when the kiosk is bound to a location; None records at the default location
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+    #[serde(default)]
+    pub kiosk_id: Option<String>,
+    // Filled in by the network server from the connection, never taken from the client
+    #[serde(default, skip_deserializing)]
+    pub device_id: Option<String>,
+    // Set by the group visit flow, never taken from the client
+    #[serde(default, skip_deserializing)]
+    pub group_id: Option<Uuid>,
+    // Set from the kiosk's event mode, never taken from the client
+    #[serde(default, skip_deserializing)]
+    pub event_id: Option<Uuid>,
+}
+
+// Result of a kiosk scan; `already_logged` is set when the scan fell inside the duplicate cooldown
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttendanceScanResult {
+    #[serde(flatten)]
+    pub attendance: Attendance,
+    pub already_logged: bool,
+    // Set when the account's status policy is Warn
+    pub warning: Option<String>,
+}
+
+// Attendance with explicit timestamps, e.g. rows copied from the paper logbook
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImportAttendanceRequest {
+    pub school_id: String,
+    pub full_name: String,
+    pub time_in_date: DateTime<Utc>,
+    pub time_out_date: Option<DateTime<Utc>>,
+    pub classification: Option<String>,
+    pub purpose_label: Option<String>,
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+}
+
+// Admin entry for a missed scan or a class that visited together; every ID shares the same times
+#[derive(Debug, Deserialize, Clone)]
+pub struct ManualAttendanceRequest {
+    pub school_ids: Vec<String>,
+    pub time_in_date: DateTime<Utc>,
+    pub time_out_date: Option<DateTime<Utc>>,
+    pub purpose_label: Option<String>,
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+}
+
+// Exactly one of `attendance` and `error` is set
+#[derive(Debug, Serialize, Clone)]
+pub struct ManualAttendanceRowResult {
+    pub school_id: String,
+    pub attendance: Option<Attendance>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ManualAttendanceResult {
+    pub recorded: usize,
+    pub failed: usize,
+    pub rows: Vec<ManualAttendanceRowResult>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CheckOutAttendanceRequest {
+    pub school_id: String,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -32,6 +173,154 @@ pub struct UpdateAttendanceRequest {
     pub full_name: Option<String>,
     pub classification: Option<String>,
     pub purpose_label: Option<String>,
+    pub location_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum AttendanceSort {
+    #[default]
+    TimeInDesc,
+    TimeInAsc,
+    NameAsc,
+    NameDesc,
+    SchoolIdAsc,
+}
+
+impl AttendanceSort {
+    fn order_by(&self) -> &'static str {
+        match self {
+            AttendanceSort::TimeInDesc => "a.time_in_date DESC, a.id DESC",
+            AttendanceSort::TimeInAsc => "a.time_in_date ASC, a.id ASC",
+            AttendanceSort::NameAsc => "a.full_name COLLATE NOCASE ASC, a.time_in_date DESC",
+            AttendanceSort::NameDesc => "a.full_name COLLATE NOCASE DESC, a.time_in_date DESC",
+            AttendanceSort::SchoolIdAsc => "a.school_id ASC, a.time_in_date DESC",
+        }
+    }
+}
+
+// Filter used by the records view and exports; every list field is OR-ed within itself and AND-ed with the rest
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AttendanceQuery {
+    pub date_from: Option<DateTime<Utc>>,
+    pub date_to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub courses: Vec<String>,
+    #[serde(default)]
+    pub classifications: Vec<String>,
+    #[serde(default)]
+    pub purposes: Vec<String>,
+    pub semester_id: Option<Uuid>,
+    #[serde(default)]
+    pub locations: Vec<Uuid>,
+    #[serde(default)]
+    pub devices: Vec<String>,
+    #[serde(default)]
+    pub group_visits: Vec<Uuid>,
+    #[serde(default)]
+    pub events: Vec<Uuid>,
+    // Also reads the archive database, for reports and exports reaching past the retention period
+    #[serde(default)]
+    pub include_archive: bool,
+    pub school_id: Option<String>,
+    // Matches school_id or full name
+    pub search: Option<String>,
+    #[serde(default)]
+    pub sort: AttendanceSort,
+    pub page: Option<u64>,
+    pub page_size: Option<u64>,
+    // next_cursor of the previous page; only valid for the time in sorts
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaginatedAttendances {
+    pub attendances: Vec<Attendance>,
+    pub total_count: u64,
+    pub page: u64,
+    pub page_size: u64,
+    pub total_pages: u64,
+    pub next_cursor: Option<String>,
+}
+
+const DEFAULT_ATTENDANCE_PAGE_SIZE: u64 = 50;
+const MAX_ATTENDANCE_PAGE_SIZE: u64 = 500;
+
+fn encode_cursor(attendance: &Attendance) -> String {
+    format!("{}|{}", attendance.time_in_date.to_rfc3339(), attendance.id)
+}
+
+// Cursors keep their RFC3339 form so ones handed out before the integer timestamps still work
+fn decode_cursor(cursor: &str) -> Result<(i64, String)> {
+    cursor.split_once('|')
+        .and_then(|(time_in, id)| {
+            let time_in = DateTime::parse_from_rfc3339(time_in).ok()?.with_timezone(&Utc);
+            Uuid::parse_str(id).ok()?;
+            Some((to_epoch_nanos(time_in).ok()?, id.to_string()))
+        })
+        .ok_or_else(|| rusqlite::Error::InvalidParameterName("Invalid attendance cursor".to_string()))
+}
+
+fn push_in_condition(conditions: &mut Vec<String>, params: &mut Vec<Value>, column: &str, values: &[String]) {
+    if values.is_empty() {
+        return;
+    }
+
+    let placeholders = values.iter().map(|_| "?").collect::<Vec<&str>>().join(",");
+    conditions.push(format!("{} IN ({})", column, placeholders));
+    params.extend(values.iter().cloned().map(Value::from));
+}
+
+// WHERE clause (without the cursor) and its positional parameters for an AttendanceQuery
+pub(crate) fn build_query_conditions(query: &AttendanceQuery) -> (String, Vec<Value>) {
+    let mut conditions: Vec<String> = Vec::new();
+    let mut params: Vec<Value> = Vec::new();
+
+    if let Some(date_from) = query.date_from {
+        conditions.push("a.time_in_date >= ?".to_string());
+        params.push(Value::Integer(epoch_nanos_bound(date_from)));
+    }
+
+    if let Some(date_to) = query.date_to {
+        conditions.push("a.time_in_date <= ?".to_string());
+        params.push(Value::Integer(epoch_nanos_bound(date_to)));
+    }
+
+    push_in_condition(&mut conditions, &mut params, "sa.course", &query.courses);
+    push_in_condition(&mut conditions, &mut params, "a.classification", &query.classifications);
+    push_in_condition(&mut conditions, &mut params, "a.purpose_label", &query.purposes);
+
+    if let Some(semester_id) = query.semester_id {
+        conditions.push("a.semester_id = ?".to_string());
+        params.push(Value::from(semester_id.to_string()));
+    }
+
+    let locations: Vec<String> = query.locations.iter().map(|id| id.to_string()).collect();
+    push_in_condition(&mut conditions, &mut params, "a.location_id", &locations);
+    push_in_condition(&mut conditions, &mut params, "a.device_id", &query.devices);
+    let group_visits: Vec<String> = query.group_visits.iter().map(|id| id.to_string()).collect();
+    push_in_condition(&mut conditions, &mut params, "a.group_id", &group_visits);
+    let events: Vec<String> = query.events.iter().map(|id| id.to_string()).collect();
+    push_in_condition(&mut conditions, &mut params, "a.event_id", &events);
+
+    if let Some(school_id) = query.school_id.as_ref().filter(|s| !s.trim().is_empty()) {
+        conditions.push("a.school_id = ?".to_string());
+        params.push(Value::from(school_id.trim().to_string()));
+    }
+
+    if let Some(search) = query.search.as_ref().filter(|s| !s.trim().is_empty()) {
+        let pattern = format!("%{}%", search.trim());
+        conditions.push("(a.school_id LIKE ? OR a.full_name LIKE ?)".to_string());
+        params.push(Value::from(pattern.clone()));
+        params.push(Value::from(pattern));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        "WHERE 1=1".to_string()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    (where_clause, params)
 }
 
 // Custom error type for CSV operations
@@ -40,6 +329,7 @@ pub enum AttendanceExportError {
     Csv(csv::Error),
     Sqlite(SqliteError),
     Io(io::Error),
+    Xlsx(rust_xlsxwriter::XlsxError),
 }
 
 impl From<csv::Error> for AttendanceExportError {
@@ -60,15 +350,130 @@ impl From<io::Error> for AttendanceExportError {
     }
 }
 
+impl From<rust_xlsxwriter::XlsxError> for AttendanceExportError {
+    fn from(err: rust_xlsxwriter::XlsxError) -> Self {
+        AttendanceExportError::Xlsx(err)
+    }
+}
+
+impl std::fmt::Display for AttendanceExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AttendanceExportError::Csv(err) => write!(f, "CSV Error: {}", err),
+            AttendanceExportError::Sqlite(err) => write!(f, "Database Error: {}", err),
+            AttendanceExportError::Io(err) => write!(f, "IO Error: {}", err),
+            AttendanceExportError::Xlsx(err) => write!(f, "XLSX Error: {}", err),
+        }
+    }
+}
+
+// Attendance times are stored as Unix epoch nanoseconds, which keeps times exact so rows still match
+// the snapshots in the audit log. That only reaches from 1677 to 2262; anything outside is refused
+pub(crate) fn to_epoch_nanos(time: DateTime<Utc>) -> Result<i64> {
+    time.timestamp_nanos_opt().ok_or_else(|| rusqlite::Error::InvalidParameterName(
+        format!("{} is outside the supported date range", time.format("%Y-%m-%d %H:%M"))
+    ))
+}
+
+// For range bounds in queries, which may saturate since nothing is stored outside the range
+pub(crate) fn epoch_nanos_bound(time: DateTime<Utc>) -> i64 {
+    time.timestamp_nanos_opt().unwrap_or(if time.timestamp() < 0 { i64::MIN } else { i64::MAX })
+}
+
+pub(crate) fn from_epoch_nanos(nanos: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp_nanos(nanos)
+}
+
+// SQL expression turning a stored attendance time into Unix seconds
+pub(crate) fn epoch_seconds_sql(column: &str) -> String {
+    format!("({} / 1000000000)", column)
+}
+
+pub(crate) fn epoch_nanos_column(row: &Row, idx: usize) -> Result<DateTime<Utc>> {
+    Ok(from_epoch_nanos(row.get(idx)?))
+}
+
+fn row_to_attendance(row: &Row) -> Result<Attendance> {
+    let time_out_date = row.get::<_, Option<i64>>(6)?.map(from_epoch_nanos);
+
+    Ok(Attendance {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        school_id: row.get(1)?,
+        full_name: row.get(2)?,
+        time_in_date: epoch_nanos_column(row, 3)?,
+        classification: row.get(4)?,
+        purpose_label: row.get(5)?,
+        time_out_date,
+        duration_minutes: row.get(7)?,
+        is_auto_closed: row.get(8)?,
+        semester_id: row.get::<_, Option<String>>(9)?
+            .and_then(|id| Uuid::parse_str(&id).ok()),
+        visitor_id: row.get::<_, Option<String>>(10)?
+            .and_then(|id| Uuid::parse_str(&id).ok()),
+        location_id: row.get::<_, Option<String>>(11)?
+            .and_then(|id| Uuid::parse_str(&id).ok()),
+        device_id: row.get(12)?,
+        group_id: row.get::<_, Option<String>>(13)?
+            .and_then(|id| Uuid::parse_str(&id).ok()),
+        event_id: row.get::<_, Option<String>>(14)?
+            .and_then(|id| Uuid::parse_str(&id).ok()),
+    })
+}
+
+// Semester a visit at the given time belongs to: the last one created before it,
+// or the first semester for visits older than every semester
+fn semester_id_for_time(conn: &Connection, time_in_date: DateTime<Utc>) -> Result<Option<Uuid>> {
+    let semester_id: Option<String> = conn.query_row(
+        "SELECT COALESCE(
+            (SELECT id FROM semesters WHERE created_at <= ?1 ORDER BY created_at DESC LIMIT 1),
+            (SELECT id FROM semesters ORDER BY created_at ASC LIMIT 1)
+        )",
+        params![time_in_date.to_rfc3339()],
+        |row| row.get(0),
+    )?;
+
+    Ok(semester_id.and_then(|id| Uuid::parse_str(&id).ok()))
+}
+
+// Closing time on the institution's calendar day of the given time in
+fn closing_time_for(tz: Tz, time_in_date: DateTime<Utc>, closing_time: NaiveTime) -> DateTime<Utc> {
+    at_local_time(tz, local_date(tz, time_in_date), closing_time)
+}
+
+pub(crate) fn start_of_local_day(tz: Tz, now: DateTime<Utc>) -> DateTime<Utc> {
+    start_of_day(tz, local_date(tz, now))
+}
+
+// Runs a mutation and its audit entry atomically; savepoints nest, so callers may already be in a transaction
+pub(crate) fn in_savepoint<T, F>(conn: &Connection, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    conn.execute_batch("SAVEPOINT attendance_mutation")?;
+    match f() {
+        Ok(value) => {
+            conn.execute_batch("RELEASE attendance_mutation")?;
+            Ok(value)
+        },
+        Err(e) => {
+            conn.execute_batch("ROLLBACK TO attendance_mutation; RELEASE attendance_mutation")?;
+            Err(e)
+        }
+    }
+}
+
 pub trait AttendanceRepository: Send + Sync {
     fn clone_box(&self) -> Box<dyn AttendanceRepository + Send + Sync>;
-    fn create_attendance(&self, conn: &Connection, attendance: CreateAttendanceRequest) -> Result<Attendance>;
+    fn create_attendance(&self, conn: &Connection, attendance: CreateAttendanceRequest, actor: &str) -> Result<Attendance>;
     fn get_attendance(&self, conn: &Connection, id: Uuid) -> Result<Attendance>;
     fn get_attendances_by_school_id(&self, conn: &Connection, school_id: &str) -> Result<Vec<Attendance>>;
-    fn delete_attendance(&self, conn: &Connection, id: Uuid) -> Result<()>;
+    fn delete_attendance(&self, conn: &Connection, id: Uuid, actor: &str) -> Result<()>;
+    // Re-inserts a deleted attendance from its last audit snapshot
+    fn restore_attendance(&self, conn: &Connection, id: Uuid, actor: &str) -> Result<Attendance>;
     fn get_all_attendances(&self, conn: &Connection) -> Result<Vec<Attendance>>;
-    fn search_attendances(&self, conn: &Connection, query: &str) -> Result<Vec<Attendance>>;
-    fn update_attendance(&self, conn: &Connection, id: Uuid, attendance: UpdateAttendanceRequest) -> Result<Attendance>;
+    // Ranked full-text search over school ID, name and purpose, matching each term as a prefix
+    fn search_attendances(&self, conn: &Connection, query: &str, limit: usize) -> Result<Vec<AttendanceSearchHit>>;
+    fn update_attendance(&self, conn: &Connection, id: Uuid, attendance: UpdateAttendanceRequest, actor: &str) -> Result<Attendance>;
     fn get_attendances_by_semester(&self, conn: &Connection, semester_id: Uuid) -> Result<Vec<Attendance>>;
     fn get_attendances_by_school_account(&self, conn: &Connection, school_account_id: Uuid) -> Result<Vec<Attendance>>;
     fn get_last_n_attendances(&self, conn: &Connection, n: usize) -> Result<Vec<Attendance>, rusqlite::Error>;
@@ -79,48 +484,99 @@ pub trait AttendanceRepository: Send + Sync {
         date: Option<DateTime<Utc>>
     ) -> Result<Vec<Attendance>>;
     fn get_all_courses(&self, conn: &Connection) -> Result<Vec<String>>;
+    // Every device attendance was recorded from, for the records view filter
+    fn get_all_devices(&self, conn: &Connection) -> Result<Vec<String>>;
+    // One page of the records view
+    fn query_attendances(&self, conn: &Connection, query: &AttendanceQuery) -> Result<PaginatedAttendances>;
+    // Every row matching the filter, pagination fields are ignored (used by exports)
+    fn get_all_matching_attendances(&self, conn: &Connection, query: &AttendanceQuery) -> Result<Vec<Attendance>>;
+    // Latest visit of the current day that has no time out yet
+    fn get_open_attendance(&self, conn: &Connection, school_id: &str) -> Result<Option<Attendance>>;
+    fn close_attendance(
+        &self,
+        conn: &Connection,
+        id: Uuid,
+        time_out_date: DateTime<Utc>,
+        is_auto_closed: bool,
+        actor: &str
+    ) -> Result<Attendance>;
+    fn check_out_attendance(&self, conn: &Connection, school_id: &str, actor: &str) -> Result<Attendance>;
+    // Most recent time in or time out of the school_id since the given instant
+    fn find_recent_scan(
+        &self,
+        conn: &Connection,
+        school_id: &str,
+        purpose_label: Option<&str>,
+        match_purpose: bool,
+        since: DateTime<Utc>
+    ) -> Result<Option<Attendance>>;
+    // Inserts a visit with the given time in/out instead of stamping the current time
+    fn import_attendance(&self, conn: &Connection, attendance: ImportAttendanceRequest, actor: &str) -> Result<Attendance>;
+    // Records every listed ID in one transaction; rows that fail are reported and skipped
+    fn record_manual_attendances(
+        &self,
+        conn: &Connection,
+        entry: ManualAttendanceRequest,
+        actor: &str
+    ) -> Result<ManualAttendanceResult>;
+    // Existing visit of the school_id whose time in is within `tolerance_seconds` of the given instant
+    fn find_duplicate_attendance(
+        &self,
+        conn: &Connection,
+        school_id: &str,
+        time_in_date: DateTime<Utc>,
+        tolerance_seconds: i64
+    ) -> Result<Option<Attendance>>;
+    // Kiosk entry point: time in, or time out when the visit is still open and the setting allows it
+    fn record_scan(&self, conn: &Connection, attendance: CreateAttendanceRequest) -> Result<AttendanceScanResult>;
+    // Closes every open visit whose day has already reached closing time, returns how many were closed
+    fn auto_close_open_attendances(
+        &self,
+        conn: &Connection,
+        closing_time: NaiveTime,
+        now: DateTime<Utc>
+    ) -> Result<usize>;
     fn export_attendances_to_csv(
         &self, 
         conn: &Connection, 
         path: PathBuf, 
-        attendances: Vec<Attendance>
+        attendances: Vec<Attendance>,
+        template: &ExportTemplate
     ) -> std::result::Result<(), AttendanceExportError> {
-        let mut wtr = csv::Writer::from_path(path)?;
-
-        // Write header
-        wtr.write_record(&[
-            "ID",
-            "School ID",
-            "Full Name",
-            "Date",
-            "Time",
-            "Classification",
-            "Purpose"
-        ])?;
-
-        // Write data
-        for attendance in attendances {
-            // Convert UTC to local time and format
-            let local_time = attendance.time_in_date.with_timezone(&chrono::Local);
-            
-            // Format date as MM/DD/YYYY
-            let date_str = local_time.format("%m/%d/%Y").to_string();
-            
-            // Format time as hh:MM AM/PM
-            let time_str = local_time.format("%I:%M %p").to_string();
-            
-            wtr.write_record(&[
-                attendance.id.to_string(),
-                attendance.school_id,
-                attendance.full_name,
-                date_str,
-                time_str,
-                attendance.classification,
-                attendance.purpose_label.unwrap_or_default()
-            ])?;
+        let lookups = lookups_for_template(conn, template)?;
+        let mut wtr = csv::WriterBuilder::new()
+            .delimiter(template.delimiter_byte())
+            .from_writer(Vec::new());
+
+        wtr.write_record(template.headers())?;
+
+        for attendance in &attendances {
+            wtr.write_record(template.text_values(attendance, &lookups))?;
         }
 
-        wtr.flush()?;
+        // The digest signs the rendered rows and goes in a sidecar file next to them
+        let content = wtr.into_inner().map_err(|e| e.into_error())?;
+        let digest = SqliteAttendanceIntegrityRepository.create_export_digest(conn, &attendances, Some(&content), "csv")?;
+
+        std::fs::write(&path, content)?;
+        std::fs::write(csv_digest_path(&path), format!("{}\n", digest))?;
+        Ok(())
+    }
+    fn export_attendances_to_xlsx(
+        &self,
+        conn: &Connection,
+        path: PathBuf,
+        attendances: Vec<Attendance>,
+        template: &ExportTemplate,
+        grouping: XlsxSheetGrouping
+    ) -> std::result::Result<(), AttendanceExportError> {
+        let lookups = lookups_for_template(conn, template)?;
+
+        let digest = SqliteAttendanceIntegrityRepository.create_export_digest(conn, &attendances, None, "xlsx")?;
+
+        let mut sheets = attendance_sheets(attendances, template, &lookups, grouping);
+        sheets.push(integrity_sheet(&digest));
+        write_workbook(&path, &sheets)?;
         Ok(())
     }
 }
@@ -134,6 +590,146 @@ impl Clone for SqliteAttendanceRepository {
 
 pub struct SqliteAttendanceRepository;
 
+// Name and classification of a school account or registered visitor, the same way the kiosk lookup
+// derives them; None for IDs that are neither
+pub(crate) fn identity_for_school_id(conn: &Connection, school_id: &str) -> Result<Option<(String, String)>> {
+    match SqliteSchoolAccountRepository.get_school_account_by_school_id(conn, school_id) {
+        Ok(account) => {
+            let classification = match (&account.course, &account.position) {
+                (Some(course), _) if !course.is_empty() => course.clone(),
+                (_, Some(position)) if !position.is_empty() => FACULTY_CLASSIFICATION.to_string(),
+                _ => VISITOR_CLASSIFICATION.to_string(),
+            };
+            let full_name = compose_full_name(account.first_name, account.middle_name, account.last_name);
+            Ok(Some((if full_name.is_empty() { school_id.to_string() } else { full_name }, classification)))
+        },
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(SqliteVisitorRepository.get_visitor_by_code(conn, school_id)?
+            .map(|visitor| (visitor.full_name, VISITOR_CLASSIFICATION.to_string()))),
+        Err(e) => Err(e),
+    }
+}
+
+// Like identity_for_school_id, but an unknown ID is an error
+pub(crate) fn known_identity(conn: &Connection, school_id: &str) -> Result<(String, String)> {
+    identity_for_school_id(conn, school_id)?.ok_or_else(|| rusqlite::Error::InvalidParameterName(
+        format!("No school account or registered visitor has ID {}", school_id)
+    ))
+}
+
+impl SqliteAttendanceRepository {
+    // Shared by imports and manual entries, which only differ in how the audit log labels them
+    fn insert_backdated(
+        &self,
+        conn: &Connection,
+        attendance: ImportAttendanceRequest,
+        action: AttendanceAuditAction,
+        actor: &str
+    ) -> Result<Attendance> {
+        in_savepoint(conn, || {
+            if attendance.school_id.is_empty() {
+                return Err(rusqlite::Error::InvalidParameterName("School ID cannot be empty".to_string()));
+            }
+
+            if let Some(time_out_date) = attendance.time_out_date {
+                if time_out_date < attendance.time_in_date {
+                    return Err(rusqlite::Error::InvalidParameterName("Time out cannot be before time in".to_string()));
+                }
+            }
+
+            let id = Uuid::new_v4();
+            let visitor_id = SqliteVisitorRepository.get_visitor_by_code(conn, &attendance.school_id)?
+                .map(|v| v.id);
+            let classification = attendance.classification.unwrap_or_else(|| VISITOR_CLASSIFICATION.to_string());
+            let duration_minutes = attendance.time_out_date
+                .map(|time_out| (time_out - attendance.time_in_date).num_minutes());
+            // Backdated visits go to the semester of their time in, not the one active today
+            let semester_id = semester_id_for_time(conn, attendance.time_in_date)?;
+            let location_id = SqliteLocationRepository.resolve_location(conn, attendance.location_id, None)?;
+
+            conn.execute(
+                "INSERT INTO attendance (
+                    id, school_id, full_name, time_in_date, classification, purpose_label,
+                    time_out_date, duration_minutes, is_auto_closed, semester_id, visitor_id, location_id,
+                    device_id
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, 0, ?9, ?10, ?11, ?12)",
+                params![
+                    id.to_string(),
+                    attendance.school_id,
+                    attendance.full_name,
+                    to_epoch_nanos(attendance.time_in_date)?,
+                    classification,
+                    attendance.purpose_label,
+                    attendance.time_out_date.map(to_epoch_nanos).transpose()?,
+                    duration_minutes,
+                    semester_id.map(|id| id.to_string()),
+                    visitor_id.map(|id| id.to_string()),
+                    location_id.map(|id| id.to_string()),
+                    ADMIN_APP_DEVICE
+                ],
+            )?;
+
+            if let Some(visitor_id) = visitor_id {
+                SqliteVisitorRepository.record_visit(conn, visitor_id, attendance.time_in_date)?;
+            }
+
+            let inserted = Attendance {
+                id,
+                school_id: attendance.school_id,
+                full_name: attendance.full_name,
+                time_in_date: attendance.time_in_date,
+                classification,
+                purpose_label: attendance.purpose_label,
+                time_out_date: attendance.time_out_date,
+                duration_minutes,
+                is_auto_closed: false,
+                semester_id,
+                visitor_id,
+                location_id,
+                device_id: Some(ADMIN_APP_DEVICE.to_string()),
+                group_id: None,
+                event_id: None,
+            };
+
+            SqliteAttendanceAuditRepository.record(
+                conn,
+                id,
+                action,
+                actor,
+                None,
+                Some(&inserted)
+            )?;
+
+            Ok(inserted)
+        })
+    }
+
+    // Backdated row for one ID of a manual entry, refusing IDs that already have a visit at that time
+    fn manual_entry_request(
+        &self,
+        conn: &Connection,
+        school_id: &str,
+        entry: &ManualAttendanceRequest
+    ) -> Result<ImportAttendanceRequest> {
+        let (full_name, classification) = known_identity(conn, school_id)?;
+
+        if self.find_duplicate_attendance(conn, school_id, entry.time_in_date, DUPLICATE_TOLERANCE_SECONDS)?.is_some() {
+            return Err(rusqlite::Error::InvalidParameterName(
+                format!("{} already has a visit recorded at this time", school_id)
+            ));
+        }
+
+        Ok(ImportAttendanceRequest {
+            school_id: school_id.to_string(),
+            full_name,
+            time_in_date: entry.time_in_date,
+            time_out_date: entry.time_out_date,
+            classification: Some(classification),
+            purpose_label: entry.purpose_label.clone(),
+            location_id: entry.location_id,
+        })
+    }
+}
+
 impl AttendanceRepository for SqliteAttendanceRepository {
     fn clone_box(&self) -> Box<dyn AttendanceRepository + Send + Sync> {
         Box::new(self.clone())
@@ -160,6 +756,15 @@ impl AttendanceRepository for SqliteAttendanceRepository {
         Ok(courses)
     }
 
+    fn get_all_devices(&self, conn: &Connection) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT device_id FROM attendance WHERE device_id IS NOT NULL ORDER BY device_id ASC"
+        )?;
+
+        let devices = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        devices.collect()
+    }
+
     fn get_filtered_attendances(
         &self, 
         conn: &Connection, 
@@ -167,11 +772,11 @@ impl AttendanceRepository for SqliteAttendanceRepository {
         date: Option<DateTime<Utc>>
     ) -> Result<Vec<Attendance>> {
         // Base query with flexible filtering
-        let mut query = String::from("
-            SELECT DISTINCT a.* FROM attendance a
+        let mut query = format!("
+            SELECT DISTINCT {} FROM attendance a
             LEFT JOIN school_accounts sa ON a.school_id = sa.school_id
             WHERE 1=1
-        ");
+        ", ATTENDANCE_COLUMNS);
     
         // Prepare parameters for the query
         let mut param_conditions = Vec::new();
@@ -180,14 +785,17 @@ impl AttendanceRepository for SqliteAttendanceRepository {
         // Add course filter if specified
         if let Some(course_name) = course {
             param_conditions.push("sa.course = ?");
-            param_values.push(course_name);
+            param_values.push(Value::from(course_name));
         }
     
-        // Add date filter if specified (exact date match)
+        // Add date filter if specified: the institution's calendar day the given time falls on,
+        // matched as a range so the time in index is used
         if let Some(filter_date) = date {
-            // Match the entire day
-            param_conditions.push("date(a.time_in_date) = date(?)");
-            param_values.push(filter_date.to_rfc3339());
+            let tz = institution_timezone(conn)?;
+            let (day_start, day_end) = day_range(tz, local_date(tz, filter_date));
+            param_conditions.push("a.time_in_date >= ? AND a.time_in_date < ?");
+            param_values.push(Value::Integer(epoch_nanos_bound(day_start)));
+            param_values.push(Value::Integer(epoch_nanos_bound(day_end)));
         }
     
         // Add conditions to query if any
@@ -202,25 +810,10 @@ impl AttendanceRepository for SqliteAttendanceRepository {
         // Prepare the statement with dynamic parameters
         let mut stmt = conn.prepare(&query)?;
         
-        let attendance_iter = stmt.query_map(rusqlite::params_from_iter(param_values.iter().map(|v| v.as_str())), |row| {
-            let time_in_str: String = row.get(3)?;
-            let time_in_date = DateTime::parse_from_rfc3339(&time_in_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                    3,
-                    rusqlite::types::Type::Text,
-                    Box::new(e)
-                ))?;
-    
-            Ok(Attendance {
-                id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                school_id: row.get(1)?,
-                full_name: row.get(2)?,
-                time_in_date,
-                classification: row.get(4)?,
-                purpose_label: row.get(5)?,
-            })
-        })?;
+        let attendance_iter = stmt.query_map(
+            rusqlite::params_from_iter(param_values.iter()),
+            row_to_attendance
+        )?;
     
         let mut attendances = Vec::new();
         for attendance in attendance_iter {
@@ -231,156 +824,513 @@ impl AttendanceRepository for SqliteAttendanceRepository {
     }
     
     
-    fn create_attendance(&self, conn: &Connection, attendance: CreateAttendanceRequest) -> Result<Attendance> {
-        if attendance.school_id.is_empty() {
-            let err = rusqlite::Error::InvalidParameterName("School ID cannot be empty".to_string());
-            return Err(err);
+    fn query_attendances(&self, conn: &Connection, query: &AttendanceQuery) -> Result<PaginatedAttendances> {
+        let page = query.page.unwrap_or(1).max(1);
+        let page_size = query.page_size
+            .unwrap_or(DEFAULT_ATTENDANCE_PAGE_SIZE)
+            .clamp(1, MAX_ATTENDANCE_PAGE_SIZE);
+
+        let from_clause = format!(
+            "FROM {} a
+            LEFT JOIN school_accounts sa ON a.school_id = sa.school_id",
+            attendance_source(conn, query.include_archive)?
+        );
+        let (where_clause, mut param_values) = build_query_conditions(query);
+
+        // Count total records matching the filter
+        let total_count: u64 = conn.query_row(
+            &format!("SELECT COUNT(*) {} {}", from_clause, where_clause),
+            rusqlite::params_from_iter(param_values.iter()),
+            |row| row.get(0)
+        )?;
+
+        let total_pages = (total_count as f64 / page_size as f64).ceil() as u64;
+
+        // A cursor continues after the last row of the previous page, otherwise fall back to offsets
+        let mut sql = format!("SELECT {} {} {}", ATTENDANCE_COLUMNS, from_clause, where_clause);
+        match &query.cursor {
+            Some(cursor) => {
+                let comparison = match query.sort {
+                    AttendanceSort::TimeInDesc => "<",
+                    AttendanceSort::TimeInAsc => ">",
+                    _ => return Err(rusqlite::Error::InvalidParameterName(
+                        "Cursor pagination requires a time in sort".to_string()
+                    )),
+                };
+                let (time_in, id) = decode_cursor(cursor)?;
+                sql.push_str(&format!(" AND (a.time_in_date, a.id) {} (?, ?)", comparison));
+                param_values.push(Value::Integer(time_in));
+                param_values.push(Value::from(id));
+                sql.push_str(&format!(" ORDER BY {} LIMIT {}", query.sort.order_by(), page_size));
+            }
+            None => {
+                let offset = (page - 1) * page_size;
+                sql.push_str(&format!(" ORDER BY {} LIMIT {} OFFSET {}", query.sort.order_by(), page_size, offset));
+            }
         }
-        
-        // Only get the full name from database if not provided
-        let full_name = match conn.query_row(
-            "SELECT 
-                COALESCE(
-                    CASE 
-                        WHEN first_name IS NOT NULL AND middle_name IS NOT NULL AND last_name IS NOT NULL THEN 
-                            first_name || ' ' || middle_name || ' ' || last_name
-                        WHEN first_name IS NOT NULL AND last_name IS NOT NULL THEN 
-                            first_name || ' ' || last_name
-                        ELSE first_name
-                    END, 
-                    ?1
-                ) as computed_full_name
-            FROM school_accounts 
-            WHERE school_id = ?2",
-            params![
-                attendance.full_name, 
-                attendance.school_id
-            ],
-            |row| row.get::<_, String>(0)
-        ) {
-            Ok(name) => name,
-            Err(_) => attendance.full_name.clone()
+
+        let mut stmt = conn.prepare(&sql)?;
+        let attendances = stmt.query_map(
+            rusqlite::params_from_iter(param_values.iter()),
+            row_to_attendance
+        )?.collect::<Result<Vec<Attendance>>>()?;
+
+        let is_time_sort = matches!(query.sort, AttendanceSort::TimeInDesc | AttendanceSort::TimeInAsc);
+        let next_cursor = match attendances.last() {
+            Some(last) if is_time_sort && attendances.len() as u64 == page_size => Some(encode_cursor(last)),
+            _ => None,
         };
+
+        Ok(PaginatedAttendances {
+            attendances,
+            total_count,
+            page,
+            page_size,
+            total_pages,
+            next_cursor,
+        })
+    }
+
+    fn get_all_matching_attendances(&self, conn: &Connection, query: &AttendanceQuery) -> Result<Vec<Attendance>> {
+        let (where_clause, param_values) = build_query_conditions(query);
+        let sql = format!(
+            "SELECT {} FROM {} a
+             LEFT JOIN school_accounts sa ON a.school_id = sa.school_id
+             {}
+             ORDER BY {}",
+            ATTENDANCE_COLUMNS,
+            attendance_source(conn, query.include_archive)?,
+            where_clause,
+            query.sort.order_by()
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let attendances = stmt.query_map(
+            rusqlite::params_from_iter(param_values.iter()),
+            row_to_attendance
+        )?.collect::<Result<Vec<Attendance>>>()?;
+
+        Ok(attendances)
+    }
+
+    fn create_attendance(&self, conn: &Connection, attendance: CreateAttendanceRequest, actor: &str) -> Result<Attendance> {
+        in_savepoint(conn, || {
+            if attendance.school_id.is_empty() {
+                let err = rusqlite::Error::InvalidParameterName("School ID cannot be empty".to_string());
+                return Err(err);
+            }
         
-        let id = Uuid::new_v4();
-        let time_in_date = Utc::now();
-        let time_in_str = time_in_date.to_rfc3339();
+            // Only get the full name from database if not provided
+            let account_name = match conn.query_row(
+                "SELECT 
+                    COALESCE(
+                        CASE 
+                            WHEN first_name IS NOT NULL AND middle_name IS NOT NULL AND last_name IS NOT NULL THEN 
+                                first_name || ' ' || middle_name || ' ' || last_name
+                            WHEN first_name IS NOT NULL AND last_name IS NOT NULL THEN 
+                                first_name || ' ' || last_name
+                            ELSE first_name
+                        END, 
+                        ?1
+                    ) as computed_full_name
+                FROM school_accounts 
+                WHERE school_id = ?2",
+                params![
+                    attendance.full_name, 
+                    attendance.school_id
+                ],
+                |row| row.get::<_, String>(0)
+            ) {
+                Ok(name) => Some(name),
+                Err(_) => None
+            };
+
+            // IDs that are not a school account are linked to the registered visitor, if any
+            let visitor = match account_name {
+                Some(_) => None,
+                None => SqliteVisitorRepository.get_visitor_by_code(conn, &attendance.school_id)?,
+            };
+            let full_name = match (&account_name, &visitor) {
+                (Some(name), _) => name.clone(),
+                (None, Some(visitor)) => visitor.full_name.clone(),
+                (None, None) => attendance.full_name.clone(),
+            };
         
-        // Use the classification provided by the frontend, with "Visitor" as fallback
-        let classification = attendance.classification.unwrap_or_else(|| "Visitor".to_string());
+            let id = Uuid::new_v4();
+            let time_in_date = Utc::now();
         
-        conn.execute(
-            "INSERT INTO attendance (
-                id, school_id, full_name, time_in_date, classification, purpose_label
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![
-                id.to_string(),
-                attendance.school_id,
+            // Use the classification provided by the frontend, with "Visitor" as fallback
+            let classification = match &visitor {
+                Some(_) => VISITOR_CLASSIFICATION.to_string(),
+                None => attendance.classification.unwrap_or_else(|| VISITOR_CLASSIFICATION.to_string()),
+            };
+            let visitor_id = visitor.map(|v| v.id);
+
+            let semester_id = SqliteSemesterRepository.get_active_semester(conn)?.map(|s| s.id);
+            let location_id = SqliteLocationRepository.resolve_location(
+                conn,
+                attendance.location_id,
+                attendance.kiosk_id.as_deref()
+            )?;
+        
+            let device_id = attendance.device_id.clone().unwrap_or_else(|| ADMIN_APP_DEVICE.to_string());
+        
+            conn.execute(
+                "INSERT INTO attendance (
+                    id, school_id, full_name, time_in_date, classification, purpose_label, semester_id, visitor_id,
+                    location_id, device_id, group_id, event_id
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+                params![
+                    id.to_string(),
+                    attendance.school_id,
+                    full_name,
+                    to_epoch_nanos(time_in_date)?,
+                    classification,
+                    attendance.purpose_label,
+                    semester_id.map(|id| id.to_string()),
+                    visitor_id.map(|id| id.to_string()),
+                    location_id.map(|id| id.to_string()),
+                    device_id,
+                    attendance.group_id.map(|id| id.to_string()),
+                    attendance.event_id.map(|id| id.to_string())
+                ],
+            )?;
+
+            if let Some(visitor_id) = visitor_id {
+                SqliteVisitorRepository.record_visit(conn, visitor_id, time_in_date)?;
+            }
+        
+            let created_attendance = Attendance {
+                id,
+                school_id: attendance.school_id,
                 full_name,
-                time_in_str,
+                time_in_date,
                 classification,
-                attendance.purpose_label
+                purpose_label: attendance.purpose_label,
+                time_out_date: None,
+                duration_minutes: None,
+                is_auto_closed: false,
+                semester_id,
+                visitor_id,
+                location_id,
+                device_id: Some(device_id),
+                group_id: attendance.group_id,
+                event_id: attendance.event_id,
+            };
+        
+            SqliteAttendanceAuditRepository.record(
+                conn,
+                created_attendance.id,
+                AttendanceAuditAction::Created,
+                actor,
+                None,
+                Some(&created_attendance)
+            )?;
+
+            Ok(created_attendance)
+        })
+    }
+
+    fn import_attendance(&self, conn: &Connection, attendance: ImportAttendanceRequest, actor: &str) -> Result<Attendance> {
+        self.insert_backdated(conn, attendance, AttendanceAuditAction::Imported, actor)
+    }
+
+    fn record_manual_attendances(
+        &self,
+        conn: &Connection,
+        entry: ManualAttendanceRequest,
+        actor: &str
+    ) -> Result<ManualAttendanceResult> {
+        if entry.school_ids.is_empty() {
+            return Err(rusqlite::Error::InvalidParameterName("At least one School ID is required".to_string()));
+        }
+        if entry.time_in_date > Utc::now() {
+            return Err(rusqlite::Error::InvalidParameterName("Time in cannot be in the future".to_string()));
+        }
+        if entry.time_out_date.is_some_and(|time_out| time_out < entry.time_in_date) {
+            return Err(rusqlite::Error::InvalidParameterName("Time out cannot be before time in".to_string()));
+        }
+        to_epoch_nanos(entry.time_in_date)?;
+        entry.time_out_date.map(to_epoch_nanos).transpose()?;
+
+        let tx = conn.unchecked_transaction()?;
+        let mut rows = Vec::with_capacity(entry.school_ids.len());
+        let mut seen = std::collections::HashSet::new();
+
+        for school_id in &entry.school_ids {
+            let school_id = school_id.trim().to_string();
+            let result = if school_id.is_empty() {
+                Err(rusqlite::Error::InvalidParameterName("School ID cannot be empty".to_string()))
+            } else if !seen.insert(school_id.clone()) {
+                Err(rusqlite::Error::InvalidParameterName(format!("{} is listed more than once", school_id)))
+            } else {
+                self.manual_entry_request(&tx, &school_id, &entry)
+                    .and_then(|request| self.insert_backdated(&tx, request, AttendanceAuditAction::ManualEntry, actor))
+            };
+
+            rows.push(match result {
+                Ok(attendance) => ManualAttendanceRowResult { school_id, attendance: Some(attendance), error: None },
+                Err(rusqlite::Error::InvalidParameterName(msg)) => ManualAttendanceRowResult { school_id, attendance: None, error: Some(msg) },
+                Err(e) => return Err(e),
+            });
+        }
+
+        tx.commit()?;
+
+        let recorded = rows.iter().filter(|row| row.attendance.is_some()).count();
+        Ok(ManualAttendanceResult {
+            recorded,
+            failed: rows.len() - recorded,
+            rows,
+        })
+    }
+
+    fn find_duplicate_attendance(
+        &self,
+        conn: &Connection,
+        school_id: &str,
+        time_in_date: DateTime<Utc>,
+        tolerance_seconds: i64
+    ) -> Result<Option<Attendance>> {
+        let query = format!(
+            "SELECT {} FROM attendance a
+             WHERE a.school_id = ?1 AND a.time_in_date >= ?2 AND a.time_in_date <= ?3
+             ORDER BY a.time_in_date
+             LIMIT 1",
+            ATTENDANCE_COLUMNS
+        );
+
+        let tolerance = Duration::seconds(tolerance_seconds);
+        conn.query_row(
+            &query,
+            params![
+                school_id,
+                epoch_nanos_bound(time_in_date - tolerance),
+                epoch_nanos_bound(time_in_date + tolerance)
             ],
+            row_to_attendance,
+        ).optional()
+    }
+
+    fn get_open_attendance(&self, conn: &Connection, school_id: &str) -> Result<Option<Attendance>> {
+        let query = format!(
+            "SELECT {} FROM attendance a
+             WHERE a.school_id = ?1 AND a.time_out_date IS NULL AND a.time_in_date >= ?2
+             ORDER BY a.time_in_date DESC
+             LIMIT 1",
+            ATTENDANCE_COLUMNS
+        );
+    
+        conn.query_row(
+            &query,
+            params![school_id, epoch_nanos_bound(start_of_local_day(institution_timezone(conn)?, Utc::now()))],
+            row_to_attendance,
+        ).optional()
+    }
+
+    fn close_attendance(
+        &self,
+        conn: &Connection,
+        id: Uuid,
+        time_out_date: DateTime<Utc>,
+        is_auto_closed: bool,
+        actor: &str
+    ) -> Result<Attendance> {
+        in_savepoint(conn, || {
+            let attendance = self.get_attendance(conn, id)?;
+
+            // Never record a time out before the time in
+            let time_out_date = time_out_date.max(attendance.time_in_date);
+            let duration_minutes = (time_out_date - attendance.time_in_date).num_minutes();
+
+            conn.execute(
+                "UPDATE attendance
+                 SET time_out_date = ?1, duration_minutes = ?2, is_auto_closed = ?3
+                 WHERE id = ?4",
+                params![
+                    to_epoch_nanos(time_out_date)?,
+                    duration_minutes,
+                    is_auto_closed,
+                    id.to_string()
+                ],
+            )?;
+
+            let closed = self.get_attendance(conn, id)?;
+            let action = if is_auto_closed { AttendanceAuditAction::AutoClosed } else { AttendanceAuditAction::CheckedOut };
+            SqliteAttendanceAuditRepository.record(conn, id, action, actor, Some(&attendance), Some(&closed))?;
+
+            Ok(closed)
+        })
+    }
+
+    fn check_out_attendance(&self, conn: &Connection, school_id: &str, actor: &str) -> Result<Attendance> {
+        match self.get_open_attendance(conn, school_id)? {
+            Some(open) => self.close_attendance(conn, open.id, Utc::now(), false, actor),
+            None => Err(rusqlite::Error::QueryReturnedNoRows),
+        }
+    }
+
+    fn find_recent_scan(
+        &self,
+        conn: &Connection,
+        school_id: &str,
+        purpose_label: Option<&str>,
+        match_purpose: bool,
+        since: DateTime<Utc>
+    ) -> Result<Option<Attendance>> {
+        let query = format!(
+            "SELECT {} FROM attendance a
+             WHERE a.school_id = ?1
+               AND (?2 = 0 OR a.purpose_label IS ?3)
+               AND (a.time_in_date >= ?4 OR a.time_out_date >= ?4)
+             ORDER BY a.time_in_date DESC
+             LIMIT 1",
+            ATTENDANCE_COLUMNS
+        );
+
+        conn.query_row(
+            &query,
+            params![school_id, match_purpose, purpose_label, epoch_nanos_bound(since)],
+            row_to_attendance,
+        ).optional()
+    }
+
+    fn record_scan(&self, conn: &Connection, mut attendance: CreateAttendanceRequest) -> Result<AttendanceScanResult> {
+        let settings = AppSettingsDatabase;
+        attendance.location_id = SqliteLocationRepository.resolve_location(
+            conn,
+            attendance.location_id,
+            attendance.kiosk_id.as_deref()
         )?;
-        
-        let created_attendance = Attendance {
-            id,
-            school_id: attendance.school_id,
-            full_name,
-            time_in_date,
-            classification,
-            purpose_label: attendance.purpose_label,
+        let event = SqliteEventRepository.get_kiosk_event(conn, attendance.kiosk_id.as_deref())?;
+        attendance.event_id = event.as_ref().map(|e| e.id);
+
+        if settings.get_bool(conn, ATTENDANCE_REQUIRE_VISITOR_REGISTRATION, false)?
+            && SqliteSchoolAccountRepository.get_school_account_by_school_id(conn, &attendance.school_id).is_err()
+            && SqliteVisitorRepository.get_visitor_by_code(conn, &attendance.school_id)?.is_none()
+        {
+            return Err(rusqlite::Error::InvalidParameterName(VISITOR_REGISTRATION_REQUIRED.to_string()));
+        }
+
+        // A double tap returns the record it duplicates instead of inserting or checking out
+        let cooldown_seconds = settings.get_i64(conn, ATTENDANCE_DUPLICATE_COOLDOWN_SECONDS, 60)?;
+        if cooldown_seconds > 0 {
+            let per_purpose = settings.get_bool(conn, ATTENDANCE_DUPLICATE_COOLDOWN_PER_PURPOSE, false)?;
+            let since = Utc::now() - Duration::seconds(cooldown_seconds);
+
+            if let Some(existing) = self.find_recent_scan(
+                conn,
+                &attendance.school_id,
+                attendance.purpose_label.as_deref(),
+                per_purpose,
+                since
+            )? {
+                return Ok(AttendanceScanResult { attendance: existing, already_logged: true, warning: None });
+            }
+        }
+
+        // Restricted accounts can still check out of a visit that is already open
+        let account_status = SqliteAccountStatusRepository.get_account_status(conn, &attendance.school_id)?;
+        let time_in = |attendance: CreateAttendanceRequest| -> Result<(Attendance, Option<String>)> {
+            if let Some(event) = &event {
+                let classification = identity_for_school_id(conn, &attendance.school_id)?
+                    .map(|(_, classification)| classification)
+                    .or_else(|| attendance.classification.clone())
+                    .unwrap_or_else(|| VISITOR_CLASSIFICATION.to_string());
+                SqliteEventRepository.check_admission(conn, event, &attendance.school_id, &classification)?;
+            }
+
+            match account_status {
+                Some(AccountStatusInfo { policy: KioskPolicy::Refuse, message, .. }) => Err(
+                    rusqlite::Error::InvalidParameterName(
+                        format!("Entry refused: {}", message.unwrap_or_else(|| "account is restricted".to_string()))
+                    )
+                ),
+                Some(AccountStatusInfo { policy: KioskPolicy::Warn, message, .. }) => {
+                    Ok((self.create_attendance(conn, attendance, ACTOR_KIOSK)?, message))
+                },
+                _ => Ok((self.create_attendance(conn, attendance, ACTOR_KIOSK)?, None)),
+            }
         };
-        
-        Ok(created_attendance)
+
+        let (recorded, warning) = match settings.get_bool(conn, ATTENDANCE_CHECKOUT_ON_SECOND_SCAN, true)? {
+            true => match self.get_open_attendance(conn, &attendance.school_id)? {
+                Some(open) if open.location_id == attendance.location_id && open.event_id == attendance.event_id => {
+                    (self.close_attendance(conn, open.id, Utc::now(), false, ACTOR_KIOSK)?, None)
+                },
+                // Moving to another location or event ends the visit there and starts one here.
+                // If entry here is refused, the visit there stays open
+                Some(open) => in_savepoint(conn, || {
+                    self.close_attendance(conn, open.id, Utc::now(), false, ACTOR_KIOSK)?;
+                    time_in(attendance)
+                })?,
+                None => time_in(attendance)?,
+            },
+            false => time_in(attendance)?,
+        };
+
+        Ok(AttendanceScanResult { attendance: recorded, already_logged: false, warning })
     }
 
-    
+    fn auto_close_open_attendances(
+        &self,
+        conn: &Connection,
+        closing_time: NaiveTime,
+        now: DateTime<Utc>
+    ) -> Result<usize> {
+        let query = format!(
+            "SELECT {} FROM attendance a WHERE a.time_out_date IS NULL",
+            ATTENDANCE_COLUMNS
+        );
+
+        let mut stmt = conn.prepare(&query)?;
+        let open_attendances = stmt.query_map([], row_to_attendance)?
+            .collect::<Result<Vec<Attendance>>>()?;
+
+        let tz = institution_timezone(conn)?;
+        let mut closed = 0;
+        for attendance in open_attendances {
+            let closing = closing_time_for(tz, attendance.time_in_date, closing_time);
+            if closing <= now {
+                self.close_attendance(conn, attendance.id, closing, true, ACTOR_SYSTEM)?;
+                closed += 1;
+            }
+        }
+
+        Ok(closed)
+    }
 
     fn get_attendance(&self, conn: &Connection, id: Uuid) -> Result<Attendance> {
         let attendance = conn.query_row(
-            "SELECT * FROM attendance WHERE id = ?1",
+            &format!("SELECT {} FROM attendance a WHERE a.id = ?1", ATTENDANCE_COLUMNS),
             params![id.to_string()],
-            |row| {
-                let time_in_str: String = row.get(3)?;
-                let time_in_date = DateTime::parse_from_rfc3339(&time_in_str)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                        3,
-                        rusqlite::types::Type::Text,
-                        Box::new(e)
-                    ))?;
-
-                Ok(Attendance {
-                    id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                    school_id: row.get(1)?,
-                    full_name: row.get(2)?,
-                    time_in_date,
-                    classification: row.get(4)?,
-                    purpose_label: row.get(5)?,
-                })
-            },
+            row_to_attendance,
         )?;
 
         Ok(attendance)
     }
 
     fn get_last_n_attendances(&self, conn: &Connection, n: usize) -> Result<Vec<Attendance>, rusqlite::Error> {
-        let query = "
-            SELECT id, school_id, full_name, time_in_date, classification, purpose_label
-            FROM attendance 
-            ORDER BY time_in_date DESC 
+        let query = format!("
+            SELECT {}
+            FROM attendance a
+            ORDER BY a.time_in_date DESC
             LIMIT ?
-        ";
+        ", ATTENDANCE_COLUMNS);
         
-        let mut stmt = conn.prepare(query)?;
-        let attendance_iter = stmt.query_map([n], |row| {
-            let time_in_str: String = row.get(3)?;
-            let time_in_date = DateTime::parse_from_rfc3339(&time_in_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                    3,
-                    rusqlite::types::Type::Text,
-                    Box::new(e)
-                ))?;
-    
-            Ok(Attendance {
-                id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                school_id: row.get(1)?,
-                full_name: row.get(2)?,
-                time_in_date,
-                classification: row.get(4)?,
-                purpose_label: row.get(5)?,
-            })
-        })?;
+        let mut stmt = conn.prepare(&query)?;
+        let attendance_iter = stmt.query_map([n], row_to_attendance)?;
     
         attendance_iter.collect::<Result<Vec<Attendance>, _>>()
     }
 
     fn get_attendances_by_school_id(&self, conn: &Connection, school_id: &str) -> Result<Vec<Attendance>> {
-        let mut stmt = conn.prepare(
-            "SELECT * FROM attendance WHERE school_id = ?1 ORDER BY time_in_date DESC"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attendance a WHERE a.school_id = ?1 ORDER BY a.time_in_date DESC",
+            ATTENDANCE_COLUMNS
+        ))?;
         
-        let attendance_iter = stmt.query_map(params![school_id], |row| {
-            let time_in_str: String = row.get(3)?;
-            let time_in_date = DateTime::parse_from_rfc3339(&time_in_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                    3,
-                    rusqlite::types::Type::Text,
-                    Box::new(e)
-                ))?;
-
-            Ok(Attendance {
-                id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                school_id: row.get(1)?,
-                full_name: row.get(2)?,
-                time_in_date,
-                classification: row.get(4)?,
-                purpose_label: row.get(5)?,
-            })
-        })?;
+        let attendance_iter = stmt.query_map(params![school_id], row_to_attendance)?;
 
         let mut attendances = Vec::new();
         for attendance in attendance_iter {
@@ -391,32 +1341,14 @@ impl AttendanceRepository for SqliteAttendanceRepository {
     }
 
     fn get_attendances_by_semester(&self, conn: &Connection, semester_id: Uuid) -> Result<Vec<Attendance>> {
-        let mut stmt = conn.prepare(
-            "SELECT * FROM attendance 
-             JOIN semester_accounts ON attendance.school_id = semester_accounts.school_id 
-             WHERE semester_accounts.semester_id = ?1 
-             ORDER BY attendance.time_in_date DESC"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attendance a
+             WHERE a.semester_id = ?1
+             ORDER BY a.time_in_date DESC",
+            ATTENDANCE_COLUMNS
+        ))?;
         
-        let attendance_iter = stmt.query_map(params![semester_id.to_string()], |row| {
-            let time_in_str: String = row.get(3)?;
-            let time_in_date = DateTime::parse_from_rfc3339(&time_in_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                    3,
-                    rusqlite::types::Type::Text,
-                    Box::new(e)
-                ))?;
-
-            Ok(Attendance {
-                id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                school_id: row.get(1)?,
-                full_name: row.get(2)?,
-                time_in_date,
-                classification: row.get(4)?,
-                purpose_label: row.get(5)?,
-            })
-        })?;
+        let attendance_iter = stmt.query_map(params![semester_id.to_string()], row_to_attendance)?;
 
         let mut attendances = Vec::new();
         for attendance in attendance_iter {
@@ -427,34 +1359,17 @@ impl AttendanceRepository for SqliteAttendanceRepository {
     }
 
     fn get_attendances_by_school_account(&self, conn: &Connection, school_account_id: Uuid) -> Result<Vec<Attendance>> {
-        let mut stmt = conn.prepare(
-            "SELECT * FROM attendance 
-             WHERE school_id = (
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attendance a
+             WHERE a.school_id = (
                  SELECT school_id FROM school_accounts 
                  WHERE id = ?1
              ) 
-             ORDER BY time_in_date DESC"
-        )?;
+             ORDER BY a.time_in_date DESC",
+            ATTENDANCE_COLUMNS
+        ))?;
         
-        let attendance_iter = stmt.query_map(params![school_account_id.to_string()], |row| {
-            let time_in_str: String = row.get(3)?;
-            let time_in_date = DateTime::parse_from_rfc3339(&time_in_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                    3,
-                    rusqlite::types::Type::Text,
-                    Box::new(e)
-                ))?;
-
-            Ok(Attendance {
-                id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                school_id: row.get(1)?,
-                full_name: row.get(2)?,
-                time_in_date,
-                classification: row.get(4)?,
-                purpose_label: row.get(5)?,
-            })
-        })?;
+        let attendance_iter = stmt.query_map(params![school_account_id.to_string()], row_to_attendance)?;
 
         let mut attendances = Vec::new();
         for attendance in attendance_iter {
@@ -464,39 +1379,69 @@ impl AttendanceRepository for SqliteAttendanceRepository {
         Ok(attendances)
     }
 
-    fn delete_attendance(&self, conn: &Connection, id: Uuid) -> Result<()> {
-        conn.execute(
-            "DELETE FROM attendance WHERE id = ?1",
-            params![id.to_string()],
-        )?;
+    fn delete_attendance(&self, conn: &Connection, id: Uuid, actor: &str) -> Result<()> {
+        in_savepoint(conn, || {
+            let attendance = self.get_attendance(conn, id)?;
 
-        Ok(())
+            conn.execute(
+                "DELETE FROM attendance WHERE id = ?1",
+                params![id.to_string()],
+            )?;
+
+            SqliteAttendanceAuditRepository.record(conn, id, AttendanceAuditAction::Deleted, actor, Some(&attendance), None)?;
+            Ok(())
+        })
+    }
+
+    fn restore_attendance(&self, conn: &Connection, id: Uuid, actor: &str) -> Result<Attendance> {
+        in_savepoint(conn, || {
+            if self.get_attendance(conn, id).optional()?.is_some() {
+                return Err(rusqlite::Error::InvalidParameterName("Attendance record is not deleted".to_string()));
+            }
+
+            let snapshot = SqliteAttendanceAuditRepository.get_last_deleted_entry(conn, id)?
+                .and_then(|entry| entry.before)
+                .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+            let attendance: Attendance = serde_json::from_value(snapshot)
+                .map_err(|e| rusqlite::Error::InvalidParameterName(format!("Unreadable audit snapshot: {}", e)))?;
+
+            conn.execute(
+                "INSERT INTO attendance (
+                    id, school_id, full_name, time_in_date, classification, purpose_label,
+                    time_out_date, duration_minutes, is_auto_closed, semester_id, visitor_id, location_id,
+                    device_id, group_id, event_id
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                params![
+                    attendance.id.to_string(),
+                    attendance.school_id,
+                    attendance.full_name,
+                    to_epoch_nanos(attendance.time_in_date)?,
+                    attendance.classification,
+                    attendance.purpose_label,
+                    attendance.time_out_date.map(to_epoch_nanos).transpose()?,
+                    attendance.duration_minutes,
+                    attendance.is_auto_closed,
+                    attendance.semester_id.map(|id| id.to_string()),
+                    attendance.visitor_id.map(|id| id.to_string()),
+                    attendance.location_id.map(|id| id.to_string()),
+                    attendance.device_id,
+                    attendance.group_id.map(|id| id.to_string()),
+                    attendance.event_id.map(|id| id.to_string())
+                ],
+            )?;
+
+            SqliteAttendanceAuditRepository.record(conn, id, AttendanceAuditAction::Restored, actor, None, Some(&attendance))?;
+            Ok(attendance)
+        })
     }
 
     fn get_all_attendances(&self, conn: &Connection) -> Result<Vec<Attendance>> {
-        let mut stmt = conn.prepare(
-            "SELECT * FROM attendance ORDER BY time_in_date DESC"
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attendance a ORDER BY a.time_in_date DESC",
+            ATTENDANCE_COLUMNS
+        ))?;
         
-        let attendance_iter = stmt.query_map([], |row| {
-            let time_in_str: String = row.get(3)?;
-            let time_in_date = DateTime::parse_from_rfc3339(&time_in_str)
-                .map(|dt| dt.with_timezone(&Utc))
-                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                    3,
-                    rusqlite::types::Type::Text,
-                    Box::new(e)
-                ))?;
-
-            Ok(Attendance {
-                id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                school_id: row.get(1)?,
-                full_name: row.get(2)?,
-                time_in_date,
-                classification: row.get(4)?,
-                purpose_label: row.get(5)?,
-            })
-        })?;
+        let attendance_iter = stmt.query_map([], row_to_attendance)?;
 
         let mut attendances = Vec::new();
         for attendance in attendance_iter {
@@ -506,106 +1451,349 @@ impl AttendanceRepository for SqliteAttendanceRepository {
         Ok(attendances)
     }
 
-    fn update_attendance(&self, conn: &Connection, id: Uuid, attendance: UpdateAttendanceRequest) -> Result<Attendance> {
-        let mut update_parts = Vec::new();
-        let mut params_values: Vec<String> = Vec::new();
-        let mut param_count = 1;
+    fn update_attendance(&self, conn: &Connection, id: Uuid, attendance: UpdateAttendanceRequest, actor: &str) -> Result<Attendance> {
+        in_savepoint(conn, || {
+            let before = self.get_attendance(conn, id)?;
+            let mut update_parts = Vec::new();
+            let mut params_values: Vec<String> = Vec::new();
+            let mut param_count = 1;
     
-        if let Some(school_id) = &attendance.school_id {
-            update_parts.push(format!("school_id = ?{}", param_count));
-            params_values.push(school_id.clone());
-            param_count += 1;
-        }
+            if let Some(school_id) = &attendance.school_id {
+                update_parts.push(format!("school_id = ?{}", param_count));
+                params_values.push(school_id.clone());
+                param_count += 1;
+            }
     
-        if let Some(full_name) = &attendance.full_name {
-            update_parts.push(format!("full_name = ?{}", param_count));
-            params_values.push(full_name.clone());
-            param_count += 1;
-        }
+            if let Some(full_name) = &attendance.full_name {
+                update_parts.push(format!("full_name = ?{}", param_count));
+                params_values.push(full_name.clone());
+                param_count += 1;
+            }
     
-        if let Some(classification) = &attendance.classification {
-            update_parts.push(format!("classification = ?{}", param_count));
-            params_values.push(classification.clone());
-            param_count += 1;
-        }
+            if let Some(classification) = &attendance.classification {
+                update_parts.push(format!("classification = ?{}", param_count));
+                params_values.push(classification.clone());
+                param_count += 1;
+            }
+
+            // An empty purpose clears it
+            if let Some(purpose_label) = &attendance.purpose_label {
+                if purpose_label.trim().is_empty() {
+                    update_parts.push("purpose_label = NULL".to_string());
+                } else {
+                    update_parts.push(format!("purpose_label = ?{}", param_count));
+                    params_values.push(purpose_label.trim().to_string());
+                    param_count += 1;
+                }
+            }
+
+            if let Some(location_id) = attendance.location_id {
+                SqliteLocationRepository.get_location(conn, location_id).optional()?
+                    .ok_or_else(|| rusqlite::Error::InvalidParameterName("Unknown location".to_string()))?;
+                update_parts.push(format!("location_id = ?{}", param_count));
+                params_values.push(location_id.to_string());
+                param_count += 1;
+            }
     
-        if update_parts.is_empty() {
-            // If no updates are provided, return the existing record
-            return self.get_attendance(conn, id);
-        }
+            if update_parts.is_empty() {
+                // If no updates are provided, return the existing record
+                return Ok(before);
+            }
     
-        let sql = format!(
-            "UPDATE attendance SET {} WHERE id = ?{}",
-            update_parts.join(", "),
-            param_count
-        );
+            let sql = format!(
+                "UPDATE attendance SET {} WHERE id = ?{}",
+                update_parts.join(", "),
+                param_count
+            );
     
-        params_values.push(id.to_string());
+            params_values.push(id.to_string());
     
-        // Use the params! macro to create parameters
-        let result = conn.execute(
-            &sql, 
-            rusqlite::params_from_iter(params_values.iter().map(|v| v.as_str()))
-        )?;
+            // Use the params! macro to create parameters
+            let result = conn.execute(
+                &sql, 
+                rusqlite::params_from_iter(params_values.iter().map(|v| v.as_str()))
+            )?;
     
-        // Retrieve and return the updated record
-        self.get_attendance(conn, id)
+            // Retrieve and return the updated record
+            let updated = self.get_attendance(conn, id)?;
+            SqliteAttendanceAuditRepository.record(conn, id, AttendanceAuditAction::Updated, actor, Some(&before), Some(&updated))?;
+
+            Ok(updated)
+        })
     }
 
-    fn search_attendances(&self, conn: &Connection, query: &str) -> Result<Vec<Attendance>> {
-        let sql = "SELECT * FROM attendance 
-                   WHERE school_id LIKE ? OR 
-                         full_name LIKE ? OR
-                         purpose_label LIKE ?
-                   ORDER BY time_in_date DESC";
-        
-        let search_pattern = format!("%{}%", query);
-        
-        let mut stmt = conn.prepare(sql)?;
-        let attendance_iter = stmt.query_map(
-            params![&search_pattern, &search_pattern, &search_pattern],
-            |row| {
-                let time_in_str: String = row.get(3)?;
-                let time_in_date = DateTime::parse_from_rfc3339(&time_in_str)
-                    .map(|dt| dt.with_timezone(&Utc))
-                    .map_err(|e| rusqlite::Error::FromSqlConversionFailure(
-                        3,
-                        rusqlite::types::Type::Text,
-                        Box::new(e)
-                    ))?;
-    
-                Ok(Attendance {
-                    id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                    school_id: row.get(1)?,
-                    full_name: row.get(2)?,
-                    time_in_date,
-                    classification: row.get(4)?,
-                    purpose_label: row.get(5)?, // Use purpose_label instead of purpose_id
-                })
-            }
-        )?;
-    
-        let mut attendances = Vec::new();
-        for attendance in attendance_iter {
-            attendances.push(attendance?);
+    fn search_attendances(&self, conn: &Connection, query: &str, limit: usize) -> Result<Vec<AttendanceSearchHit>> {
+        let sql = format!("SELECT {},
+                       highlight(attendance_fts, 0, ?2, ?3),
+                       highlight(attendance_fts, 1, ?2, ?3),
+                       snippet(attendance_fts, -1, ?2, ?3, '…', 12),
+                       bm25(attendance_fts, 10.0, 5.0, 1.0) AS score
+                   FROM attendance_fts
+                   JOIN attendance a ON a.rowid = attendance_fts.rowid
+                   WHERE attendance_fts MATCH ?1
+                   ORDER BY score ASC, a.time_in_date DESC
+                   LIMIT ?4", ATTENDANCE_COLUMNS);
+
+        let mut stmt = conn.prepare(&sql)?;
+        let mut run = |expression: String| -> Result<Vec<AttendanceSearchHit>> {
+            let hits = stmt.query_map(
+                params![expression, MATCH_START, MATCH_END, limit as i64],
+                |row| {
+                    Ok(AttendanceSearchHit {
+                        attendance: row_to_attendance(row)?,
+                        school_id_highlight: highlight_markup(&row.get::<_, String>(15)?),
+                        full_name_highlight: highlight_markup(&row.get::<_, String>(16)?),
+                        snippet: highlight_markup(&row.get::<_, Option<String>>(17)?.unwrap_or_default()),
+                        score: row.get(18)?,
+                    })
+                }
+            )?;
+            hits.collect()
+        };
+
+        // When no row has every term, fall back to rows with any of them, best matches first
+        let Some(expression) = match_expression(query, false) else {
+            return Ok(Vec::new());
+        };
+        let hits = run(expression)?;
+        match match_expression(query, true) {
+            Some(any_term) if hits.is_empty() && query.split_whitespace().count() > 1 => run(any_term),
+            _ => Ok(hits),
         }
-    
-        Ok(attendances)
     }
 }
 
+// Column types for a new attendance table; time_in_date and time_out_date hold epoch nanoseconds
+const ATTENDANCE_TABLE_COLUMNS: &str = "
+    id TEXT PRIMARY KEY,
+    school_id TEXT NOT NULL,
+    full_name TEXT NOT NULL,
+    time_in_date INTEGER NOT NULL,
+    classification TEXT NOT NULL,
+    purpose_label TEXT,
+    time_out_date INTEGER,
+    duration_minutes INTEGER,
+    is_auto_closed INTEGER NOT NULL DEFAULT 0,
+    semester_id TEXT,
+    visitor_id TEXT,
+    location_id TEXT,
+    device_id TEXT,
+    group_id TEXT,
+    event_id TEXT,
+    chain_hash TEXT
+";
+
+// Name the pre-conversion table is moved to while its rows are copied
+const TEXT_TIMESTAMPS_TABLE: &str = "attendance_text_timestamps";
+
+// True while an attendance table in the given schema still stores RFC3339 text times
+pub(crate) fn has_text_timestamps(conn: &Connection, schema: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info(attendance)", schema))?;
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let name: String = row.get(1)?;
+        let column_type: String = row.get(2)?;
+        if name == "time_in_date" {
+            return Ok(column_type.eq_ignore_ascii_case("TEXT"));
+        }
+    }
+    Ok(false)
+}
+
+// Rebuilds `schema`.attendance with integer times. `create_sql` creates the new, empty table;
+// rowids are kept so the search index stays valid, and the indexes are left for the caller to recreate
+pub(crate) fn convert_text_timestamps(conn: &Connection, schema: &str, create_sql: &str) -> Result<usize> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(&format!("ALTER TABLE {}.attendance RENAME TO {}", schema, TEXT_TIMESTAMPS_TABLE), [])?;
+    tx.execute(create_sql, [])?;
+
+    let columns: Vec<String> = {
+        let mut stmt = tx.prepare(&format!("PRAGMA {}.table_info({})", schema, TEXT_TIMESTAMPS_TABLE))?;
+        let columns = stmt.query_map([], |row| row.get::<_, String>(1))?;
+        columns.collect::<Result<Vec<String>>>()?
+    };
+    let column_list = columns.join(", ");
+    let placeholders = vec!["?"; columns.len() + 1].join(", ");
+
+    let mut select = tx.prepare(&format!("SELECT rowid, {} FROM {}.{}", column_list, schema, TEXT_TIMESTAMPS_TABLE))?;
+    let mut insert = tx.prepare(&format!(
+        "INSERT INTO {}.attendance (rowid, {}) VALUES ({})",
+        schema, column_list, placeholders
+    ))?;
+
+    let mut converted = 0;
+    let mut rows = select.query([])?;
+    while let Some(row) = rows.next()? {
+        let mut values = Vec::with_capacity(columns.len() + 1);
+        values.push(row.get::<_, Value>(0)?);
+        for (i, column) in columns.iter().enumerate() {
+            let value = row.get::<_, Value>(i + 1)?;
+            let value = match value {
+                Value::Text(text) if column == "time_in_date" || column == "time_out_date" => {
+                    let time = DateTime::parse_from_rfc3339(&text)
+                        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(i + 1, rusqlite::types::Type::Text, Box::new(e)))?;
+                    Value::Integer(to_epoch_nanos(time.with_timezone(&Utc))?)
+                },
+                value => value,
+            };
+            values.push(value);
+        }
+        insert.execute(rusqlite::params_from_iter(values.iter()))?;
+        converted += 1;
+    }
+    drop(rows);
+    drop(select);
+    drop(insert);
+
+    tx.execute(&format!("DROP TABLE {}.{}", schema, TEXT_TIMESTAMPS_TABLE), [])?;
+    tx.commit()?;
+    Ok(converted)
+}
+
 pub fn create_attendance_table(conn: &Connection) -> Result<()> {
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS attendance (
-            id TEXT PRIMARY KEY,
-            school_id TEXT NOT NULL,
-            full_name TEXT NOT NULL,
-            time_in_date TEXT NOT NULL,
-            classification TEXT NOT NULL,
-            purpose_label TEXT
-        )",
+        &format!("CREATE TABLE IF NOT EXISTS attendance ({})", ATTENDANCE_TABLE_COLUMNS),
+        [],
+    )?;
+
+    // Databases created before time out tracking only have the time in columns
+    add_column_if_missing(conn, "attendance", "time_out_date", "INTEGER")?;
+    add_column_if_missing(conn, "attendance", "duration_minutes", "INTEGER")?;
+    add_column_if_missing(conn, "attendance", "is_auto_closed", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_missing(conn, "attendance", "semester_id", "TEXT")?;
+    add_column_if_missing(conn, "attendance", "visitor_id", "TEXT")?;
+    add_column_if_missing(conn, "attendance", "location_id", "TEXT")?;
+    add_column_if_missing(conn, "attendance", "device_id", "TEXT")?;
+    add_column_if_missing(conn, "attendance", "group_id", "TEXT")?;
+    add_column_if_missing(conn, "attendance", "event_id", "TEXT")?;
+    // Hash of the audit entry that produced the row's current state, kept out of `Attendance`
+    add_column_if_missing(conn, "attendance", "chain_hash", "TEXT")?;
+
+    // Times were stored as RFC3339 text before; those tables are converted once, in place
+    if has_text_timestamps(conn, "main")? {
+        let converted = convert_text_timestamps(
+            conn,
+            "main",
+            &format!("CREATE TABLE main.attendance ({})", ATTENDANCE_TABLE_COLUMNS)
+        )?;
+        info!("Converted {} attendance records to integer timestamps", converted);
+    }
+
+    // Lookups by school ID are narrowed by time in, which also orders the kiosk's recent scans
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_school_id_time_in ON attendance(school_id, time_in_date)",
+        [],
+    )?;
+
+    // Date range filters and the default newest-first order, including cursor pagination
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_time_in ON attendance(time_in_date, id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_purpose_label ON attendance(purpose_label, time_in_date)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_semester_id ON attendance(semester_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_visitor_id ON attendance(visitor_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_location_id ON attendance(location_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_device_id ON attendance(device_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_group_id ON attendance(group_id)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_event_id ON attendance(event_id)",
         [],
     )?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+// Applies a one-off correction to every row `select_sql` returns, logging each as a system edit so
+// the hash chain stays valid. `apply` updates one row and returns false when there is nothing to apply
+fn backfill_attendances<F>(conn: &Connection, select_sql: &str, mut apply: F) -> Result<usize>
+where
+    F: FnMut(&Attendance) -> Result<bool>,
+{
+    let ids: Vec<String> = {
+        let mut stmt = conn.prepare(select_sql)?;
+        let ids = stmt.query_map([], |row| row.get(0))?;
+        ids.collect::<Result<Vec<String>>>()?
+    };
+
+    let repo = SqliteAttendanceRepository;
+    in_savepoint(conn, || {
+        let mut updated = 0;
+        for id in ids.iter().filter_map(|id| Uuid::parse_str(id).ok()) {
+            let before = repo.get_attendance(conn, id)?;
+            if !apply(&before)? {
+                break;
+            }
+
+            let after = repo.get_attendance(conn, id)?;
+            SqliteAttendanceAuditRepository.record(
+                conn,
+                id,
+                AttendanceAuditAction::Updated,
+                ACTOR_SYSTEM,
+                Some(&before),
+                Some(&after)
+            )?;
+            updated += 1;
+        }
+        Ok(updated)
+    })
+}
+
+// Rows recorded before semester tracking (or with no active semester) are assigned by creation order
+pub fn backfill_attendance_semesters(conn: &Connection) -> Result<usize> {
+    backfill_attendances(conn, "SELECT id FROM attendance WHERE semester_id IS NULL", |attendance| {
+        match semester_id_for_time(conn, attendance.time_in_date)? {
+            Some(semester_id) => {
+                conn.execute(
+                    "UPDATE attendance SET semester_id = ?1 WHERE id = ?2",
+                    params![semester_id.to_string(), attendance.id.to_string()],
+                )?;
+                Ok(true)
+            },
+            // No semesters at all yet
+            None => Ok(false),
+        }
+    })
+}
+
+// Rows recorded before locations existed happened at the default location
+pub fn backfill_attendance_locations(conn: &Connection) -> Result<usize> {
+    let default_location = match SqliteLocationRepository.get_default_location(conn)? {
+        Some(location) => location,
+        None => return Ok(0),
+    };
+
+    backfill_attendances(conn, "SELECT id FROM attendance WHERE location_id IS NULL", |attendance| {
+        conn.execute(
+            "UPDATE attendance SET location_id = ?1 WHERE id = ?2",
+            params![default_location.id.to_string(), attendance.id.to_string()],
+        )?;
+        Ok(true)
+    })
+}
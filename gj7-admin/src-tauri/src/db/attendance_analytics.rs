@@ -0,0 +1,226 @@
+// src/db/attendance_analytics.rs
+
+use chrono::Utc;
+use rusqlite::{Connection, Result};
+use serde::{Serialize, Deserialize};
+
+use crate::db::attendance::{epoch_seconds_sql, from_epoch_nanos, AttendanceQuery, build_query_conditions};
+use crate::db::attendance_archive::attendance_source;
+use crate::db::institution_time::{institution_timezone, local_seconds_sql};
+
+// Every aggregate buckets on the institution's calendar, matching what the exports print
+fn attendance_from(conn: &Connection, query: &AttendanceQuery) -> Result<String> {
+    Ok(format!(
+        "FROM {} a
+        LEFT JOIN school_accounts sa ON a.school_id = sa.school_id
+        LEFT JOIN locations l ON a.location_id = l.id
+        LEFT JOIN events e ON a.event_id = e.id",
+        attendance_source(conn, query.include_archive)?
+    ))
+}
+
+// Time in on the institution's wall clock, for strftime with 'unixepoch'. Offset changes are looked up
+// over the queried range, or over the stored visits when the query leaves it open
+fn local_time_in(conn: &Connection, query: &AttendanceQuery) -> Result<String> {
+    let (first, last): (Option<i64>, Option<i64>) = conn.query_row(
+        &format!(
+            "SELECT MIN(a.time_in_date), MAX(a.time_in_date) FROM {} a",
+            attendance_source(conn, query.include_archive)?
+        ),
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+
+    let from = query.date_from.or(first.map(from_epoch_nanos)).unwrap_or_else(Utc::now);
+    let to = query.date_to.or(last.map(from_epoch_nanos)).unwrap_or(from);
+    Ok(local_seconds_sql(institution_timezone(conn)?, &epoch_seconds_sql("a.time_in_date"), from, to))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum TimeBucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl TimeBucket {
+    fn strftime_format(&self) -> &'static str {
+        match self {
+            TimeBucket::Day => "%Y-%m-%d",
+            TimeBucket::Week => "%Y-W%W",
+            TimeBucket::Month => "%Y-%m",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum BreakdownDimension {
+    Purpose,
+    Classification,
+    Course,
+    Location,
+    Device,
+    // Group visits against individual check-ins
+    VisitType,
+    Event,
+}
+
+impl BreakdownDimension {
+    fn column(&self) -> &'static str {
+        match self {
+            BreakdownDimension::Purpose => "COALESCE(NULLIF(a.purpose_label, ''), 'Unspecified')",
+            BreakdownDimension::Classification => "COALESCE(NULLIF(a.classification, ''), 'Unspecified')",
+            BreakdownDimension::Course => "COALESCE(NULLIF(sa.course, ''), 'No Course')",
+            BreakdownDimension::Location => "COALESCE(l.name, 'No Location')",
+            BreakdownDimension::Device => "COALESCE(NULLIF(a.device_id, ''), 'Unknown Device')",
+            BreakdownDimension::VisitType => "CASE WHEN a.group_id IS NULL THEN 'Individual' ELSE 'Group Visit' END",
+            BreakdownDimension::Event => "COALESCE(e.name, 'No Event')",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeatmapCell {
+    // 0 = Sunday through 6 = Saturday
+    pub weekday: u32,
+    pub hour: u32,
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimeBucketCount {
+    pub bucket: String,
+    pub count: u64,
+    pub unique_visitors: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BreakdownCount {
+    pub label: String,
+    pub count: u64,
+    pub unique_visitors: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AttendanceSummary {
+    pub total_visits: u64,
+    pub unique_visitors: u64,
+    pub completed_visits: u64,
+    pub average_duration_minutes: Option<f64>,
+}
+
+pub trait AttendanceAnalyticsRepository: Send + Sync {
+    fn get_hourly_heatmap(&self, conn: &Connection, query: &AttendanceQuery) -> Result<Vec<HeatmapCell>>;
+    fn get_time_series(&self, conn: &Connection, query: &AttendanceQuery, bucket: TimeBucket) -> Result<Vec<TimeBucketCount>>;
+    fn get_breakdown(&self, conn: &Connection, query: &AttendanceQuery, dimension: BreakdownDimension) -> Result<Vec<BreakdownCount>>;
+    fn get_summary(&self, conn: &Connection, query: &AttendanceQuery) -> Result<AttendanceSummary>;
+}
+
+pub struct SqliteAttendanceAnalyticsRepository;
+
+impl AttendanceAnalyticsRepository for SqliteAttendanceAnalyticsRepository {
+    fn get_hourly_heatmap(&self, conn: &Connection, query: &AttendanceQuery) -> Result<Vec<HeatmapCell>> {
+        let (where_clause, param_values) = build_query_conditions(query);
+        let sql = format!(
+            "SELECT
+                CAST(strftime('%w', {time_in}, 'unixepoch') AS INTEGER) AS weekday,
+                CAST(strftime('%H', {time_in}, 'unixepoch') AS INTEGER) AS hour,
+                COUNT(*)
+             {} {}
+             GROUP BY weekday, hour
+             ORDER BY weekday, hour",
+            attendance_from(conn, query)?,
+            where_clause,
+            time_in = local_time_in(conn, query)?
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let cells = stmt.query_map(rusqlite::params_from_iter(param_values.iter()), |row| {
+            Ok(HeatmapCell {
+                weekday: row.get(0)?,
+                hour: row.get(1)?,
+                count: row.get(2)?,
+            })
+        })?.collect::<Result<Vec<HeatmapCell>>>()?;
+
+        Ok(cells)
+    }
+
+    fn get_time_series(&self, conn: &Connection, query: &AttendanceQuery, bucket: TimeBucket) -> Result<Vec<TimeBucketCount>> {
+        let (where_clause, param_values) = build_query_conditions(query);
+        let sql = format!(
+            "SELECT
+                strftime('{}', {time_in}, 'unixepoch') AS bucket,
+                COUNT(*),
+                COUNT(DISTINCT a.school_id)
+             {} {}
+             GROUP BY bucket
+             ORDER BY bucket",
+            bucket.strftime_format(),
+            attendance_from(conn, query)?,
+            where_clause,
+            time_in = local_time_in(conn, query)?
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let buckets = stmt.query_map(rusqlite::params_from_iter(param_values.iter()), |row| {
+            Ok(TimeBucketCount {
+                bucket: row.get(0)?,
+                count: row.get(1)?,
+                unique_visitors: row.get(2)?,
+            })
+        })?.collect::<Result<Vec<TimeBucketCount>>>()?;
+
+        Ok(buckets)
+    }
+
+    fn get_breakdown(&self, conn: &Connection, query: &AttendanceQuery, dimension: BreakdownDimension) -> Result<Vec<BreakdownCount>> {
+        let (where_clause, param_values) = build_query_conditions(query);
+        let sql = format!(
+            "SELECT
+                {} AS label,
+                COUNT(*) AS visits,
+                COUNT(DISTINCT a.school_id)
+             {} {}
+             GROUP BY label
+             ORDER BY visits DESC, label ASC",
+            dimension.column(),
+            attendance_from(conn, query)?,
+            where_clause
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let breakdown = stmt.query_map(rusqlite::params_from_iter(param_values.iter()), |row| {
+            Ok(BreakdownCount {
+                label: row.get(0)?,
+                count: row.get(1)?,
+                unique_visitors: row.get(2)?,
+            })
+        })?.collect::<Result<Vec<BreakdownCount>>>()?;
+
+        Ok(breakdown)
+    }
+
+    fn get_summary(&self, conn: &Connection, query: &AttendanceQuery) -> Result<AttendanceSummary> {
+        let (where_clause, param_values) = build_query_conditions(query);
+        let sql = format!(
+            "SELECT
+                COUNT(*),
+                COUNT(DISTINCT a.school_id),
+                COUNT(a.time_out_date),
+                AVG(a.duration_minutes)
+             {} {}",
+            attendance_from(conn, query)?,
+            where_clause
+        );
+
+        conn.query_row(&sql, rusqlite::params_from_iter(param_values.iter()), |row| {
+            Ok(AttendanceSummary {
+                total_visits: row.get(0)?,
+                unique_visitors: row.get(1)?,
+                completed_visits: row.get(2)?,
+                average_duration_minutes: row.get(3)?,
+            })
+        })
+    }
+}
@@ -0,0 +1,324 @@
+// src/db/attendance_archive.rs
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use chrono::{DateTime, Months, Utc};
+use log::info;
+use rusqlite::{params, Connection, Result};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::app_settings::{AppSettingsDatabase, RETENTION_KEEP_SEMESTERS, RETENTION_KEEP_YEARS};
+use crate::db::attendance::{
+    convert_text_timestamps,
+    epoch_nanos_bound,
+    from_epoch_nanos,
+    has_text_timestamps,
+    in_savepoint,
+    AttendanceRepository,
+    SqliteAttendanceRepository,
+};
+use crate::db::attendance_audit::{
+    AttendanceAuditAction,
+    AttendanceAuditRepository,
+    SqliteAttendanceAuditRepository,
+    ACTOR_SYSTEM,
+};
+
+// Schema name the archive is attached under, read-only, by connections that report on it
+const ARCHIVE_SCHEMA: &str = "archive";
+// Attached read-write only by the connection that moves rows into the archive
+const ARCHIVE_WRITER_SCHEMA: &str = "archive_writer";
+// Rows moved per pair of copy and delete transactions
+const ARCHIVE_BATCH_SIZE: usize = 1000;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchiveStatus {
+    pub archive_path: String,
+    pub archived_count: u64,
+    pub oldest_time_in: Option<DateTime<Utc>>,
+    pub newest_time_in: Option<DateTime<Utc>>,
+    // 0 disables the rule
+    pub keep_semesters: i64,
+    pub keep_years: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchiveRunResult {
+    pub archive_path: String,
+    // Rows copied into the archive and removed from the live table
+    pub archived: usize,
+    // Rows edited between the copy and the delete; they stay live and are moved on the next run
+    pub skipped: usize,
+}
+
+// The archive sits next to the live database in AppStorage::get_database_dir, named after it
+pub fn archive_path(db_path: &Path) -> PathBuf {
+    let stem = db_path.file_stem().and_then(|s| s.to_str()).unwrap_or("attendance");
+    db_path.with_file_name(format!("{}_archive.db", stem))
+}
+
+fn archive_path_for(conn: &Connection) -> Result<PathBuf> {
+    let main_path: String = conn.query_row(
+        "SELECT file FROM pragma_database_list WHERE name = 'main'",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(archive_path(Path::new(&main_path)))
+}
+
+// SQLite URI opening the file read-only; the characters URIs reserve are percent-encoded
+fn read_only_uri(path: &Path) -> String {
+    let mut encoded = String::new();
+    for c in path.to_string_lossy().replace('\\', "/").chars() {
+        match c {
+            '%' => encoded.push_str("%25"),
+            '?' => encoded.push_str("%3f"),
+            '#' => encoded.push_str("%23"),
+            ' ' => encoded.push_str("%20"),
+            c => encoded.push(c),
+        }
+    }
+
+    // Windows paths start with a drive letter rather than a slash
+    if encoded.starts_with('/') {
+        format!("file://{}?mode=ro", encoded)
+    } else {
+        format!("file:///{}?mode=ro", encoded)
+    }
+}
+
+fn is_attached(conn: &Connection, schema: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_database_list WHERE name = ?1)",
+        params![schema],
+        |row| row.get(0),
+    )
+}
+
+fn attendance_columns(conn: &Connection, schema: &str) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info(attendance)", schema))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    columns.collect()
+}
+
+// Attaches the archive read-only; pooled connections keep it attached once done.
+// Must be called outside a transaction
+pub(crate) fn attach_archive(conn: &Connection) -> Result<()> {
+    if is_attached(conn, ARCHIVE_SCHEMA)? {
+        return Ok(());
+    }
+
+    let uri = read_only_uri(&archive_path_for(conn)?);
+    conn.execute(&format!("ATTACH DATABASE ?1 AS {}", ARCHIVE_SCHEMA), params![uri])?;
+    Ok(())
+}
+
+// Table expression to select attendance from as `a`. With the archive included, rows that an
+// interrupted move left in both databases are taken from the live copy only
+pub(crate) fn attendance_source(conn: &Connection, include_archive: bool) -> Result<String> {
+    if !include_archive {
+        return Ok("attendance".to_string());
+    }
+
+    attach_archive(conn)?;
+    let columns = attendance_columns(conn, "main")?.join(", ");
+    Ok(format!(
+        "(SELECT {columns} FROM main.attendance
+          UNION ALL
+          SELECT {columns} FROM {archive}.attendance
+          WHERE id NOT IN (SELECT id FROM main.attendance))",
+        columns = columns,
+        archive = ARCHIVE_SCHEMA
+    ))
+}
+
+// Creates the archive file on first start and adds any columns the live table gained since
+pub fn create_archive_database(conn: &Connection) -> Result<()> {
+    let path = archive_path_for(conn)?;
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {}", ARCHIVE_WRITER_SCHEMA),
+        params![path.to_string_lossy().to_string()],
+    )?;
+
+    let result = sync_archive_schema(conn);
+    conn.execute(&format!("DETACH DATABASE {}", ARCHIVE_WRITER_SCHEMA), [])?;
+    result
+}
+
+fn sync_archive_schema(conn: &Connection) -> Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TABLE IF NOT EXISTS {}.attendance AS SELECT * FROM main.attendance WHERE 0",
+            ARCHIVE_WRITER_SCHEMA
+        ),
+        [],
+    )?;
+
+    let archived_columns = attendance_columns(conn, ARCHIVE_WRITER_SCHEMA)?;
+    for column in attendance_columns(conn, "main")? {
+        if !archived_columns.contains(&column) {
+            info!("Adding column {} to the attendance archive", column);
+            conn.execute(&format!("ALTER TABLE {}.attendance ADD COLUMN {}", ARCHIVE_WRITER_SCHEMA, column), [])?;
+        }
+    }
+
+    // Archives written before the live table moved to integer times are converted the same way
+    if has_text_timestamps(conn, ARCHIVE_WRITER_SCHEMA)? {
+        let converted = convert_text_timestamps(
+            conn,
+            ARCHIVE_WRITER_SCHEMA,
+            &format!("CREATE TABLE {}.attendance AS SELECT * FROM main.attendance WHERE 0", ARCHIVE_WRITER_SCHEMA)
+        )?;
+        info!("Converted {} archived attendance records to integer timestamps", converted);
+    }
+
+    conn.execute_batch(&format!(
+        "CREATE UNIQUE INDEX IF NOT EXISTS {schema}.idx_archive_attendance_id ON attendance(id);
+         CREATE INDEX IF NOT EXISTS {schema}.idx_archive_attendance_time_in_date ON attendance(time_in_date);
+         CREATE INDEX IF NOT EXISTS {schema}.idx_archive_attendance_school_id ON attendance(school_id, time_in_date);
+         CREATE INDEX IF NOT EXISTS {schema}.idx_archive_attendance_semester_id ON attendance(semester_id);",
+        schema = ARCHIVE_WRITER_SCHEMA
+    ))
+}
+
+pub fn get_archive_status(conn: &Connection) -> Result<ArchiveStatus> {
+    attach_archive(conn)?;
+    let settings = AppSettingsDatabase;
+
+    let (archived_count, oldest_time_in, newest_time_in) = conn.query_row(
+        &format!("SELECT COUNT(*), MIN(time_in_date), MAX(time_in_date) FROM {}.attendance", ARCHIVE_SCHEMA),
+        [],
+        |row| Ok((
+            row.get::<_, i64>(0)? as u64,
+            row.get::<_, Option<i64>>(1)?.map(from_epoch_nanos),
+            row.get::<_, Option<i64>>(2)?.map(from_epoch_nanos),
+        )),
+    )?;
+
+    Ok(ArchiveStatus {
+        archive_path: archive_path_for(conn)?.to_string_lossy().to_string(),
+        archived_count,
+        oldest_time_in,
+        newest_time_in,
+        keep_semesters: settings.get_i64(conn, RETENTION_KEEP_SEMESTERS, 0)?,
+        keep_years: settings.get_i64(conn, RETENTION_KEEP_YEARS, 0)?,
+    })
+}
+
+// Closed visits older than the retention settings allow. The active semester is never archived
+fn archivable_ids(conn: &Connection, now: DateTime<Utc>) -> Result<Vec<String>> {
+    let settings = AppSettingsDatabase;
+    let keep_semesters = settings.get_i64(conn, RETENTION_KEEP_SEMESTERS, 0)?.max(0);
+    let keep_years = settings.get_i64(conn, RETENTION_KEEP_YEARS, 0)?.max(0);
+
+    let cutoff = match keep_years {
+        0 => None,
+        years => now.checked_sub_months(Months::new(years as u32 * 12)).map(epoch_nanos_bound),
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT a.id FROM main.attendance a
+         WHERE a.time_out_date IS NOT NULL
+           AND (a.semester_id IS NULL OR a.semester_id NOT IN (SELECT id FROM semesters WHERE is_active = 1))
+           AND (
+               (?1 IS NOT NULL AND a.time_in_date < ?1)
+               OR (?2 > 0 AND a.semester_id IS NOT NULL
+                   AND a.semester_id NOT IN (SELECT id FROM semesters ORDER BY created_at DESC LIMIT ?2))
+           )
+         ORDER BY a.time_in_date ASC"
+    )?;
+
+    let ids = stmt.query_map(params![cutoff, keep_semesters], |row| row.get(0))?;
+    ids.collect()
+}
+
+fn archive_batch(conn: &Connection, ids: &[String], columns: &str) -> Result<(usize, usize)> {
+    let placeholders = ids.iter().map(|_| "?").collect::<Vec<&str>>().join(",");
+
+    // Copy first, in a transaction of its own on the archive file. Replacing keeps the copy in
+    // step with the live row when a previous run was interrupted before its delete
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        &format!(
+            "INSERT OR REPLACE INTO {schema}.attendance ({columns})
+             SELECT {columns} FROM main.attendance WHERE id IN ({placeholders})",
+            schema = ARCHIVE_WRITER_SCHEMA,
+            columns = columns,
+            placeholders = placeholders
+        ),
+        rusqlite::params_from_iter(ids.iter()),
+    )?;
+    tx.commit()?;
+
+    // Then delete from the live table, only rows whose archived copy matches them exactly. Each
+    // removal is logged so the hash chain accounts for every row that leaves
+    let repo = SqliteAttendanceRepository;
+    in_savepoint(conn, || {
+        let mut archived = 0;
+        let mut skipped = 0;
+        for id in ids.iter().filter_map(|id| Uuid::parse_str(id).ok()) {
+            let copied: bool = conn.query_row(
+                &format!(
+                    "SELECT EXISTS(
+                        SELECT 1 FROM main.attendance a JOIN {}.attendance x ON x.id = a.id
+                        WHERE a.id = ?1 AND x.chain_hash IS a.chain_hash
+                    )",
+                    ARCHIVE_WRITER_SCHEMA
+                ),
+                params![id.to_string()],
+                |row| row.get(0),
+            )?;
+            if !copied {
+                skipped += 1;
+                continue;
+            }
+
+            let attendance = repo.get_attendance(conn, id)?;
+            SqliteAttendanceAuditRepository.record(conn, id, AttendanceAuditAction::Archived, ACTOR_SYSTEM, Some(&attendance), None)?;
+            conn.execute("DELETE FROM main.attendance WHERE id = ?1", params![id.to_string()])?;
+            archived += 1;
+        }
+        Ok((archived, skipped))
+    })
+}
+
+// Moves attendance past the retention settings into the archive. Runs on a connection of its own,
+// since it attaches the archive read-write
+pub fn run_retention(db_path: &Path, now: DateTime<Utc>) -> Result<ArchiveRunResult> {
+    let conn = Connection::open(db_path)?;
+    conn.busy_timeout(Duration::from_secs(300))?;
+    create_archive_database(&conn)?;
+
+    let path = archive_path(db_path);
+    conn.execute(
+        &format!("ATTACH DATABASE ?1 AS {}", ARCHIVE_WRITER_SCHEMA),
+        params![path.to_string_lossy().to_string()],
+    )?;
+
+    let result: Result<(usize, usize)> = (|| {
+        let columns = attendance_columns(&conn, "main")?.join(", ");
+        let mut archived = 0;
+        let mut skipped = 0;
+
+        for batch in archivable_ids(&conn, now)?.chunks(ARCHIVE_BATCH_SIZE) {
+            let (batch_archived, batch_skipped) = archive_batch(&conn, batch, &columns)?;
+            archived += batch_archived;
+            skipped += batch_skipped;
+        }
+        Ok((archived, skipped))
+    })();
+
+    conn.execute(&format!("DETACH DATABASE {}", ARCHIVE_WRITER_SCHEMA), [])?;
+    let (archived, skipped) = result?;
+
+    if archived > 0 {
+        info!("Archived {} attendance records to {:?}", archived, path);
+    }
+
+    Ok(ArchiveRunResult {
+        archive_path: path.to_string_lossy().to_string(),
+        archived,
+        skipped,
+    })
+}
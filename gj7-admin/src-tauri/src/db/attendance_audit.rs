@@ -0,0 +1,348 @@
+// src/db/attendance_audit.rs
+
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::db::add_column_if_missing;
+use crate::db::attendance::Attendance;
+
+// Actors for mutations that are not made by a logged in admin
+pub const ACTOR_KIOSK: &str = "kiosk";
+pub const ACTOR_SYSTEM: &str = "system";
+
+// prev_hash of the first chained entry
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AttendanceAuditAction {
+    Created,
+    Imported,
+    Updated,
+    CheckedOut,
+    AutoClosed,
+    Deleted,
+    Restored,
+    // Backdated or bulk entry made by an admin, e.g. a missed scan or a class visit
+    ManualEntry,
+    // Rows that existed before the hash chain, brought into it as they were
+    Sealed,
+    // Moved to the archive database by the retention policy; `before` is the row as it left
+    Archived,
+}
+
+impl AttendanceAuditAction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AttendanceAuditAction::Created => "Created",
+            AttendanceAuditAction::Imported => "Imported",
+            AttendanceAuditAction::Updated => "Updated",
+            AttendanceAuditAction::CheckedOut => "CheckedOut",
+            AttendanceAuditAction::AutoClosed => "AutoClosed",
+            AttendanceAuditAction::Deleted => "Deleted",
+            AttendanceAuditAction::Restored => "Restored",
+            AttendanceAuditAction::ManualEntry => "ManualEntry",
+            AttendanceAuditAction::Sealed => "Sealed",
+            AttendanceAuditAction::Archived => "Archived",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "Created" => Some(AttendanceAuditAction::Created),
+            "Imported" => Some(AttendanceAuditAction::Imported),
+            "Updated" => Some(AttendanceAuditAction::Updated),
+            "CheckedOut" => Some(AttendanceAuditAction::CheckedOut),
+            "AutoClosed" => Some(AttendanceAuditAction::AutoClosed),
+            "Deleted" => Some(AttendanceAuditAction::Deleted),
+            "Restored" => Some(AttendanceAuditAction::Restored),
+            "ManualEntry" => Some(AttendanceAuditAction::ManualEntry),
+            "Sealed" => Some(AttendanceAuditAction::Sealed),
+            "Archived" => Some(AttendanceAuditAction::Archived),
+            _ => None,
+        }
+    }
+}
+
+// Snapshots are kept as raw JSON so entries written by older versions still load
+#[derive(Debug, Serialize, Clone)]
+pub struct AttendanceAuditEntry {
+    pub id: Uuid,
+    pub attendance_id: Uuid,
+    pub action: AttendanceAuditAction,
+    pub actor: String,
+    pub changed_at: DateTime<Utc>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    // None for entries written before the log was hash chained
+    pub prev_hash: Option<String>,
+    pub entry_hash: Option<String>,
+}
+
+const AUDIT_COLUMNS: &str = "id, attendance_id, action, actor, changed_at, before_json, after_json, prev_hash, entry_hash";
+
+// An entry's fields as they are stored, which is what its hash covers
+pub struct AuditEntryText<'a> {
+    pub id: &'a str,
+    pub attendance_id: &'a str,
+    pub action: &'a str,
+    pub actor: &'a str,
+    pub changed_at: &'a str,
+    pub before_json: Option<&'a str>,
+    pub after_json: Option<&'a str>,
+}
+
+// Hash of one entry over its stored text, chained to the entry before it
+pub fn compute_entry_hash(prev_hash: &str, entry: &AuditEntryText) -> String {
+    let fields = [
+        prev_hash,
+        entry.id,
+        entry.attendance_id,
+        entry.action,
+        entry.actor,
+        entry.changed_at,
+        entry.before_json.unwrap_or(""),
+        entry.after_json.unwrap_or(""),
+    ];
+
+    let mut hasher = Sha256::new();
+    for field in fields {
+        hasher.update(field.as_bytes());
+        hasher.update([0x1f]);
+    }
+    hex::encode(hasher.finalize())
+}
+
+// Hash of the newest chained entry, i.e. the head of the chain
+pub fn chain_head(conn: &Connection) -> Result<String> {
+    let head: Option<String> = conn.query_row(
+        "SELECT entry_hash FROM attendance_audit_log WHERE entry_hash IS NOT NULL ORDER BY rowid DESC LIMIT 1",
+        [],
+        |row| row.get(0),
+    ).optional()?;
+
+    Ok(head.unwrap_or_else(|| GENESIS_HASH.to_string()))
+}
+
+fn parse_json_column(row: &Row, idx: usize) -> Result<Option<serde_json::Value>> {
+    match row.get::<_, Option<String>>(idx)? {
+        Some(json) => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))),
+        None => Ok(None),
+    }
+}
+
+fn row_to_audit_entry(row: &Row) -> Result<AttendanceAuditEntry> {
+    let action: String = row.get(2)?;
+    let changed_at: String = row.get(4)?;
+
+    Ok(AttendanceAuditEntry {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        attendance_id: Uuid::parse_str(&row.get::<_, String>(1)?).unwrap(),
+        action: AttendanceAuditAction::from_str(&action).ok_or_else(|| {
+            rusqlite::Error::InvalidColumnType(2, action.clone(), rusqlite::types::Type::Text)
+        })?,
+        actor: row.get(3)?,
+        changed_at: DateTime::parse_from_rfc3339(&changed_at)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(e)))?,
+        before: parse_json_column(row, 5)?,
+        after: parse_json_column(row, 6)?,
+        prev_hash: row.get(7)?,
+        entry_hash: row.get(8)?,
+    })
+}
+
+fn to_json(attendance: Option<&Attendance>) -> Result<Option<String>> {
+    attendance
+        .map(|a| serde_json::to_string(a)
+            .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e))))
+        .transpose()
+}
+
+pub trait AttendanceAuditRepository: Send + Sync {
+    fn record(
+        &self,
+        conn: &Connection,
+        attendance_id: Uuid,
+        action: AttendanceAuditAction,
+        actor: &str,
+        before: Option<&Attendance>,
+        after: Option<&Attendance>
+    ) -> Result<AttendanceAuditEntry>;
+    // Oldest first
+    fn get_history(&self, conn: &Connection, attendance_id: Uuid) -> Result<Vec<AttendanceAuditEntry>>;
+    fn get_recent_entries(&self, conn: &Connection, limit: usize) -> Result<Vec<AttendanceAuditEntry>>;
+    // Latest Deleted entry of every attendance that has not been restored since
+    fn get_deleted_entries(&self, conn: &Connection) -> Result<Vec<AttendanceAuditEntry>>;
+    fn get_last_deleted_entry(&self, conn: &Connection, attendance_id: Uuid) -> Result<Option<AttendanceAuditEntry>>;
+}
+
+pub struct SqliteAttendanceAuditRepository;
+
+impl AttendanceAuditRepository for SqliteAttendanceAuditRepository {
+    fn record(
+        &self,
+        conn: &Connection,
+        attendance_id: Uuid,
+        action: AttendanceAuditAction,
+        actor: &str,
+        before: Option<&Attendance>,
+        after: Option<&Attendance>
+    ) -> Result<AttendanceAuditEntry> {
+        let id = Uuid::new_v4();
+        let changed_at = Utc::now();
+        let before_json = to_json(before)?;
+        let after_json = to_json(after)?;
+
+        // Runs inside the caller's savepoint, so no other connection can extend the chain in between
+        let prev_hash = chain_head(conn)?;
+        let entry_hash = compute_entry_hash(&prev_hash, &AuditEntryText {
+            id: &id.to_string(),
+            attendance_id: &attendance_id.to_string(),
+            action: action.as_str(),
+            actor,
+            changed_at: &changed_at.to_rfc3339(),
+            before_json: before_json.as_deref(),
+            after_json: after_json.as_deref(),
+        });
+
+        conn.execute(
+            "INSERT INTO attendance_audit_log (
+                id, attendance_id, action, actor, changed_at, before_json, after_json, prev_hash, entry_hash
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                id.to_string(),
+                attendance_id.to_string(),
+                action.as_str(),
+                actor,
+                changed_at.to_rfc3339(),
+                before_json,
+                after_json,
+                prev_hash,
+                entry_hash
+            ],
+        )?;
+
+        // The row carries the hash of the entry that produced its current state
+        if after.is_some() {
+            conn.execute(
+                "UPDATE attendance SET chain_hash = ?1 WHERE id = ?2",
+                params![entry_hash, attendance_id.to_string()],
+            )?;
+        }
+
+        Ok(AttendanceAuditEntry {
+            id,
+            attendance_id,
+            action,
+            actor: actor.to_string(),
+            changed_at,
+            before: before.and_then(|a| serde_json::to_value(a).ok()),
+            after: after.and_then(|a| serde_json::to_value(a).ok()),
+            prev_hash: Some(prev_hash),
+            entry_hash: Some(entry_hash),
+        })
+    }
+
+    fn get_history(&self, conn: &Connection, attendance_id: Uuid) -> Result<Vec<AttendanceAuditEntry>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attendance_audit_log WHERE attendance_id = ?1 ORDER BY changed_at ASC, rowid ASC",
+            AUDIT_COLUMNS
+        ))?;
+
+        let entries = stmt.query_map(params![attendance_id.to_string()], row_to_audit_entry)?;
+        entries.collect()
+    }
+
+    fn get_recent_entries(&self, conn: &Connection, limit: usize) -> Result<Vec<AttendanceAuditEntry>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attendance_audit_log ORDER BY changed_at DESC, rowid DESC LIMIT ?1",
+            AUDIT_COLUMNS
+        ))?;
+
+        let entries = stmt.query_map(params![limit as i64], row_to_audit_entry)?;
+        entries.collect()
+    }
+
+    fn get_deleted_entries(&self, conn: &Connection) -> Result<Vec<AttendanceAuditEntry>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM attendance_audit_log l
+             WHERE l.action = 'Deleted'
+               AND NOT EXISTS (SELECT 1 FROM attendance a WHERE a.id = l.attendance_id)
+               AND NOT EXISTS (
+                   SELECT 1 FROM attendance_audit_log x
+                   WHERE x.attendance_id = l.attendance_id AND x.action = 'Archived' AND x.rowid > l.rowid
+               )
+               AND l.rowid = (
+                   SELECT MAX(rowid) FROM attendance_audit_log
+                   WHERE attendance_id = l.attendance_id AND action = 'Deleted'
+               )
+             ORDER BY l.changed_at DESC",
+            AUDIT_COLUMNS
+        ))?;
+
+        let entries = stmt.query_map([], row_to_audit_entry)?;
+        entries.collect()
+    }
+
+    fn get_last_deleted_entry(&self, conn: &Connection, attendance_id: Uuid) -> Result<Option<AttendanceAuditEntry>> {
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM attendance_audit_log
+                 WHERE attendance_id = ?1 AND action = 'Deleted'
+                 ORDER BY rowid DESC LIMIT 1",
+                AUDIT_COLUMNS
+            ),
+            params![attendance_id.to_string()],
+            row_to_audit_entry,
+        ).optional()
+    }
+}
+
+pub fn create_attendance_audit_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS attendance_audit_log (
+            id TEXT PRIMARY KEY,
+            attendance_id TEXT NOT NULL,
+            action TEXT NOT NULL,
+            actor TEXT NOT NULL,
+            changed_at TEXT NOT NULL,
+            before_json TEXT,
+            after_json TEXT,
+            prev_hash TEXT,
+            entry_hash TEXT
+        )",
+        [],
+    )?;
+
+    // Entries from before the hash chain keep NULL hashes
+    add_column_if_missing(conn, "attendance_audit_log", "prev_hash", "TEXT")?;
+    add_column_if_missing(conn, "attendance_audit_log", "entry_hash", "TEXT")?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_attendance_audit_log_attendance_id
+         ON attendance_audit_log(attendance_id)",
+        [],
+    )?;
+
+    // Append-only: entries can be added but never changed or removed
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS attendance_audit_log_no_update
+         BEFORE UPDATE ON attendance_audit_log
+         BEGIN
+             SELECT RAISE(ABORT, 'attendance_audit_log is append-only');
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS attendance_audit_log_no_delete
+         BEFORE DELETE ON attendance_audit_log
+         BEGIN
+             SELECT RAISE(ABORT, 'attendance_audit_log is append-only');
+         END;"
+    )?;
+
+    Ok(())
+}
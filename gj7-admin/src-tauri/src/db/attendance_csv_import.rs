@@ -0,0 +1,431 @@
+// src/db/attendance_csv_import.rs
+
+use std::collections::HashMap;
+use std::path::Path;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use csv::{Reader, StringRecord};
+use rusqlite::{Connection, Result};
+use serde::{Serialize, Deserialize};
+
+use crate::db::attendance::{to_epoch_nanos, AttendanceRepository, ImportAttendanceRequest, SqliteAttendanceRepository};
+use crate::db::classification::{ClassificationRepository, SqliteClassificationRepository};
+use crate::db::csv_import::{ValidationError, ValidationErrorType};
+use crate::db::institution_time::{institution_timezone, local_date};
+use crate::db::school_accounts::{SchoolAccountRepository, SqliteSchoolAccountRepository};
+
+// 50MB is far more than any logbook transcription
+const MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+// Paper logs are written to the minute, so anything this close to an existing time in is the same visit
+pub const DUPLICATE_TOLERANCE_SECONDS: i64 = 60;
+
+// Optional: full_name, date, time_out, classification, purpose; other columns are ignored
+const REQUIRED_HEADERS: [&str; 2] = ["school_id", "time_in"];
+
+const DATETIME_FORMATS: [&str; 6] = [
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%dT%H:%M",
+    "%m/%d/%Y %H:%M",
+    "%m/%d/%Y %I:%M %p",
+];
+const DATE_FORMATS: [&str; 2] = ["%Y-%m-%d", "%m/%d/%Y"];
+const TIME_FORMATS: [&str; 4] = ["%H:%M:%S", "%H:%M", "%I:%M %p", "%I:%M%p"];
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum AttendanceImportRowStatus {
+    // Ready to be inserted
+    New,
+    // Matches an attendance already in the database or an earlier row of the file
+    Duplicate,
+    // Failed validation, see the errors list for the reason
+    Invalid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AttendanceImportRow {
+    pub row_number: usize,
+    pub school_id: String,
+    pub full_name: String,
+    pub time_in_date: Option<DateTime<Utc>>,
+    pub time_out_date: Option<DateTime<Utc>>,
+    pub classification: Option<String>,
+    pub purpose_label: Option<String>,
+    // Whether the school_id belongs to a known school account
+    pub matched_account: bool,
+    pub status: AttendanceImportRowStatus,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttendanceCsvValidationResult {
+    pub is_valid: bool,
+    pub file_name: String,
+    pub total_rows: usize,
+    pub new_rows: usize,
+    pub duplicate_rows: usize,
+    pub invalid_rows: usize,
+    pub unmatched_rows: usize,
+    pub rows: Vec<AttendanceImportRow>,
+    pub errors: Vec<ValidationError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttendanceCsvImportResult {
+    pub validation_result: AttendanceCsvValidationResult,
+    pub imported: usize,
+    pub skipped_duplicates: usize,
+    pub skipped_invalid: usize,
+}
+
+fn file_error(error_type: ValidationErrorType, message: &str) -> Vec<ValidationError> {
+    vec![ValidationError {
+        row_number: 0,
+        field: None,
+        error_type,
+        error_message: message.to_string(),
+    }]
+}
+
+fn row_error(row_number: usize, field: &str, error_type: ValidationErrorType, message: String) -> ValidationError {
+    ValidationError {
+        row_number,
+        field: Some(field.to_string()),
+        error_type,
+        error_message: message,
+    }
+}
+
+// Logbook times are written on the institution's clock
+fn local_to_utc(tz: Tz, naive: NaiveDateTime) -> Option<DateTime<Utc>> {
+    tz.from_local_datetime(&naive).earliest().map(|dt| dt.with_timezone(&Utc))
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    DATE_FORMATS.iter().find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    TIME_FORMATS.iter().find_map(|format| NaiveTime::parse_from_str(&value.to_uppercase(), format).ok())
+}
+
+// Accepts an RFC 3339 timestamp, a full local date and time, or a bare time on `date`
+fn parse_timestamp(tz: Tz, value: &str, date: Option<NaiveDate>) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Some(dt.with_timezone(&Utc));
+    }
+
+    if let Some(naive) = DATETIME_FORMATS.iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    {
+        return local_to_utc(tz, naive);
+    }
+
+    let time = parse_time(value)?;
+    local_to_utc(tz, date?.and_time(time))
+}
+
+pub(crate) fn compose_full_name(first: Option<String>, middle: Option<String>, last: Option<String>) -> String {
+    [first, middle, last]
+        .into_iter()
+        .flatten()
+        .map(|part| part.trim().to_string())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+pub struct AttendanceCsvImporter;
+
+impl AttendanceCsvImporter {
+    pub fn read_file(&self, file_path: &Path) -> std::result::Result<(StringRecord, Vec<StringRecord>), Vec<ValidationError>> {
+        let metadata = std::fs::metadata(file_path)
+            .map_err(|_| file_error(ValidationErrorType::FileSize, "Unable to read file metadata"))?;
+
+        if metadata.len() > MAX_FILE_SIZE {
+            return Err(file_error(
+                ValidationErrorType::FileSize,
+                &format!("File exceeds maximum size of {} bytes", MAX_FILE_SIZE),
+            ));
+        }
+
+        let extension = file_path.extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or("");
+
+        if extension.to_lowercase() != "csv" {
+            return Err(file_error(ValidationErrorType::FileType, "Invalid file type. Only .csv files are allowed"));
+        }
+
+        let buffer = std::fs::read(file_path)
+            .map_err(|_| file_error(ValidationErrorType::Encoding, "Unable to open file"))?;
+
+        if std::str::from_utf8(&buffer).is_err() {
+            return Err(file_error(ValidationErrorType::Encoding, "File is not valid UTF-8"));
+        }
+
+        let mut rdr = Reader::from_reader(std::io::Cursor::new(buffer));
+        let headers = rdr.headers()
+            .map_err(|_| file_error(ValidationErrorType::HeaderMissing, "Unable to read CSV headers"))?
+            .clone();
+
+        self.validate_headers(&headers)?;
+
+        let mut records = Vec::new();
+        for (idx, result) in rdr.records().enumerate() {
+            match result {
+                Ok(record) => records.push(record),
+                Err(_) => return Err(vec![ValidationError {
+                    row_number: idx + 2,
+                    field: None,
+                    error_type: ValidationErrorType::DataIntegrity,
+                    error_message: "Invalid CSV record".to_string(),
+                }]),
+            }
+        }
+
+        Ok((headers, records))
+    }
+
+    pub fn validate_headers(&self, headers: &StringRecord) -> std::result::Result<(), Vec<ValidationError>> {
+        let header_names: Vec<String> = headers.iter().map(|h| h.trim().to_lowercase()).collect();
+
+        let errors: Vec<ValidationError> = REQUIRED_HEADERS.iter()
+            .filter(|required| !header_names.iter().any(|h| h == *required))
+            .map(|header| ValidationError {
+                row_number: 0,
+                field: Some(header.to_string()),
+                error_type: ValidationErrorType::HeaderMissing,
+                error_message: format!("Missing required header: {}", header),
+            })
+            .collect();
+
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    // Parses every row, resolves names from school_accounts and flags duplicates; nothing is written
+    pub fn validate_records(
+        &self,
+        conn: &Connection,
+        file_name: String,
+        headers: &StringRecord,
+        records: &[StringRecord]
+    ) -> Result<AttendanceCsvValidationResult> {
+        let school_accounts = SqliteSchoolAccountRepository;
+        let classifications = SqliteClassificationRepository;
+        let attendances = SqliteAttendanceRepository;
+        let tz = institution_timezone(conn)?;
+
+        let header_index = |name: &str| headers.iter().position(|h| h.trim().to_lowercase() == name);
+        let (school_id_idx, full_name_idx, date_idx, time_in_idx, time_out_idx, classification_idx, purpose_idx) = (
+            header_index("school_id"),
+            header_index("full_name"),
+            header_index("date"),
+            header_index("time_in"),
+            header_index("time_out"),
+            header_index("classification"),
+            header_index("purpose"),
+        );
+
+        let mut rows = Vec::with_capacity(records.len());
+        let mut errors = Vec::new();
+        let mut seen: HashMap<String, Vec<DateTime<Utc>>> = HashMap::new();
+
+        for (idx, record) in records.iter().enumerate() {
+            // Row 1 is the header
+            let row_number = idx + 2;
+            let value = |index: Option<usize>| -> Option<String> {
+                index.and_then(|i| record.get(i))
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty())
+            };
+            let mut row_errors = Vec::new();
+
+            let school_id = value(school_id_idx).unwrap_or_default();
+            if school_id.is_empty() {
+                row_errors.push(row_error(row_number, "school_id", ValidationErrorType::DataIntegrity, "School ID is required".to_string()));
+            }
+
+            let date = match value(date_idx) {
+                Some(raw) => match parse_date(&raw) {
+                    Some(date) => Some(date),
+                    None => {
+                        row_errors.push(row_error(row_number, "date", ValidationErrorType::TypeMismatch, format!("Invalid date: {}", raw)));
+                        None
+                    }
+                },
+                None => None,
+            };
+
+            let time_in_date = match value(time_in_idx) {
+                Some(raw) => match parse_timestamp(tz, &raw, date) {
+                    // Years like "0225" parse fine but cannot be stored
+                    Some(time_in) if to_epoch_nanos(time_in).is_err() => {
+                        row_errors.push(row_error(row_number, "time_in", ValidationErrorType::DataIntegrity, format!("Time in is outside the supported date range: {}", raw)));
+                        None
+                    },
+                    Some(time_in) => Some(time_in),
+                    None => {
+                        row_errors.push(row_error(row_number, "time_in", ValidationErrorType::TypeMismatch, format!("Invalid time in: {}", raw)));
+                        None
+                    }
+                },
+                None => {
+                    row_errors.push(row_error(row_number, "time_in", ValidationErrorType::DataIntegrity, "Time in is required".to_string()));
+                    None
+                }
+            };
+
+            // A bare time out belongs to the same day as the time in
+            let time_out_date = match value(time_out_idx) {
+                Some(raw) => {
+                    let day = date.or_else(|| time_in_date.map(|t| local_date(tz, t)));
+                    match parse_timestamp(tz, &raw, day) {
+                        Some(time_out) if to_epoch_nanos(time_out).is_err() => {
+                            row_errors.push(row_error(row_number, "time_out", ValidationErrorType::DataIntegrity, format!("Time out is outside the supported date range: {}", raw)));
+                            None
+                        },
+                        Some(time_out) if time_in_date.is_none_or(|time_in| time_out >= time_in) => Some(time_out),
+                        Some(_) => {
+                            row_errors.push(row_error(row_number, "time_out", ValidationErrorType::DataIntegrity, "Time out is before time in".to_string()));
+                            None
+                        },
+                        None => {
+                            row_errors.push(row_error(row_number, "time_out", ValidationErrorType::TypeMismatch, format!("Invalid time out: {}", raw)));
+                            None
+                        }
+                    }
+                },
+                None => None,
+            };
+
+            let account = if school_id.is_empty() {
+                None
+            } else {
+                school_accounts.get_school_account_by_school_id(conn, &school_id).ok()
+            };
+
+            let full_name = match &account {
+                Some(account) => compose_full_name(
+                    account.first_name.clone(),
+                    account.middle_name.clone(),
+                    account.last_name.clone(),
+                ),
+                None => value(full_name_idx).unwrap_or_default(),
+            };
+            if full_name.is_empty() && !school_id.is_empty() {
+                row_errors.push(row_error(
+                    row_number,
+                    "full_name",
+                    ValidationErrorType::DataIntegrity,
+                    format!("School ID {} has no school account, full_name is required", school_id),
+                ));
+            }
+
+            let classification = match value(classification_idx) {
+                Some(classification) => Some(classification),
+                None => match account.as_ref().and_then(|a| a.course.clone()) {
+                    Some(course) => Some(
+                        classifications.get_classification_by_long_name(conn, &course)?
+                            .and_then(|c| c.short_name)
+                            .unwrap_or(course)
+                    ),
+                    None => None,
+                },
+            };
+
+            let status = if !row_errors.is_empty() {
+                AttendanceImportRowStatus::Invalid
+            } else {
+                let time_in = time_in_date.expect("validated above");
+                let earlier = seen.entry(school_id.clone()).or_default();
+                let in_file = earlier.iter()
+                    .any(|t| (*t - time_in).num_seconds().abs() <= DUPLICATE_TOLERANCE_SECONDS);
+                earlier.push(time_in);
+                let in_database = attendances
+                    .find_duplicate_attendance(conn, &school_id, time_in, DUPLICATE_TOLERANCE_SECONDS)?
+                    .is_some();
+
+                if in_file || in_database {
+                    AttendanceImportRowStatus::Duplicate
+                } else {
+                    AttendanceImportRowStatus::New
+                }
+            };
+
+            errors.extend(row_errors);
+            rows.push(AttendanceImportRow {
+                row_number,
+                school_id,
+                full_name,
+                time_in_date,
+                time_out_date,
+                classification,
+                purpose_label: value(purpose_idx),
+                matched_account: account.is_some(),
+                status,
+            });
+        }
+
+        let count = |status: AttendanceImportRowStatus| rows.iter().filter(|r| r.status == status).count();
+        let (new_rows, duplicate_rows, invalid_rows) = (
+            count(AttendanceImportRowStatus::New),
+            count(AttendanceImportRowStatus::Duplicate),
+            count(AttendanceImportRowStatus::Invalid),
+        );
+        let unmatched_rows = rows.iter()
+            .filter(|r| !r.matched_account && r.status != AttendanceImportRowStatus::Invalid)
+            .count();
+
+        Ok(AttendanceCsvValidationResult {
+            is_valid: errors.is_empty(),
+            file_name,
+            total_rows: rows.len(),
+            new_rows,
+            duplicate_rows,
+            invalid_rows,
+            unmatched_rows,
+            rows,
+            errors,
+        })
+    }
+
+    // Inserts the New rows in one transaction; duplicates and invalid rows are skipped
+    pub fn import_rows(
+        &self,
+        conn: &Connection,
+        validation_result: AttendanceCsvValidationResult,
+        actor: &str
+    ) -> Result<AttendanceCsvImportResult> {
+        let attendances = SqliteAttendanceRepository;
+        let tx = conn.unchecked_transaction()?;
+
+        let mut imported = 0;
+        for row in &validation_result.rows {
+            if row.status != AttendanceImportRowStatus::New {
+                continue;
+            }
+
+            attendances.import_attendance(&tx, ImportAttendanceRequest {
+                school_id: row.school_id.clone(),
+                full_name: row.full_name.clone(),
+                time_in_date: row.time_in_date.expect("new rows always have a time in"),
+                time_out_date: row.time_out_date,
+                classification: row.classification.clone(),
+                purpose_label: row.purpose_label.clone(),
+                location_id: None,
+            }, actor)?;
+            imported += 1;
+        }
+
+        tx.commit()?;
+
+        Ok(AttendanceCsvImportResult {
+            imported,
+            skipped_duplicates: validation_result.duplicate_rows,
+            skipped_invalid: validation_result.invalid_rows,
+            validation_result,
+        })
+    }
+}
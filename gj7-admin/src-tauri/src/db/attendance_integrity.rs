@@ -0,0 +1,598 @@
+// src/db/attendance_integrity.rs
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use log::info;
+use rand::rngs::OsRng;
+use rusqlite::{params, Connection, OptionalExtension, Result};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::db::attendance::{in_savepoint, Attendance, AttendanceRepository, SqliteAttendanceRepository};
+use crate::db::attendance_audit::{
+    chain_head,
+    compute_entry_hash,
+    AuditEntryText,
+    AttendanceAuditAction,
+    AttendanceAuditRepository,
+    SqliteAttendanceAuditRepository,
+    ACTOR_SYSTEM,
+    GENESIS_HASH,
+};
+
+const DIGEST_VERSION: &str = "v1";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ChainIssueKind {
+    // prev_hash does not point at the entry before it
+    BrokenLink,
+    // Entry content no longer hashes to its entry_hash
+    HashMismatch,
+    // Entry without hashes written after the chain started
+    UnchainedEntry,
+    // Attendance row differs from the last snapshot the log has for it
+    ContentMismatch,
+    // Row's chain_hash is not the hash of its last log entry
+    RowHashMismatch,
+    // Row has no log entry at all
+    NotInLog,
+    // Log says the row exists but it is gone, or says it was deleted but it is still there
+    MissingRow,
+    UnexpectedRow,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChainIssue {
+    pub kind: ChainIssueKind,
+    pub audit_entry_id: Option<String>,
+    pub attendance_id: Option<String>,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChainVerificationReport {
+    pub checked_at: DateTime<Utc>,
+    pub entries_checked: usize,
+    // Entries from before the log was chained, which cannot be verified
+    pub legacy_entries: usize,
+    pub records_checked: usize,
+    pub chain_head: String,
+    pub issues: Vec<ChainIssue>,
+    pub is_intact: bool,
+}
+
+// What an export covered, kept so its digest can be checked against the log later
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportManifestRecord {
+    pub id: Uuid,
+    pub chain_hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportDigestVerification {
+    pub manifest_id: Option<Uuid>,
+    pub format: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub record_count: usize,
+    pub signature_valid: bool,
+    pub log_matches: bool,
+    // None when the export carries no content hash (XLSX and PDF) or no file was given
+    pub content_matches: Option<bool>,
+    // Records edited or deleted after the export was made; the export still matches the log as it was
+    pub changed_since_export: Vec<Uuid>,
+    pub issues: Vec<String>,
+    pub is_valid: bool,
+}
+
+struct ParsedDigest {
+    manifest_id: Uuid,
+    record_count: usize,
+    log_digest: String,
+    content_sha256: Option<String>,
+    chain_head: String,
+    public_key: String,
+    signature: String,
+    signed_payload: String,
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn invalid(message: impl Into<String>) -> rusqlite::Error {
+    rusqlite::Error::InvalidParameterName(message.into())
+}
+
+fn log_digest(records: &[ExportManifestRecord]) -> String {
+    let mut hasher = Sha256::new();
+    for record in records {
+        hasher.update(format!("{}:{}\n", record.id, record.chain_hash).as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+fn parse_digest(digest: &str) -> Option<ParsedDigest> {
+    let parts: Vec<&str> = digest.trim().split(';').collect();
+    if parts.len() != 8 || parts[0] != DIGEST_VERSION {
+        return None;
+    }
+
+    Some(ParsedDigest {
+        manifest_id: Uuid::parse_str(parts[1]).ok()?,
+        record_count: parts[2].parse().ok()?,
+        log_digest: parts[3].to_string(),
+        content_sha256: Some(parts[4].to_string()).filter(|c| c != "-"),
+        chain_head: parts[5].to_string(),
+        public_key: parts[6].to_string(),
+        signature: parts[7].to_string(),
+        signed_payload: parts[..7].join(";"),
+    })
+}
+
+fn signature_is_valid(parsed: &ParsedDigest) -> bool {
+    let key_bytes: Option<[u8; 32]> = hex::decode(&parsed.public_key).ok().and_then(|b| b.try_into().ok());
+    let signature_bytes: Option<[u8; 64]> = hex::decode(&parsed.signature).ok().and_then(|b| b.try_into().ok());
+
+    match (key_bytes.and_then(|k| VerifyingKey::from_bytes(&k).ok()), signature_bytes) {
+        (Some(key), Some(signature)) => key.verify(parsed.signed_payload.as_bytes(), &Signature::from_bytes(&signature)).is_ok(),
+        _ => false,
+    }
+}
+
+// The secret key is kept in an owner-only file next to the live database rather than in it, so whoever
+// can only edit the database cannot re-sign rewritten rows. A valid signature proves which installation
+// signed an export, not that the data is unmodified: anyone who can read this file can sign anything
+pub fn signing_key_path(db_path: &Path) -> PathBuf {
+    let stem = db_path.file_stem().and_then(|s| s.to_str()).unwrap_or("attendance");
+    db_path.with_file_name(format!("{}_signing.key", stem))
+}
+
+fn signing_key_path_for(conn: &Connection) -> Result<PathBuf> {
+    let main_path: String = conn.query_row(
+        "SELECT file FROM pragma_database_list WHERE name = 'main'",
+        [],
+        |row| row.get(0),
+    )?;
+    if main_path.is_empty() {
+        return Err(invalid("Exports can only be signed for a database stored on disk"));
+    }
+    Ok(signing_key_path(Path::new(&main_path)))
+}
+
+// Readable by the owner only on unix; on Windows the per-user app data directory already is
+fn write_secret_key(path: &Path, key: &SigningKey) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(hex::encode(key.to_bytes()).as_bytes())?;
+    file.sync_all()
+}
+
+// The installation's signing key, created the first time an export is signed.
+// Only its public half goes into integrity_keys, for verifying digests later
+fn signing_key(conn: &Connection) -> Result<SigningKey> {
+    let path = signing_key_path_for(conn)?;
+    match fs::read_to_string(&path) {
+        Ok(secret) => {
+            let bytes: [u8; 32] = hex::decode(secret.trim()).ok()
+                .and_then(|b| b.try_into().ok())
+                .ok_or_else(|| invalid(format!("Signing key {} is unreadable", path.display())))?;
+            return Ok(SigningKey::from_bytes(&bytes));
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {},
+        Err(e) => return Err(invalid(format!("Failed to read signing key {}: {}", path.display(), e))),
+    }
+
+    let key = SigningKey::generate(&mut OsRng);
+    match write_secret_key(&path, &key) {
+        Ok(()) => {},
+        // Another connection created it first
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => return signing_key(conn),
+        Err(e) => return Err(invalid(format!("Failed to write signing key {}: {}", path.display(), e))),
+    }
+    conn.execute(
+        "INSERT INTO integrity_keys (public_key, created_at) VALUES (?1, ?2)",
+        params![hex::encode(key.verifying_key().to_bytes()), Utc::now().to_rfc3339()],
+    )?;
+    info!("Generated attendance export signing key at {}", path.display());
+    Ok(key)
+}
+
+// CSV exports keep their digest in a sidecar file, so the CSV itself stays plain rows
+pub fn csv_digest_path(csv_path: &Path) -> PathBuf {
+    let mut name = csv_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".digest");
+    csv_path.with_file_name(name)
+}
+
+pub trait AttendanceIntegrityRepository: Send + Sync {
+    // Walks the whole log and every attendance row, reporting anything that does not line up
+    fn verify_chain(&self, conn: &Connection) -> Result<ChainVerificationReport>;
+    // Records a manifest for the exported rows and returns the signed digest to embed in the file
+    fn create_export_digest(
+        &self,
+        conn: &Connection,
+        attendances: &[Attendance],
+        content: Option<&[u8]>,
+        format: &str
+    ) -> Result<String>;
+    // `content` is the file content the digest claims to cover, when the format has one
+    fn verify_export_digest(&self, conn: &Connection, digest: &str, content: Option<&[u8]>) -> Result<ExportDigestVerification>;
+}
+
+pub struct SqliteAttendanceIntegrityRepository;
+
+impl AttendanceIntegrityRepository for SqliteAttendanceIntegrityRepository {
+    fn verify_chain(&self, conn: &Connection) -> Result<ChainVerificationReport> {
+        let mut issues = Vec::new();
+        let mut entries_checked = 0;
+        let mut legacy_entries = 0;
+        let mut expected_prev = GENESIS_HASH.to_string();
+        // Latest entry per attendance: (entry id, entry_hash, after_json)
+        let mut latest: HashMap<String, (String, Option<String>, Option<String>)> = HashMap::new();
+
+        let mut stmt = conn.prepare(
+            "SELECT id, attendance_id, action, actor, changed_at, before_json, after_json, prev_hash, entry_hash
+             FROM attendance_audit_log ORDER BY rowid ASC"
+        )?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: String = row.get(0)?;
+            let attendance_id: String = row.get(1)?;
+            let action: String = row.get(2)?;
+            let actor: String = row.get(3)?;
+            let changed_at: String = row.get(4)?;
+            let before_json: Option<String> = row.get(5)?;
+            let after_json: Option<String> = row.get(6)?;
+            let prev_hash: Option<String> = row.get(7)?;
+            let entry_hash: Option<String> = row.get(8)?;
+            entries_checked += 1;
+
+            match (prev_hash, &entry_hash) {
+                (Some(prev_hash), Some(entry_hash)) => {
+                    if prev_hash != expected_prev {
+                        issues.push(ChainIssue {
+                            kind: ChainIssueKind::BrokenLink,
+                            audit_entry_id: Some(id.clone()),
+                            attendance_id: Some(attendance_id.clone()),
+                            detail: format!("Expected previous hash {} but found {}", expected_prev, prev_hash),
+                        });
+                    }
+
+                    let computed = compute_entry_hash(&prev_hash, &AuditEntryText {
+                        id: &id,
+                        attendance_id: &attendance_id,
+                        action: &action,
+                        actor: &actor,
+                        changed_at: &changed_at,
+                        before_json: before_json.as_deref(),
+                        after_json: after_json.as_deref(),
+                    });
+                    if &computed != entry_hash {
+                        issues.push(ChainIssue {
+                            kind: ChainIssueKind::HashMismatch,
+                            audit_entry_id: Some(id.clone()),
+                            attendance_id: Some(attendance_id.clone()),
+                            detail: "Entry was modified after it was written".to_string(),
+                        });
+                    }
+
+                    // Continue from the stored hash so one bad entry is reported once
+                    expected_prev = entry_hash.clone();
+                },
+                _ if expected_prev == GENESIS_HASH => legacy_entries += 1,
+                _ => issues.push(ChainIssue {
+                    kind: ChainIssueKind::UnchainedEntry,
+                    audit_entry_id: Some(id.clone()),
+                    attendance_id: Some(attendance_id.clone()),
+                    detail: "Entry without a hash was added after the chain started".to_string(),
+                }),
+            }
+
+            latest.insert(attendance_id, (id, entry_hash, after_json));
+        }
+
+        let repo = SqliteAttendanceRepository;
+        let mut records_checked = 0;
+        let mut stmt = conn.prepare("SELECT id, chain_hash FROM attendance")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))?
+            .collect::<Result<Vec<_>>>()?;
+
+        for (attendance_id, chain_hash) in rows {
+            records_checked += 1;
+            let issue = |kind: ChainIssueKind, audit_entry_id: Option<&String>, detail: &str| ChainIssue {
+                kind,
+                audit_entry_id: audit_entry_id.cloned(),
+                attendance_id: Some(attendance_id.clone()),
+                detail: detail.to_string(),
+            };
+
+            let (entry_id, entry_hash, after_json) = match latest.remove(&attendance_id) {
+                Some(entry) => entry,
+                None => {
+                    issues.push(issue(ChainIssueKind::NotInLog, None, "Record has no audit log entry"));
+                    continue;
+                },
+            };
+
+            let after_json = match after_json {
+                Some(after_json) => after_json,
+                None => {
+                    issues.push(issue(ChainIssueKind::UnexpectedRow, Some(&entry_id), "Record was deleted in the log but is still present"));
+                    continue;
+                },
+            };
+
+            if entry_hash.is_some() && chain_hash != entry_hash {
+                issues.push(issue(ChainIssueKind::RowHashMismatch, Some(&entry_id), "Record's chain hash does not match its last log entry"));
+            }
+
+            // Both sides go through `Attendance` so snapshots from older versions compare field by field
+            let id = match Uuid::parse_str(&attendance_id) {
+                Ok(id) => id,
+                Err(_) => {
+                    issues.push(issue(ChainIssueKind::ContentMismatch, Some(&entry_id), "Record has an invalid id"));
+                    continue;
+                },
+            };
+            let current = repo.get_attendance(conn, id)
+                .map_err(|e| e.to_string())
+                .and_then(|a| serde_json::to_value(a).map_err(|e| e.to_string()));
+            let logged = serde_json::from_str::<Attendance>(&after_json)
+                .map_err(|e| e.to_string())
+                .and_then(|a| serde_json::to_value(a).map_err(|e| e.to_string()));
+
+            match (current, logged) {
+                (Ok(current), Ok(logged)) if current == logged => {},
+                (Ok(_), Ok(_)) => issues.push(issue(ChainIssueKind::ContentMismatch, Some(&entry_id), "Record was changed outside the application")),
+                (Err(e), _) | (_, Err(e)) => issues.push(issue(ChainIssueKind::ContentMismatch, Some(&entry_id), &format!("Record could not be compared: {}", e))),
+            }
+        }
+
+        // What is left in `latest` has no row; that is only expected after a delete
+        for (attendance_id, (entry_id, _, after_json)) in latest {
+            if after_json.is_some() {
+                issues.push(ChainIssue {
+                    kind: ChainIssueKind::MissingRow,
+                    audit_entry_id: Some(entry_id),
+                    attendance_id: Some(attendance_id),
+                    detail: "Record was removed outside the application".to_string(),
+                });
+            }
+        }
+
+        Ok(ChainVerificationReport {
+            checked_at: Utc::now(),
+            entries_checked,
+            legacy_entries,
+            records_checked,
+            chain_head: chain_head(conn)?,
+            is_intact: issues.is_empty(),
+            issues,
+        })
+    }
+
+    fn create_export_digest(
+        &self,
+        conn: &Connection,
+        attendances: &[Attendance],
+        content: Option<&[u8]>,
+        format: &str
+    ) -> Result<String> {
+        let mut hash_stmt = conn.prepare_cached("SELECT chain_hash FROM attendance WHERE id = ?1")?;
+        let mut records = Vec::with_capacity(attendances.len());
+        for attendance in attendances {
+            let chain_hash: Option<String> = hash_stmt.query_row(params![attendance.id.to_string()], |row| row.get(0))?;
+            records.push(ExportManifestRecord {
+                id: attendance.id,
+                chain_hash: chain_hash.ok_or_else(|| invalid(format!("Attendance {} is not in the audit chain", attendance.id)))?,
+            });
+        }
+
+        let key = signing_key(conn)?;
+        let manifest_id = Uuid::new_v4();
+        let log_digest = log_digest(&records);
+        let content_sha256 = content.map(sha256_hex);
+        let head = chain_head(conn)?;
+
+        let payload = [
+            DIGEST_VERSION.to_string(),
+            manifest_id.to_string(),
+            records.len().to_string(),
+            log_digest.clone(),
+            content_sha256.clone().unwrap_or_else(|| "-".to_string()),
+            head.clone(),
+            hex::encode(key.verifying_key().to_bytes()),
+        ].join(";");
+        let signature = hex::encode(key.sign(payload.as_bytes()).to_bytes());
+
+        conn.execute(
+            "INSERT INTO export_manifests (
+                id, format, created_at, records_json, log_digest, content_sha256, chain_head
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                manifest_id.to_string(),
+                format,
+                Utc::now().to_rfc3339(),
+                serde_json::to_string(&records).map_err(|e| invalid(e.to_string()))?,
+                log_digest,
+                content_sha256,
+                head
+            ],
+        )?;
+
+        Ok(format!("{};{}", payload, signature))
+    }
+
+    fn verify_export_digest(&self, conn: &Connection, digest: &str, content: Option<&[u8]>) -> Result<ExportDigestVerification> {
+        let mut result = ExportDigestVerification {
+            manifest_id: None,
+            format: None,
+            created_at: None,
+            record_count: 0,
+            signature_valid: false,
+            log_matches: false,
+            content_matches: None,
+            changed_since_export: Vec::new(),
+            issues: Vec::new(),
+            is_valid: false,
+        };
+
+        let parsed = match parse_digest(digest) {
+            Some(parsed) => parsed,
+            None => {
+                result.issues.push("Digest is not in a recognised format".to_string());
+                return Ok(result);
+            },
+        };
+        result.manifest_id = Some(parsed.manifest_id);
+        result.record_count = parsed.record_count;
+
+        result.signature_valid = signature_is_valid(&parsed);
+        if !result.signature_valid {
+            result.issues.push("Signature does not match the digest".to_string());
+        }
+
+        let known_key: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM integrity_keys WHERE public_key = ?1)",
+            params![parsed.public_key],
+            |row| row.get(0),
+        )?;
+        if !known_key {
+            result.issues.push("Digest was signed by a key this installation does not hold".to_string());
+        }
+
+        if let Some(content) = content {
+            let matches = parsed.content_sha256.as_deref() == Some(sha256_hex(content).as_str());
+            if !matches {
+                result.issues.push("File content does not match the digest".to_string());
+            }
+            result.content_matches = Some(matches);
+        }
+
+        let manifest = conn.query_row(
+            "SELECT format, created_at, records_json, log_digest, content_sha256, chain_head
+             FROM export_manifests WHERE id = ?1",
+            params![parsed.manifest_id.to_string()],
+            |row| Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, String>(5)?,
+            )),
+        ).optional()?;
+
+        let (format, created_at, records_json, manifest_log_digest, manifest_content, manifest_head) = match manifest {
+            Some(manifest) => manifest,
+            None => {
+                result.issues.push("No export with this digest was recorded".to_string());
+                return Ok(result);
+            },
+        };
+        result.format = Some(format);
+        result.created_at = DateTime::parse_from_rfc3339(&created_at).ok().map(|dt| dt.with_timezone(&Utc));
+
+        if manifest_log_digest != parsed.log_digest
+            || manifest_content != parsed.content_sha256
+            || manifest_head != parsed.chain_head
+        {
+            result.issues.push("Digest does not match the recorded export".to_string());
+        }
+
+        let records: Vec<ExportManifestRecord> = serde_json::from_str(&records_json)
+            .map_err(|e| invalid(format!("Unreadable export manifest: {}", e)))?;
+        if records.len() != parsed.record_count || log_digest(&records) != parsed.log_digest {
+            result.issues.push("Recorded export does not match its digest".to_string());
+        }
+
+        // Every exported state has to be an entry in the log for that record
+        let mut entry_stmt = conn.prepare_cached(
+            "SELECT EXISTS(SELECT 1 FROM attendance_audit_log WHERE attendance_id = ?1 AND entry_hash = ?2)"
+        )?;
+        let mut current_stmt = conn.prepare_cached("SELECT chain_hash FROM attendance WHERE id = ?1")?;
+        let mut missing = 0;
+        for record in &records {
+            let in_log: bool = entry_stmt.query_row(params![record.id.to_string(), record.chain_hash], |row| row.get(0))?;
+            if !in_log {
+                missing += 1;
+            }
+
+            let current: Option<Option<String>> = current_stmt.query_row(params![record.id.to_string()], |row| row.get(0)).optional()?;
+            if current.flatten().as_deref() != Some(record.chain_hash.as_str()) {
+                result.changed_since_export.push(record.id);
+            }
+        }
+        result.log_matches = missing == 0;
+        if missing > 0 {
+            result.issues.push(format!("{} exported record(s) have no matching audit log entry", missing));
+        }
+
+        result.is_valid = result.issues.is_empty();
+        Ok(result)
+    }
+}
+
+// Brings rows that predate the hash chain into it, one Sealed entry each
+pub fn seal_unchained_attendances(conn: &Connection) -> Result<usize> {
+    let ids: Vec<String> = {
+        let mut stmt = conn.prepare("SELECT id FROM attendance WHERE chain_hash IS NULL ORDER BY time_in_date ASC")?;
+        let ids = stmt.query_map([], |row| row.get(0))?;
+        ids.collect::<Result<Vec<String>>>()?
+    };
+
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let repo = SqliteAttendanceRepository;
+    let sealed = in_savepoint(conn, || {
+        let mut sealed = 0;
+        for id in ids.iter().filter_map(|id| Uuid::parse_str(id).ok()) {
+            let attendance = repo.get_attendance(conn, id)?;
+            SqliteAttendanceAuditRepository.record(conn, id, AttendanceAuditAction::Sealed, ACTOR_SYSTEM, None, Some(&attendance))?;
+            sealed += 1;
+        }
+        Ok(sealed)
+    })?;
+
+    info!("Sealed {} attendance records into the audit chain", sealed);
+    Ok(sealed)
+}
+
+pub fn create_attendance_integrity_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS integrity_keys (
+            public_key TEXT PRIMARY KEY,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS export_manifests (
+            id TEXT PRIMARY KEY,
+            format TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            records_json TEXT NOT NULL,
+            log_digest TEXT NOT NULL,
+            content_sha256 TEXT,
+            chain_head TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,475 @@
+// src/db/clearance.rs
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::db::attendance_csv_import::compose_full_name;
+use crate::db::semester::{SemesterRepository, SqliteSemesterRepository};
+
+// Label of the default purpose students pick at the kiosk to check their clearance
+pub const CLEARANCE_PURPOSE: &str = "Clearance";
+
+// Students nobody has placed a hold on or signed off yet are Pending without having a row
+const CLEARANCE_COLUMNS: &str = "
+    sa.school_id, sa.first_name, sa.middle_name, sa.last_name, sa.course, sa.year_level,
+    COALESCE(c.status, 'Pending'), c.cleared_at, c.cleared_by, c.updated_at
+";
+const CLEARANCE_FROM: &str = "FROM school_accounts sa
+    LEFT JOIN clearances c ON c.school_id = sa.school_id AND c.semester_id = ?1";
+// Every active student, plus anyone who already has a clearance row for the semester
+const CLEARANCE_SCOPE: &str = "((sa.is_active = 1 AND sa.course IS NOT NULL AND sa.course != '') OR c.school_id IS NOT NULL)";
+
+const HOLD_COLUMNS: &str = "id, school_id, semester_id, reason, details, placed_at, placed_by, resolved_at, resolved_by";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ClearanceStatus {
+    Pending,
+    Cleared,
+    // Has at least one unresolved hold; cannot be signed off until they are resolved
+    OnHold,
+}
+
+impl ClearanceStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ClearanceStatus::Pending => "Pending",
+            ClearanceStatus::Cleared => "Cleared",
+            ClearanceStatus::OnHold => "OnHold",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "Pending" => Some(ClearanceStatus::Pending),
+            "Cleared" => Some(ClearanceStatus::Cleared),
+            "OnHold" => Some(ClearanceStatus::OnHold),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClearanceStatus::Pending => "Pending",
+            ClearanceStatus::Cleared => "Cleared",
+            ClearanceStatus::OnHold => "On Hold",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum HoldReason {
+    UnreturnedItem,
+    UnpaidFine,
+    Other,
+}
+
+impl HoldReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HoldReason::UnreturnedItem => "UnreturnedItem",
+            HoldReason::UnpaidFine => "UnpaidFine",
+            HoldReason::Other => "Other",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "UnreturnedItem" => Some(HoldReason::UnreturnedItem),
+            "UnpaidFine" => Some(HoldReason::UnpaidFine),
+            "Other" => Some(HoldReason::Other),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HoldReason::UnreturnedItem => "Unreturned item",
+            HoldReason::UnpaidFine => "Unpaid fine",
+            HoldReason::Other => "Other",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClearanceHold {
+    pub id: Uuid,
+    pub school_id: String,
+    pub semester_id: Uuid,
+    pub reason: HoldReason,
+    // e.g. the title of the unreturned book or the fine amount
+    pub details: Option<String>,
+    pub placed_at: DateTime<Utc>,
+    pub placed_by: String,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolved_by: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Clearance {
+    pub school_id: String,
+    pub full_name: String,
+    pub course: Option<String>,
+    pub year_level: Option<String>,
+    pub semester_id: Uuid,
+    pub status: ClearanceStatus,
+    pub cleared_at: Option<DateTime<Utc>>,
+    pub cleared_by: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    // Unresolved holds only
+    pub holds: Vec<ClearanceHold>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PlaceHoldRequest {
+    pub school_id: String,
+    pub reason: HoldReason,
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ClearanceSkip {
+    pub school_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ClearanceSignOffResult {
+    pub cleared: Vec<Clearance>,
+    pub skipped: Vec<ClearanceSkip>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct CourseClearanceSummary {
+    pub course: String,
+    pub total: u64,
+    pub pending: u64,
+    pub cleared: u64,
+    pub on_hold: u64,
+}
+
+fn parse_datetime_column(row: &Row, idx: usize) -> Result<Option<DateTime<Utc>>> {
+    match row.get::<_, Option<String>>(idx)? {
+        Some(value) => DateTime::parse_from_rfc3339(&value)
+            .map(|dt| Some(dt.with_timezone(&Utc)))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))),
+        None => Ok(None),
+    }
+}
+
+fn row_to_hold(row: &Row) -> Result<ClearanceHold> {
+    Ok(ClearanceHold {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        school_id: row.get(1)?,
+        semester_id: Uuid::parse_str(&row.get::<_, String>(2)?).unwrap(),
+        reason: HoldReason::from_str(&row.get::<_, String>(3)?).unwrap_or(HoldReason::Other),
+        details: row.get(4)?,
+        placed_at: parse_datetime_column(row, 5)?.unwrap_or_else(Utc::now),
+        placed_by: row.get(6)?,
+        resolved_at: parse_datetime_column(row, 7)?,
+        resolved_by: row.get(8)?,
+    })
+}
+
+// Holds are attached afterwards
+fn row_to_clearance(row: &Row, semester_id: Uuid) -> Result<Clearance> {
+    let school_id: String = row.get(0)?;
+    let full_name = compose_full_name(row.get(1)?, row.get(2)?, row.get(3)?);
+
+    Ok(Clearance {
+        full_name: if full_name.is_empty() { school_id.clone() } else { full_name },
+        school_id,
+        course: row.get(4)?,
+        year_level: row.get(5)?,
+        semester_id,
+        status: ClearanceStatus::from_str(&row.get::<_, String>(6)?).unwrap_or(ClearanceStatus::Pending),
+        cleared_at: parse_datetime_column(row, 7)?,
+        cleared_by: row.get(8)?,
+        updated_at: parse_datetime_column(row, 9)?,
+        holds: Vec::new(),
+    })
+}
+
+// Clearances default to the active semester when none is picked
+pub(crate) fn clearance_semester(conn: &Connection, semester_id: Option<Uuid>) -> Result<Uuid> {
+    match semester_id {
+        Some(id) => Ok(id),
+        None => SqliteSemesterRepository.get_active_semester(conn)?
+            .map(|semester| semester.id)
+            .ok_or_else(|| rusqlite::Error::InvalidParameterName("There is no active semester".to_string())),
+    }
+}
+
+pub trait ClearanceRepository: Send + Sync {
+    // Fails with QueryReturnedNoRows when the school_id has no school account
+    fn get_clearance(&self, conn: &Connection, school_id: &str, semester_id: Uuid) -> Result<Clearance>;
+    fn get_clearances(
+        &self,
+        conn: &Connection,
+        semester_id: Uuid,
+        course: Option<&str>,
+        status: Option<ClearanceStatus>
+    ) -> Result<Vec<Clearance>>;
+    // Puts the clearance on hold, undoing a sign-off if there was one
+    fn place_hold(&self, conn: &Connection, semester_id: Uuid, hold: PlaceHoldRequest, actor: &str) -> Result<Clearance>;
+    // The clearance goes back to Pending once its last hold is resolved
+    fn resolve_hold(&self, conn: &Connection, hold_id: Uuid, actor: &str) -> Result<Clearance>;
+    // Signs off every listed student in one transaction; students on hold are skipped
+    fn sign_off_clearances(
+        &self,
+        conn: &Connection,
+        semester_id: Uuid,
+        school_ids: Vec<String>,
+        actor: &str
+    ) -> Result<ClearanceSignOffResult>;
+    fn get_course_report(&self, conn: &Connection, semester_id: Uuid) -> Result<Vec<CourseClearanceSummary>>;
+}
+
+pub struct SqliteClearanceRepository;
+
+impl SqliteClearanceRepository {
+    fn unresolved_holds(&self, conn: &Connection, semester_id: Uuid, school_id: Option<&str>) -> Result<Vec<ClearanceHold>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM clearance_holds
+             WHERE semester_id = ?1 AND resolved_at IS NULL AND (?2 IS NULL OR school_id = ?2)
+             ORDER BY placed_at ASC",
+            HOLD_COLUMNS
+        ))?;
+
+        let holds = stmt.query_map(params![semester_id.to_string(), school_id], row_to_hold)?;
+        holds.collect()
+    }
+
+    // Keeps the stored status in step with the holds
+    fn set_status(
+        &self,
+        conn: &Connection,
+        school_id: &str,
+        semester_id: Uuid,
+        status: ClearanceStatus,
+        cleared_by: Option<&str>
+    ) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let cleared_at = cleared_by.map(|_| now.clone());
+
+        conn.execute(
+            "INSERT INTO clearances (school_id, semester_id, status, cleared_at, cleared_by, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(school_id, semester_id) DO UPDATE SET
+                status = excluded.status,
+                cleared_at = excluded.cleared_at,
+                cleared_by = excluded.cleared_by,
+                updated_at = excluded.updated_at",
+            params![school_id, semester_id.to_string(), status.as_str(), cleared_at, cleared_by, now],
+        )?;
+        Ok(())
+    }
+}
+
+impl ClearanceRepository for SqliteClearanceRepository {
+    fn get_clearance(&self, conn: &Connection, school_id: &str, semester_id: Uuid) -> Result<Clearance> {
+        let mut clearance = conn.query_row(
+            &format!("SELECT {} {} WHERE sa.school_id = ?2", CLEARANCE_COLUMNS, CLEARANCE_FROM),
+            params![semester_id.to_string(), school_id.trim()],
+            |row| row_to_clearance(row, semester_id),
+        )?;
+
+        clearance.holds = self.unresolved_holds(conn, semester_id, Some(&clearance.school_id))?;
+        Ok(clearance)
+    }
+
+    fn get_clearances(
+        &self,
+        conn: &Connection,
+        semester_id: Uuid,
+        course: Option<&str>,
+        status: Option<ClearanceStatus>
+    ) -> Result<Vec<Clearance>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} {}
+             WHERE {} AND (?2 IS NULL OR sa.course = ?2) AND (?3 IS NULL OR COALESCE(c.status, 'Pending') = ?3)
+             ORDER BY sa.course ASC, sa.year_level ASC, sa.last_name COLLATE NOCASE, sa.first_name COLLATE NOCASE",
+            CLEARANCE_COLUMNS,
+            CLEARANCE_FROM,
+            CLEARANCE_SCOPE
+        ))?;
+
+        let mut clearances = stmt.query_map(
+            params![semester_id.to_string(), course, status.map(|s| s.as_str())],
+            |row| row_to_clearance(row, semester_id)
+        )?.collect::<Result<Vec<Clearance>>>()?;
+
+        let mut holds: HashMap<String, Vec<ClearanceHold>> = HashMap::new();
+        for hold in self.unresolved_holds(conn, semester_id, None)? {
+            holds.entry(hold.school_id.clone()).or_default().push(hold);
+        }
+        for clearance in &mut clearances {
+            clearance.holds = holds.remove(&clearance.school_id).unwrap_or_default();
+        }
+
+        Ok(clearances)
+    }
+
+    fn place_hold(&self, conn: &Connection, semester_id: Uuid, hold: PlaceHoldRequest, actor: &str) -> Result<Clearance> {
+        let school_id = hold.school_id.trim().to_string();
+        // Only school accounts have clearances
+        self.get_clearance(conn, &school_id, semester_id)?;
+
+        let details = hold.details.map(|d| d.trim().to_string()).filter(|d| !d.is_empty());
+        if hold.reason == HoldReason::Other && details.is_none() {
+            return Err(rusqlite::Error::InvalidParameterName("Describe the reason for the hold".to_string()));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO clearance_holds (id, school_id, semester_id, reason, details, placed_at, placed_by)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                Uuid::new_v4().to_string(),
+                school_id,
+                semester_id.to_string(),
+                hold.reason.as_str(),
+                details,
+                Utc::now().to_rfc3339(),
+                actor
+            ],
+        )?;
+        self.set_status(&tx, &school_id, semester_id, ClearanceStatus::OnHold, None)?;
+        tx.commit()?;
+
+        info!("Clearance of {} put on hold: {}", school_id, hold.reason.label());
+        self.get_clearance(conn, &school_id, semester_id)
+    }
+
+    fn resolve_hold(&self, conn: &Connection, hold_id: Uuid, actor: &str) -> Result<Clearance> {
+        let hold = conn.query_row(
+            &format!("SELECT {} FROM clearance_holds WHERE id = ?1", HOLD_COLUMNS),
+            params![hold_id.to_string()],
+            row_to_hold,
+        )?;
+        if hold.resolved_at.is_some() {
+            return Err(rusqlite::Error::InvalidParameterName("This hold is already resolved".to_string()));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE clearance_holds SET resolved_at = ?1, resolved_by = ?2 WHERE id = ?3",
+            params![Utc::now().to_rfc3339(), actor, hold_id.to_string()],
+        )?;
+        if self.unresolved_holds(&tx, hold.semester_id, Some(&hold.school_id))?.is_empty() {
+            self.set_status(&tx, &hold.school_id, hold.semester_id, ClearanceStatus::Pending, None)?;
+        }
+        tx.commit()?;
+
+        self.get_clearance(conn, &hold.school_id, hold.semester_id)
+    }
+
+    fn sign_off_clearances(
+        &self,
+        conn: &Connection,
+        semester_id: Uuid,
+        school_ids: Vec<String>,
+        actor: &str
+    ) -> Result<ClearanceSignOffResult> {
+        let tx = conn.unchecked_transaction()?;
+        let mut cleared = Vec::new();
+        let mut skipped = Vec::new();
+
+        for school_id in school_ids {
+            let school_id = school_id.trim().to_string();
+            let clearance = match self.get_clearance(&tx, &school_id, semester_id).optional()? {
+                Some(clearance) => clearance,
+                None => {
+                    skipped.push(ClearanceSkip { school_id, reason: "No school account with this ID".to_string() });
+                    continue;
+                },
+            };
+
+            match clearance.status {
+                ClearanceStatus::OnHold => {
+                    let reasons: Vec<&str> = clearance.holds.iter().map(|h| h.reason.label()).collect();
+                    skipped.push(ClearanceSkip { school_id, reason: format!("On hold: {}", reasons.join(", ")) });
+                },
+                ClearanceStatus::Cleared => {
+                    skipped.push(ClearanceSkip { school_id, reason: "Already cleared".to_string() });
+                },
+                ClearanceStatus::Pending => {
+                    self.set_status(&tx, &school_id, semester_id, ClearanceStatus::Cleared, Some(actor))?;
+                    cleared.push(self.get_clearance(&tx, &school_id, semester_id)?);
+                },
+            }
+        }
+
+        tx.commit()?;
+        info!("{} signed off {} clearances", actor, cleared.len());
+        Ok(ClearanceSignOffResult { cleared, skipped })
+    }
+
+    fn get_course_report(&self, conn: &Connection, semester_id: Uuid) -> Result<Vec<CourseClearanceSummary>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT
+                COALESCE(NULLIF(sa.course, ''), 'No Course'),
+                COUNT(*),
+                SUM(CASE WHEN COALESCE(c.status, 'Pending') = 'Pending' THEN 1 ELSE 0 END),
+                SUM(CASE WHEN c.status = 'Cleared' THEN 1 ELSE 0 END),
+                SUM(CASE WHEN c.status = 'OnHold' THEN 1 ELSE 0 END)
+             {}
+             WHERE {}
+             GROUP BY 1
+             ORDER BY 1 ASC",
+            CLEARANCE_FROM,
+            CLEARANCE_SCOPE
+        ))?;
+
+        let summaries = stmt.query_map(params![semester_id.to_string()], |row| {
+            Ok(CourseClearanceSummary {
+                course: row.get(0)?,
+                total: row.get::<_, i64>(1)? as u64,
+                pending: row.get::<_, i64>(2)? as u64,
+                cleared: row.get::<_, i64>(3)? as u64,
+                on_hold: row.get::<_, i64>(4)? as u64,
+            })
+        })?;
+        summaries.collect()
+    }
+}
+
+pub fn create_clearance_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS clearances (
+            school_id TEXT NOT NULL,
+            semester_id TEXT NOT NULL,
+            status TEXT NOT NULL,
+            cleared_at TEXT,
+            cleared_by TEXT,
+            updated_at TEXT NOT NULL,
+            PRIMARY KEY (school_id, semester_id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS clearance_holds (
+            id TEXT PRIMARY KEY,
+            school_id TEXT NOT NULL,
+            semester_id TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            details TEXT,
+            placed_at TEXT NOT NULL,
+            placed_by TEXT NOT NULL,
+            resolved_at TEXT,
+            resolved_by TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_clearance_holds_school_semester ON clearance_holds(school_id, semester_id)",
+        [],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,368 @@
+// src/db/events.rs
+
+use chrono::{DateTime, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::db::attendance::{
+    Attendance,
+    AttendanceQuery,
+    AttendanceRepository,
+    AttendanceSort,
+    SqliteAttendanceRepository,
+};
+use crate::db::institution_time::institution_timezone;
+use crate::db::locations::{Kiosk, LocationRepository, SqliteLocationRepository};
+
+const EVENT_COLUMNS: &str = "
+    e.id, e.name, e.venue, e.starts_at, e.ends_at, e.allowed_classifications_json, e.capacity, e.created_at,
+    (SELECT COUNT(*) FROM event_registrations r WHERE r.event_id = e.id),
+    (SELECT COUNT(DISTINCT a.school_id) FROM attendance a WHERE a.event_id = e.id)
+";
+
+// A seminar, orientation or other session that kiosks can record attendance into
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Event {
+    pub id: Uuid,
+    pub name: String,
+    pub venue: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    // Admission is open to everyone when this and the registration list are both empty;
+    // otherwise a scan must match a classification here or a pre-registered school_id
+    pub allowed_classifications: Vec<String>,
+    // Most distinct attendees admitted, None for no limit
+    pub capacity: Option<u32>,
+    pub created_at: DateTime<Utc>,
+    pub registered_count: u64,
+    pub attendee_count: u64,
+}
+
+impl Event {
+    pub fn has_started(&self, now: DateTime<Utc>) -> bool {
+        self.starts_at <= now
+    }
+
+    pub fn has_ended(&self, now: DateTime<Utc>) -> bool {
+        self.ends_at < now
+    }
+
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        self.has_started(now) && !self.has_ended(now)
+    }
+
+    fn is_restricted(&self) -> bool {
+        !self.allowed_classifications.is_empty() || self.registered_count > 0
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CreateEventRequest {
+    pub name: String,
+    pub venue: Option<String>,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    #[serde(default)]
+    pub allowed_classifications: Vec<String>,
+    pub capacity: Option<u32>,
+}
+
+fn parse_datetime_column(row: &Row, idx: usize) -> Result<DateTime<Utc>> {
+    let value: String = row.get(idx)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn row_to_event(row: &Row) -> Result<Event> {
+    let allowed_classifications: String = row.get(5)?;
+
+    Ok(Event {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        name: row.get(1)?,
+        venue: row.get(2)?,
+        starts_at: parse_datetime_column(row, 3)?,
+        ends_at: parse_datetime_column(row, 4)?,
+        allowed_classifications: serde_json::from_str(&allowed_classifications).unwrap_or_default(),
+        capacity: row.get::<_, Option<i64>>(6)?.map(|c| c as u32),
+        created_at: parse_datetime_column(row, 7)?,
+        registered_count: row.get::<_, i64>(8)? as u64,
+        attendee_count: row.get::<_, i64>(9)? as u64,
+    })
+}
+
+fn clean_list(values: Vec<String>) -> Vec<String> {
+    let mut cleaned: Vec<String> = values.into_iter()
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+        .collect();
+    cleaned.sort();
+    cleaned.dedup();
+    cleaned
+}
+
+// Checks the request and returns it with its text fields trimmed
+fn validate_event(event: CreateEventRequest) -> Result<CreateEventRequest> {
+    let name = event.name.trim().to_string();
+    if name.is_empty() {
+        return Err(rusqlite::Error::InvalidParameterName("Event name cannot be empty".to_string()));
+    }
+    if event.ends_at <= event.starts_at {
+        return Err(rusqlite::Error::InvalidParameterName("An event must end after it starts".to_string()));
+    }
+    if event.capacity == Some(0) {
+        return Err(rusqlite::Error::InvalidParameterName("Capacity must be at least 1".to_string()));
+    }
+
+    Ok(CreateEventRequest {
+        name,
+        venue: event.venue.map(|v| v.trim().to_string()).filter(|v| !v.is_empty()),
+        allowed_classifications: clean_list(event.allowed_classifications),
+        ..event
+    })
+}
+
+pub trait EventRepository: Send + Sync {
+    fn create_event(&self, conn: &Connection, event: CreateEventRequest) -> Result<Event>;
+    fn get_event(&self, conn: &Connection, id: Uuid) -> Result<Event>;
+    fn update_event(&self, conn: &Connection, id: Uuid, event: CreateEventRequest) -> Result<Event>;
+    // Only events nobody attended can be deleted
+    fn delete_event(&self, conn: &Connection, id: Uuid) -> Result<()>;
+    // Newest first; ended events are left out unless asked for
+    fn get_events(&self, conn: &Connection, include_ended: bool) -> Result<Vec<Event>>;
+    fn get_event_registrations(&self, conn: &Connection, id: Uuid) -> Result<Vec<String>>;
+    // Replaces the pre-registered school_ids of the event
+    fn set_event_registrations(&self, conn: &Connection, id: Uuid, school_ids: Vec<String>) -> Result<Event>;
+    fn get_event_attendances(&self, conn: &Connection, id: Uuid) -> Result<Vec<Attendance>>;
+    // None switches the kiosk back to normal scanning
+    fn set_kiosk_event(&self, conn: &Connection, kiosk_id: &str, event_id: Option<Uuid>) -> Result<Kiosk>;
+    // Event the kiosk records into; a kiosk left in event mode returns to normal once the event ends
+    fn get_kiosk_event(&self, conn: &Connection, kiosk_id: Option<&str>) -> Result<Option<Event>>;
+    // Refuses scans before the event starts, from people it is not open to, and once it is full
+    fn check_admission(&self, conn: &Connection, event: &Event, school_id: &str, classification: &str) -> Result<()>;
+}
+
+pub struct SqliteEventRepository;
+
+impl EventRepository for SqliteEventRepository {
+    fn create_event(&self, conn: &Connection, event: CreateEventRequest) -> Result<Event> {
+        let event = validate_event(event)?;
+        let id = Uuid::new_v4();
+
+        conn.execute(
+            "INSERT INTO events (
+                id, name, venue, starts_at, ends_at, allowed_classifications_json, capacity, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                id.to_string(),
+                event.name,
+                event.venue,
+                event.starts_at.to_rfc3339(),
+                event.ends_at.to_rfc3339(),
+                serde_json::to_string(&event.allowed_classifications).unwrap_or_else(|_| "[]".to_string()),
+                event.capacity,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+
+        info!("Created event {}", event.name);
+        self.get_event(conn, id)
+    }
+
+    fn get_event(&self, conn: &Connection, id: Uuid) -> Result<Event> {
+        conn.query_row(
+            &format!("SELECT {} FROM events e WHERE e.id = ?1", EVENT_COLUMNS),
+            params![id.to_string()],
+            row_to_event,
+        )
+    }
+
+    fn update_event(&self, conn: &Connection, id: Uuid, event: CreateEventRequest) -> Result<Event> {
+        self.get_event(conn, id)?;
+        let event = validate_event(event)?;
+
+        conn.execute(
+            "UPDATE events SET
+                name = ?1, venue = ?2, starts_at = ?3, ends_at = ?4, allowed_classifications_json = ?5, capacity = ?6
+             WHERE id = ?7",
+            params![
+                event.name,
+                event.venue,
+                event.starts_at.to_rfc3339(),
+                event.ends_at.to_rfc3339(),
+                serde_json::to_string(&event.allowed_classifications).unwrap_or_else(|_| "[]".to_string()),
+                event.capacity,
+                id.to_string()
+            ],
+        )?;
+
+        self.get_event(conn, id)
+    }
+
+    fn delete_event(&self, conn: &Connection, id: Uuid) -> Result<()> {
+        let event = self.get_event(conn, id)?;
+        if event.attendee_count > 0 {
+            return Err(rusqlite::Error::InvalidParameterName(
+                format!("{} already has attendance recorded and cannot be deleted", event.name)
+            ));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("UPDATE kiosks SET event_id = NULL WHERE event_id = ?1", params![id.to_string()])?;
+        tx.execute("DELETE FROM event_registrations WHERE event_id = ?1", params![id.to_string()])?;
+        tx.execute("DELETE FROM events WHERE id = ?1", params![id.to_string()])?;
+        tx.commit()?;
+
+        info!("Deleted event {}", event.name);
+        Ok(())
+    }
+
+    fn get_events(&self, conn: &Connection, include_ended: bool) -> Result<Vec<Event>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM events e WHERE ?1 OR e.ends_at >= ?2 ORDER BY e.starts_at DESC",
+            EVENT_COLUMNS
+        ))?;
+
+        let events = stmt.query_map(params![include_ended, Utc::now().to_rfc3339()], row_to_event)?;
+        events.collect()
+    }
+
+    fn get_event_registrations(&self, conn: &Connection, id: Uuid) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT school_id FROM event_registrations WHERE event_id = ?1 ORDER BY school_id ASC"
+        )?;
+
+        let school_ids = stmt.query_map(params![id.to_string()], |row| row.get(0))?;
+        school_ids.collect()
+    }
+
+    fn set_event_registrations(&self, conn: &Connection, id: Uuid, school_ids: Vec<String>) -> Result<Event> {
+        self.get_event(conn, id)?;
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM event_registrations WHERE event_id = ?1", params![id.to_string()])?;
+        for school_id in clean_list(school_ids) {
+            tx.execute(
+                "INSERT INTO event_registrations (event_id, school_id) VALUES (?1, ?2)",
+                params![id.to_string(), school_id],
+            )?;
+        }
+        tx.commit()?;
+
+        self.get_event(conn, id)
+    }
+
+    fn get_event_attendances(&self, conn: &Connection, id: Uuid) -> Result<Vec<Attendance>> {
+        SqliteAttendanceRepository.get_all_matching_attendances(conn, &AttendanceQuery {
+            events: vec![id],
+            sort: AttendanceSort::TimeInAsc,
+            ..Default::default()
+        })
+    }
+
+    fn set_kiosk_event(&self, conn: &Connection, kiosk_id: &str, event_id: Option<Uuid>) -> Result<Kiosk> {
+        if let Some(event_id) = event_id {
+            let event = self.get_event(conn, event_id)?;
+            if event.has_ended(Utc::now()) {
+                return Err(rusqlite::Error::InvalidParameterName(format!("{} has already ended", event.name)));
+            }
+        }
+
+        let updated = conn.execute(
+            "UPDATE kiosks SET event_id = ?1 WHERE kiosk_id = ?2",
+            params![event_id.map(|id| id.to_string()), kiosk_id.trim()],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+
+        info!("Kiosk {} switched to event {:?}", kiosk_id, event_id);
+        SqliteLocationRepository.get_kiosk(conn, kiosk_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    fn get_kiosk_event(&self, conn: &Connection, kiosk_id: Option<&str>) -> Result<Option<Event>> {
+        let event_id = match kiosk_id.filter(|k| !k.trim().is_empty()) {
+            Some(kiosk_id) => SqliteLocationRepository.get_kiosk(conn, kiosk_id)?.and_then(|k| k.event_id),
+            None => None,
+        };
+
+        match event_id {
+            Some(event_id) => Ok(self.get_event(conn, event_id).optional()?
+                .filter(|event| !event.has_ended(Utc::now()))),
+            None => Ok(None),
+        }
+    }
+
+    fn check_admission(&self, conn: &Connection, event: &Event, school_id: &str, classification: &str) -> Result<()> {
+        if !event.has_started(Utc::now()) {
+            return Err(rusqlite::Error::InvalidParameterName(format!(
+                "{} starts at {}",
+                event.name,
+                event.starts_at.with_timezone(&institution_timezone(conn)?).format("%I:%M %p")
+            )));
+        }
+
+        if event.is_restricted() {
+            let allowed_classification = event.allowed_classifications.iter()
+                .any(|c| c.eq_ignore_ascii_case(classification));
+            let registered: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM event_registrations WHERE event_id = ?1 AND school_id = ?2)",
+                params![event.id.to_string(), school_id],
+                |row| row.get(0),
+            )?;
+
+            if !allowed_classification && !registered {
+                return Err(rusqlite::Error::InvalidParameterName(format!("Not registered for {}", event.name)));
+            }
+        }
+
+        // People already admitted can come back in after stepping out
+        if let Some(capacity) = event.capacity {
+            let attended: bool = conn.query_row(
+                "SELECT EXISTS(SELECT 1 FROM attendance WHERE event_id = ?1 AND school_id = ?2)",
+                params![event.id.to_string(), school_id],
+                |row| row.get(0),
+            )?;
+
+            if !attended && event.attendee_count >= capacity as u64 {
+                return Err(rusqlite::Error::InvalidParameterName(format!("{} is full", event.name)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn create_events_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS events (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            venue TEXT,
+            starts_at TEXT NOT NULL,
+            ends_at TEXT NOT NULL,
+            allowed_classifications_json TEXT NOT NULL DEFAULT '[]',
+            capacity INTEGER,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS event_registrations (
+            event_id TEXT NOT NULL,
+            school_id TEXT NOT NULL,
+            PRIMARY KEY (event_id, school_id)
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_events_starts_at ON events(starts_at)",
+        [],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,208 @@
+// src/db/export_jobs.rs
+
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ExportJobType {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl ExportJobType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportJobType::Daily => "Daily",
+            ExportJobType::Weekly => "Weekly",
+            ExportJobType::Monthly => "Monthly",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "Daily" => Some(ExportJobType::Daily),
+            "Weekly" => Some(ExportJobType::Weekly),
+            "Monthly" => Some(ExportJobType::Monthly),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ExportJobStatus {
+    Succeeded,
+    Failed,
+}
+
+impl ExportJobStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ExportJobStatus::Succeeded => "Succeeded",
+            ExportJobStatus::Failed => "Failed",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "Succeeded" => Some(ExportJobStatus::Succeeded),
+            "Failed" => Some(ExportJobStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+// One run of a scheduled (or manually triggered) export
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportJob {
+    pub id: Uuid,
+    pub job_type: ExportJobType,
+    // Institution calendar days covered, inclusive
+    pub period_start: NaiveDate,
+    pub period_end: NaiveDate,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub status: ExportJobStatus,
+    pub file_path: Option<String>,
+    pub record_count: Option<u64>,
+    pub error: Option<String>,
+    // "system" for the scheduler, otherwise the admin who ran it
+    pub triggered_by: String,
+}
+
+const EXPORT_JOB_COLUMNS: &str = "id, job_type, period_start, period_end, started_at, finished_at,
+    status, file_path, record_count, error, triggered_by";
+
+fn conversion_error<E: std::error::Error + Send + Sync + 'static>(idx: usize, e: E) -> rusqlite::Error {
+    rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))
+}
+
+fn row_to_export_job(row: &Row) -> Result<ExportJob> {
+    let job_type: String = row.get(1)?;
+    let status: String = row.get(6)?;
+    let date = |idx: usize| -> Result<NaiveDate> {
+        NaiveDate::parse_from_str(&row.get::<_, String>(idx)?, "%Y-%m-%d").map_err(|e| conversion_error(idx, e))
+    };
+    let datetime = |idx: usize| -> Result<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&row.get::<_, String>(idx)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| conversion_error(idx, e))
+    };
+
+    Ok(ExportJob {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        job_type: ExportJobType::from_str(&job_type).ok_or_else(|| {
+            rusqlite::Error::InvalidColumnType(1, job_type.clone(), rusqlite::types::Type::Text)
+        })?,
+        period_start: date(2)?,
+        period_end: date(3)?,
+        started_at: datetime(4)?,
+        finished_at: datetime(5)?,
+        status: ExportJobStatus::from_str(&status).ok_or_else(|| {
+            rusqlite::Error::InvalidColumnType(6, status.clone(), rusqlite::types::Type::Text)
+        })?,
+        file_path: row.get(7)?,
+        record_count: row.get(8)?,
+        error: row.get(9)?,
+        triggered_by: row.get(10)?,
+    })
+}
+
+pub trait ExportJobRepository: Send + Sync {
+    fn record_job(&self, conn: &Connection, job: &ExportJob) -> Result<()>;
+    fn get_recent_jobs(&self, conn: &Connection, limit: usize) -> Result<Vec<ExportJob>>;
+    // Latest run for a period by `triggered_by`, successful or not
+    fn get_last_job(&self, conn: &Connection, job_type: ExportJobType, period_start: NaiveDate, triggered_by: &str) -> Result<Option<ExportJob>>;
+    fn get_last_succeeded_period(&self, conn: &Connection, job_type: ExportJobType, triggered_by: &str) -> Result<Option<NaiveDate>>;
+}
+
+pub struct SqliteExportJobRepository;
+
+impl ExportJobRepository for SqliteExportJobRepository {
+    fn record_job(&self, conn: &Connection, job: &ExportJob) -> Result<()> {
+        conn.execute(
+            "INSERT INTO export_jobs (
+                id, job_type, period_start, period_end, started_at, finished_at,
+                status, file_path, record_count, error, triggered_by
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            params![
+                job.id.to_string(),
+                job.job_type.as_str(),
+                job.period_start.format("%Y-%m-%d").to_string(),
+                job.period_end.format("%Y-%m-%d").to_string(),
+                job.started_at.to_rfc3339(),
+                job.finished_at.to_rfc3339(),
+                job.status.as_str(),
+                job.file_path,
+                job.record_count,
+                job.error,
+                job.triggered_by
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn get_recent_jobs(&self, conn: &Connection, limit: usize) -> Result<Vec<ExportJob>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM export_jobs ORDER BY started_at DESC LIMIT ?1",
+            EXPORT_JOB_COLUMNS
+        ))?;
+
+        let jobs = stmt.query_map(params![limit as i64], row_to_export_job)?;
+        jobs.collect()
+    }
+
+    fn get_last_job(&self, conn: &Connection, job_type: ExportJobType, period_start: NaiveDate, triggered_by: &str) -> Result<Option<ExportJob>> {
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM export_jobs
+                 WHERE job_type = ?1 AND period_start = ?2 AND triggered_by = ?3
+                 ORDER BY started_at DESC LIMIT 1",
+                EXPORT_JOB_COLUMNS
+            ),
+            params![job_type.as_str(), period_start.format("%Y-%m-%d").to_string(), triggered_by],
+            row_to_export_job,
+        ).optional()
+    }
+
+    fn get_last_succeeded_period(&self, conn: &Connection, job_type: ExportJobType, triggered_by: &str) -> Result<Option<NaiveDate>> {
+        let period: Option<String> = conn.query_row(
+            "SELECT MAX(period_start) FROM export_jobs
+             WHERE job_type = ?1 AND status = 'Succeeded' AND triggered_by = ?2",
+            params![job_type.as_str(), triggered_by],
+            |row| row.get(0),
+        )?;
+
+        period
+            .map(|p| NaiveDate::parse_from_str(&p, "%Y-%m-%d").map_err(|e| conversion_error(0, e)))
+            .transpose()
+    }
+}
+
+pub fn create_export_jobs_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS export_jobs (
+            id TEXT PRIMARY KEY,
+            job_type TEXT NOT NULL,
+            period_start TEXT NOT NULL,
+            period_end TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            finished_at TEXT NOT NULL,
+            status TEXT NOT NULL,
+            file_path TEXT,
+            record_count INTEGER,
+            error TEXT,
+            triggered_by TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_export_jobs_period ON export_jobs(job_type, period_start)",
+        [],
+    )?;
+
+    Ok(())
+}
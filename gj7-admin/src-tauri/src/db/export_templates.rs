@@ -0,0 +1,493 @@
+// src/db/export_templates.rs
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use log::info;
+use rusqlite::{params, Connection, Result, Row};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::db::attendance::Attendance;
+use crate::db::institution_time::{institution_timezone, DEFAULT_INSTITUTION_TIMEZONE};
+use crate::db::school_accounts::{SchoolAccount, SchoolAccountRepository, SqliteSchoolAccountRepository};
+use crate::db::locations::{LocationRepository, SqliteLocationRepository};
+use crate::db::group_visits::{GroupVisitRepository, SqliteGroupVisitRepository};
+use crate::db::events::{EventRepository, SqliteEventRepository};
+
+// Fields an export can include; the account ones are joined from school_accounts by school_id
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum ExportColumn {
+    Id,
+    SchoolId,
+    FullName,
+    Date,
+    TimeIn,
+    TimeOut,
+    DurationMinutes,
+    Classification,
+    Purpose,
+    AutoClosed,
+    Course,
+    YearLevel,
+    Department,
+    Position,
+    Major,
+    Location,
+    Device,
+    GroupVisit,
+    Event,
+}
+
+impl ExportColumn {
+    pub fn default_header(&self) -> &'static str {
+        match self {
+            ExportColumn::Id => "ID",
+            ExportColumn::SchoolId => "School ID",
+            ExportColumn::FullName => "Full Name",
+            ExportColumn::Date => "Date",
+            ExportColumn::TimeIn => "Time In",
+            ExportColumn::TimeOut => "Time Out",
+            ExportColumn::DurationMinutes => "Duration (mins)",
+            ExportColumn::Classification => "Classification",
+            ExportColumn::Purpose => "Purpose",
+            ExportColumn::AutoClosed => "Auto Closed",
+            ExportColumn::Course => "Course",
+            ExportColumn::YearLevel => "Year Level",
+            ExportColumn::Department => "Department",
+            ExportColumn::Position => "Position",
+            ExportColumn::Major => "Major",
+            ExportColumn::Location => "Location",
+            ExportColumn::Device => "Device",
+            ExportColumn::GroupVisit => "Group Visit",
+            ExportColumn::Event => "Event",
+        }
+    }
+
+    pub fn is_account_field(&self) -> bool {
+        matches!(
+            self,
+            ExportColumn::Course | ExportColumn::YearLevel | ExportColumn::Department
+                | ExportColumn::Position | ExportColumn::Major
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportTemplateColumn {
+    pub column: ExportColumn,
+    // Falls back to the column's default header when empty
+    pub header: Option<String>,
+}
+
+impl ExportTemplateColumn {
+    pub fn header(&self) -> String {
+        self.header.clone()
+            .filter(|h| !h.trim().is_empty())
+            .unwrap_or_else(|| self.column.default_header().to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExportTemplate {
+    pub id: Uuid,
+    pub name: String,
+    // In output order
+    pub columns: Vec<ExportTemplateColumn>,
+    // strftime patterns, e.g. "%m/%d/%Y" and "%I:%M %p"
+    pub date_format: String,
+    pub time_format: String,
+    pub delimiter: String,
+    // IANA name such as "Asia/Manila"; None uses the institution timezone setting
+    pub timezone: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CreateExportTemplateRequest {
+    pub name: String,
+    pub columns: Vec<ExportTemplateColumn>,
+    pub date_format: String,
+    pub time_format: String,
+    pub delimiter: String,
+    pub timezone: Option<String>,
+}
+
+// Related records an export joins in, loaded once per export
+#[derive(Default)]
+pub struct ExportLookups {
+    // school_id -> account, only loaded when the template shows account fields
+    pub accounts: HashMap<String, SchoolAccount>,
+    pub locations: HashMap<Uuid, String>,
+    // group_id -> label, only loaded when the template has a Group Visit column
+    pub group_visits: HashMap<Uuid, String>,
+    pub events: HashMap<Uuid, String>,
+}
+
+impl ExportLookups {
+    pub fn account(&self, attendance: &Attendance) -> Option<&SchoolAccount> {
+        self.accounts.get(&attendance.school_id)
+    }
+
+    pub fn location_name(&self, attendance: &Attendance) -> Option<String> {
+        attendance.location_id.and_then(|id| self.locations.get(&id).cloned())
+    }
+
+    pub fn group_visit_label(&self, attendance: &Attendance) -> Option<String> {
+        attendance.group_id.and_then(|id| self.group_visits.get(&id).cloned())
+    }
+
+    pub fn event_name(&self, attendance: &Attendance) -> Option<String> {
+        attendance.event_id.and_then(|id| self.events.get(&id).cloned())
+    }
+}
+
+// A value ready to be written, kept typed so the XLSX writer can store real dates
+pub enum ExportValue {
+    Text(String),
+    Number(f64),
+    Date(NaiveDateTime),
+    Time(NaiveDateTime),
+    Empty,
+}
+
+fn builtin(name: &str, columns: &[ExportColumn]) -> ExportTemplate {
+    ExportTemplate {
+        id: Uuid::nil(),
+        name: name.to_string(),
+        columns: columns.iter()
+            .map(|column| ExportTemplateColumn { column: *column, header: None })
+            .collect(),
+        date_format: "%m/%d/%Y".to_string(),
+        time_format: "%I:%M %p".to_string(),
+        delimiter: ",".to_string(),
+        timezone: None,
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+    }
+}
+
+impl ExportTemplate {
+    // Layout the CSV export has always used
+    pub fn builtin_csv() -> Self {
+        let mut template = builtin("Default CSV", &[
+            ExportColumn::Id,
+            ExportColumn::SchoolId,
+            ExportColumn::FullName,
+            ExportColumn::Date,
+            ExportColumn::TimeIn,
+            ExportColumn::TimeOut,
+            ExportColumn::DurationMinutes,
+            ExportColumn::Classification,
+            ExportColumn::Purpose,
+        ]);
+        template.columns[4].header = Some("Time".to_string());
+        template
+    }
+
+    // Layout the XLSX export has always used
+    pub fn builtin_xlsx() -> Self {
+        builtin("Default XLSX", &[
+            ExportColumn::Id,
+            ExportColumn::SchoolId,
+            ExportColumn::FullName,
+            ExportColumn::Course,
+            ExportColumn::Date,
+            ExportColumn::TimeIn,
+            ExportColumn::TimeOut,
+            ExportColumn::DurationMinutes,
+            ExportColumn::Classification,
+            ExportColumn::Purpose,
+        ])
+    }
+
+    pub fn needs_accounts(&self) -> bool {
+        self.columns.iter().any(|c| c.column.is_account_field())
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.header()).collect()
+    }
+
+    pub fn delimiter_byte(&self) -> u8 {
+        self.delimiter.bytes().next().unwrap_or(b',')
+    }
+
+    // Templates from resolve_template always carry a timezone; the default only covers bad names
+    pub fn tz(&self) -> Tz {
+        self.timezone.as_deref()
+            .and_then(|tz| tz.parse::<Tz>().ok())
+            .unwrap_or(DEFAULT_INSTITUTION_TIMEZONE)
+    }
+
+    fn to_local(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        time.with_timezone(&self.tz()).naive_local()
+    }
+
+    // Day the attendance belongs to in the template's timezone
+    pub fn local_date(&self, time: DateTime<Utc>) -> chrono::NaiveDate {
+        self.to_local(time).date()
+    }
+
+    pub fn values(&self, attendance: &Attendance, lookups: &ExportLookups) -> Vec<ExportValue> {
+        let text = |value: Option<String>| match value {
+            Some(value) if !value.is_empty() => ExportValue::Text(value),
+            _ => ExportValue::Empty,
+        };
+        let account = lookups.account(attendance);
+        let account_text = |f: fn(&SchoolAccount) -> Option<String>| text(account.and_then(f));
+
+        self.columns.iter().map(|c| match c.column {
+            ExportColumn::Id => ExportValue::Text(attendance.id.to_string()),
+            ExportColumn::SchoolId => ExportValue::Text(attendance.school_id.clone()),
+            ExportColumn::FullName => ExportValue::Text(attendance.full_name.clone()),
+            ExportColumn::Date => ExportValue::Date(self.to_local(attendance.time_in_date)),
+            ExportColumn::TimeIn => ExportValue::Time(self.to_local(attendance.time_in_date)),
+            ExportColumn::TimeOut => attendance.time_out_date
+                .map(|t| ExportValue::Time(self.to_local(t)))
+                .unwrap_or(ExportValue::Empty),
+            ExportColumn::DurationMinutes => attendance.duration_minutes
+                .map(|d| ExportValue::Number(d as f64))
+                .unwrap_or(ExportValue::Empty),
+            ExportColumn::Classification => ExportValue::Text(attendance.classification.clone()),
+            ExportColumn::Purpose => text(attendance.purpose_label.clone()),
+            ExportColumn::AutoClosed => ExportValue::Text(if attendance.is_auto_closed { "Yes" } else { "No" }.to_string()),
+            ExportColumn::Course => account_text(|a| a.course.clone()),
+            ExportColumn::YearLevel => account_text(|a| a.year_level.clone()),
+            ExportColumn::Department => account_text(|a| a.department.clone()),
+            ExportColumn::Position => account_text(|a| a.position.clone()),
+            ExportColumn::Major => account_text(|a| a.major.clone()),
+            ExportColumn::Location => text(lookups.location_name(attendance)),
+            ExportColumn::Device => text(attendance.device_id.clone()),
+            ExportColumn::GroupVisit => text(lookups.group_visit_label(attendance)),
+            ExportColumn::Event => text(lookups.event_name(attendance)),
+        }).collect()
+    }
+
+    // Values as text using the template's date and time formats
+    pub fn text_values(&self, attendance: &Attendance, lookups: &ExportLookups) -> Vec<String> {
+        self.values(attendance, lookups).into_iter().map(|value| match value {
+            ExportValue::Text(value) => value,
+            ExportValue::Number(value) => value.to_string(),
+            ExportValue::Date(value) => value.format(&self.date_format).to_string(),
+            ExportValue::Time(value) => value.format(&self.time_format).to_string(),
+            ExportValue::Empty => String::new(),
+        }).collect()
+    }
+}
+
+// Exports format naive local times, so besides bad specifiers this also rejects the ones that need
+// an offset (%Z, %z, %+, ...), which would otherwise fail halfway through an export
+fn validate_format(format: &str, label: &str) -> Result<()> {
+    let mut sample = String::new();
+    if format.trim().is_empty() || write!(sample, "{}", NaiveDateTime::default().format(format)).is_err() {
+        return Err(rusqlite::Error::InvalidParameterName(format!("Invalid {} format: {}", label, format)));
+    }
+    Ok(())
+}
+
+fn validate_request(request: &CreateExportTemplateRequest) -> Result<()> {
+    if request.name.trim().is_empty() {
+        return Err(rusqlite::Error::InvalidParameterName("Template name cannot be empty".to_string()));
+    }
+    if request.columns.is_empty() {
+        return Err(rusqlite::Error::InvalidParameterName("Template needs at least one column".to_string()));
+    }
+    if request.delimiter.len() != 1 || !request.delimiter.is_ascii() {
+        return Err(rusqlite::Error::InvalidParameterName("Delimiter must be a single character".to_string()));
+    }
+    validate_format(&request.date_format, "date")?;
+    validate_format(&request.time_format, "time")?;
+
+    if let Some(timezone) = request.timezone.as_deref().filter(|tz| !tz.is_empty()) {
+        if timezone.parse::<Tz>().is_err() {
+            return Err(rusqlite::Error::InvalidParameterName(format!("Unknown timezone: {}", timezone)));
+        }
+    }
+    Ok(())
+}
+
+fn to_json_error(e: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(Box::new(e))
+}
+
+fn row_to_template(row: &Row) -> Result<ExportTemplate> {
+    let columns: String = row.get(2)?;
+    let datetime = |idx: usize| -> Result<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&row.get::<_, String>(idx)?)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e)))
+    };
+
+    Ok(ExportTemplate {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        name: row.get(1)?,
+        columns: serde_json::from_str(&columns)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?,
+        date_format: row.get(3)?,
+        time_format: row.get(4)?,
+        delimiter: row.get(5)?,
+        timezone: row.get(6)?,
+        created_at: datetime(7)?,
+        updated_at: datetime(8)?,
+    })
+}
+
+const TEMPLATE_COLUMNS: &str = "id, name, columns_json, date_format, time_format, delimiter, timezone, created_at, updated_at";
+
+pub trait ExportTemplateRepository: Send + Sync {
+    fn create_template(&self, conn: &Connection, template: CreateExportTemplateRequest) -> Result<ExportTemplate>;
+    fn get_template(&self, conn: &Connection, id: Uuid) -> Result<ExportTemplate>;
+    fn update_template(&self, conn: &Connection, id: Uuid, template: CreateExportTemplateRequest) -> Result<ExportTemplate>;
+    fn delete_template(&self, conn: &Connection, id: Uuid) -> Result<()>;
+    fn get_all_templates(&self, conn: &Connection) -> Result<Vec<ExportTemplate>>;
+}
+
+pub struct SqliteExportTemplateRepository;
+
+impl ExportTemplateRepository for SqliteExportTemplateRepository {
+    fn create_template(&self, conn: &Connection, template: CreateExportTemplateRequest) -> Result<ExportTemplate> {
+        validate_request(&template)?;
+
+        let id = Uuid::new_v4();
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO export_templates (
+                id, name, columns_json, date_format, time_format, delimiter, timezone, created_at, updated_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+            params![
+                id.to_string(),
+                template.name.trim(),
+                serde_json::to_string(&template.columns).map_err(to_json_error)?,
+                template.date_format,
+                template.time_format,
+                template.delimiter,
+                template.timezone.filter(|tz| !tz.is_empty()),
+                now
+            ],
+        )?;
+
+        info!("Created export template: {}", template.name);
+        self.get_template(conn, id)
+    }
+
+    fn get_template(&self, conn: &Connection, id: Uuid) -> Result<ExportTemplate> {
+        conn.query_row(
+            &format!("SELECT {} FROM export_templates WHERE id = ?1", TEMPLATE_COLUMNS),
+            params![id.to_string()],
+            row_to_template,
+        )
+    }
+
+    fn update_template(&self, conn: &Connection, id: Uuid, template: CreateExportTemplateRequest) -> Result<ExportTemplate> {
+        validate_request(&template)?;
+
+        let updated = conn.execute(
+            "UPDATE export_templates SET
+                name = ?1, columns_json = ?2, date_format = ?3, time_format = ?4,
+                delimiter = ?5, timezone = ?6, updated_at = ?7
+             WHERE id = ?8",
+            params![
+                template.name.trim(),
+                serde_json::to_string(&template.columns).map_err(to_json_error)?,
+                template.date_format,
+                template.time_format,
+                template.delimiter,
+                template.timezone.filter(|tz| !tz.is_empty()),
+                Utc::now().to_rfc3339(),
+                id.to_string()
+            ],
+        )?;
+
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        self.get_template(conn, id)
+    }
+
+    fn delete_template(&self, conn: &Connection, id: Uuid) -> Result<()> {
+        let deleted = conn.execute("DELETE FROM export_templates WHERE id = ?1", params![id.to_string()])?;
+        if deleted == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+        Ok(())
+    }
+
+    fn get_all_templates(&self, conn: &Connection) -> Result<Vec<ExportTemplate>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM export_templates ORDER BY name COLLATE NOCASE",
+            TEMPLATE_COLUMNS
+        ))?;
+
+        let templates = stmt.query_map([], row_to_template)?;
+        templates.collect()
+    }
+}
+
+// Looks up a stored template, or the given built-in layout when no id is passed
+pub fn resolve_template(conn: &Connection, id: Option<Uuid>, builtin: fn() -> ExportTemplate) -> Result<ExportTemplate> {
+    let mut template = match id {
+        Some(id) => SqliteExportTemplateRepository.get_template(conn, id)?,
+        None => builtin(),
+    };
+
+    // Templates without a timezone of their own follow the institution's
+    if template.timezone.as_deref().is_none_or(|tz| tz.trim().is_empty()) {
+        template.timezone = Some(institution_timezone(conn)?.name().to_string());
+    }
+    Ok(template)
+}
+
+// Accounts are only loaded when the template shows account fields; locations are always few
+pub fn lookups_for_template(conn: &Connection, template: &ExportTemplate) -> Result<ExportLookups> {
+    let accounts = if template.needs_accounts() {
+        SqliteSchoolAccountRepository.get_all_school_accounts(conn)?
+            .into_iter()
+            .map(|account| (account.school_id.clone(), account))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let locations = SqliteLocationRepository.get_all_locations(conn, true)?
+        .into_iter()
+        .map(|location| (location.id, location.name))
+        .collect();
+
+    let group_visits = if template.columns.iter().any(|c| c.column == ExportColumn::GroupVisit) {
+        SqliteGroupVisitRepository.get_group_visits(conn, None, None)?
+            .into_iter()
+            .map(|group| (group.id, group.label(template.tz())))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let events = SqliteEventRepository.get_events(conn, true)?
+        .into_iter()
+        .map(|event| (event.id, event.name))
+        .collect();
+
+    Ok(ExportLookups { accounts, locations, group_visits, events })
+}
+
+pub fn create_export_templates_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS export_templates (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            columns_json TEXT NOT NULL,
+            date_format TEXT NOT NULL,
+            time_format TEXT NOT NULL,
+            delimiter TEXT NOT NULL DEFAULT ',',
+            timezone TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,442 @@
+// src/db/group_visits.rs
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::db::attendance::{
+    in_savepoint,
+    known_identity,
+    Attendance,
+    AttendanceQuery,
+    AttendanceRepository,
+    AttendanceSort,
+    CreateAttendanceRequest,
+    SqliteAttendanceRepository,
+    FACULTY_CLASSIFICATION,
+};
+use crate::db::account_status::{
+    AccountStatusInfo,
+    AccountStatusRepository,
+    KioskPolicy,
+    SqliteAccountStatusRepository,
+};
+use crate::db::locations::{LocationRepository, SqliteLocationRepository};
+
+const GROUP_VISIT_COLUMNS: &str = "
+    g.id, g.faculty_school_id, g.faculty_name, g.course, g.year_level, g.purpose_label,
+    g.location_id, g.started_at, g.ended_at, g.started_by, g.device_id,
+    (SELECT COUNT(*) FROM attendance a WHERE a.group_id = g.id)
+";
+
+// A faculty member bringing a class to the library; every student's row shares the group id
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GroupVisit {
+    pub id: Uuid,
+    pub faculty_school_id: String,
+    pub faculty_name: String,
+    // Section picked from school_accounts, if any; students can also be scanned one by one
+    pub course: Option<String>,
+    pub year_level: Option<String>,
+    pub purpose_label: Option<String>,
+    pub location_id: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
+    // None while students can still be added
+    pub ended_at: Option<DateTime<Utc>>,
+    pub started_by: String,
+    // Kiosk the group was started on; None when started from the admin app
+    pub device_id: Option<String>,
+    // Attendance rows in the group, the faculty member's own included
+    pub member_count: u64,
+}
+
+impl GroupVisit {
+    // How the group is named in reports, dated in the given timezone
+    pub fn label(&self, tz: Tz) -> String {
+        let section = [self.course.as_deref(), self.year_level.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<&str>>()
+            .join(" ");
+        let date = self.started_at.with_timezone(&tz).format("%Y-%m-%d");
+
+        if section.is_empty() {
+            format!("{} ({})", self.faculty_name, date)
+        } else {
+            format!("{} - {} ({})", self.faculty_name, section, date)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StartGroupVisitRequest {
+    pub faculty_school_id: String,
+    pub purpose_label: Option<String>,
+    // Same precedence as a kiosk scan: the kiosk's binding, else this, else the default location
+    #[serde(default)]
+    pub location_id: Option<Uuid>,
+    #[serde(default)]
+    pub kiosk_id: Option<String>,
+    // Filled in by the network server from the connection, never taken from the client
+    #[serde(default, skip_deserializing)]
+    pub device_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AddGroupSectionRequest {
+    pub course: String,
+    // None takes every year level of the course
+    pub year_level: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AddGroupMemberRequest {
+    pub school_id: String,
+}
+
+// Course and year level combination the faculty can pick a section from
+#[derive(Debug, Serialize, Clone)]
+pub struct GroupVisitSection {
+    pub course: String,
+    pub year_level: Option<String>,
+    pub account_count: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GroupVisitSkip {
+    pub school_id: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GroupSectionResult {
+    pub group_visit: GroupVisit,
+    pub checked_in: Vec<Attendance>,
+    pub skipped: Vec<GroupVisitSkip>,
+}
+
+fn parse_datetime_column(row: &Row, idx: usize) -> Result<Option<DateTime<Utc>>> {
+    match row.get::<_, Option<String>>(idx)? {
+        Some(value) => DateTime::parse_from_rfc3339(&value)
+            .map(|dt| Some(dt.with_timezone(&Utc)))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))),
+        None => Ok(None),
+    }
+}
+
+fn row_to_group_visit(row: &Row) -> Result<GroupVisit> {
+    Ok(GroupVisit {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        faculty_school_id: row.get(1)?,
+        faculty_name: row.get(2)?,
+        course: row.get(3)?,
+        year_level: row.get(4)?,
+        purpose_label: row.get(5)?,
+        location_id: row.get::<_, Option<String>>(6)?
+            .and_then(|id| Uuid::parse_str(&id).ok()),
+        started_at: parse_datetime_column(row, 7)?.unwrap_or_else(Utc::now),
+        ended_at: parse_datetime_column(row, 8)?,
+        started_by: row.get(9)?,
+        device_id: row.get(10)?,
+        member_count: row.get::<_, i64>(11)? as u64,
+    })
+}
+
+pub trait GroupVisitRepository: Send + Sync {
+    // Checks the faculty member in as the first member of a new group
+    fn start_group_visit(&self, conn: &Connection, request: StartGroupVisitRequest, actor: &str) -> Result<GroupVisit>;
+    fn get_group_visit(&self, conn: &Connection, id: Uuid) -> Result<GroupVisit>;
+    // Groups started within the range, newest first
+    fn get_group_visits(
+        &self,
+        conn: &Connection,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>
+    ) -> Result<Vec<GroupVisit>>;
+    // Group the faculty member started and has not ended yet
+    fn get_open_group_visit(&self, conn: &Connection, faculty_school_id: &str) -> Result<Option<GroupVisit>>;
+    fn get_group_members(&self, conn: &Connection, id: Uuid) -> Result<Vec<Attendance>>;
+    fn get_sections(&self, conn: &Connection) -> Result<Vec<GroupVisitSection>>;
+    // One scanned student
+    fn add_group_member(&self, conn: &Connection, id: Uuid, school_id: &str, actor: &str) -> Result<Attendance>;
+    // Every active account of the section in one transaction; students that cannot be checked in are skipped
+    fn add_group_section(&self, conn: &Connection, id: Uuid, section: AddGroupSectionRequest, actor: &str) -> Result<GroupSectionResult>;
+    // Checks out every member still inside and closes the group
+    fn end_group_visit(&self, conn: &Connection, id: Uuid, actor: &str) -> Result<GroupVisit>;
+}
+
+pub struct SqliteGroupVisitRepository;
+
+impl SqliteGroupVisitRepository {
+    fn check_in(&self, conn: &Connection, group: &GroupVisit, school_id: &str, actor: &str) -> Result<Attendance> {
+        let attendances = SqliteAttendanceRepository;
+        let school_id = school_id.trim();
+        if school_id.is_empty() {
+            return Err(rusqlite::Error::InvalidParameterName("School ID cannot be empty".to_string()));
+        }
+
+        let (full_name, classification) = known_identity(conn, school_id)?;
+
+        if let Some(AccountStatusInfo { policy: KioskPolicy::Refuse, message, .. }) =
+            SqliteAccountStatusRepository.get_account_status(conn, school_id)?
+        {
+            return Err(rusqlite::Error::InvalidParameterName(
+                format!("Entry refused: {}", message.unwrap_or_else(|| "account is restricted".to_string()))
+            ));
+        }
+
+        match attendances.get_open_attendance(conn, school_id)? {
+            Some(open) if open.group_id == Some(group.id) => {
+                return Err(rusqlite::Error::InvalidParameterName(
+                    format!("{} is already checked in with this group", school_id)
+                ));
+            },
+            // A student already inside joins the class, ending their own visit
+            Some(open) => {
+                attendances.close_attendance(conn, open.id, Utc::now(), false, actor)?;
+            },
+            None => {},
+        }
+
+        attendances.create_attendance(conn, CreateAttendanceRequest {
+            school_id: school_id.to_string(),
+            full_name,
+            classification: Some(classification),
+            purpose_label: group.purpose_label.clone(),
+            location_id: group.location_id,
+            kiosk_id: None,
+            device_id: group.device_id.clone(),
+            group_id: Some(group.id),
+            event_id: None,
+        }, actor)
+    }
+
+    fn get_open(&self, conn: &Connection, id: Uuid) -> Result<GroupVisit> {
+        let group = self.get_group_visit(conn, id)?;
+        if group.ended_at.is_some() {
+            return Err(rusqlite::Error::InvalidParameterName("This group visit has already ended".to_string()));
+        }
+        Ok(group)
+    }
+}
+
+impl GroupVisitRepository for SqliteGroupVisitRepository {
+    fn start_group_visit(&self, conn: &Connection, request: StartGroupVisitRequest, actor: &str) -> Result<GroupVisit> {
+        let faculty_school_id = request.faculty_school_id.trim().to_string();
+        let (faculty_name, classification) = known_identity(conn, &faculty_school_id)?;
+        if classification != FACULTY_CLASSIFICATION {
+            return Err(rusqlite::Error::InvalidParameterName("Only faculty accounts can start a group visit".to_string()));
+        }
+
+        if self.get_open_group_visit(conn, &faculty_school_id)?.is_some() {
+            return Err(rusqlite::Error::InvalidParameterName(
+                format!("{} already has a group visit in progress", faculty_name)
+            ));
+        }
+
+        let location_id = SqliteLocationRepository.resolve_location(
+            conn,
+            request.location_id,
+            request.kiosk_id.as_deref()
+        )?;
+        let id = Uuid::new_v4();
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO group_visits (
+                id, faculty_school_id, faculty_name, purpose_label, location_id, started_at, started_by,
+                device_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                id.to_string(),
+                faculty_school_id,
+                faculty_name,
+                request.purpose_label,
+                location_id.map(|id| id.to_string()),
+                Utc::now().to_rfc3339(),
+                actor,
+                request.device_id
+            ],
+        )?;
+
+        let group = self.get_group_visit(&tx, id)?;
+        self.check_in(&tx, &group, &faculty_school_id, actor)?;
+        tx.commit()?;
+
+        info!("{} started group visit {}", faculty_name, id);
+        self.get_group_visit(conn, id)
+    }
+
+    fn get_group_visit(&self, conn: &Connection, id: Uuid) -> Result<GroupVisit> {
+        conn.query_row(
+            &format!("SELECT {} FROM group_visits g WHERE g.id = ?1", GROUP_VISIT_COLUMNS),
+            params![id.to_string()],
+            row_to_group_visit,
+        )
+    }
+
+    fn get_group_visits(
+        &self,
+        conn: &Connection,
+        date_from: Option<DateTime<Utc>>,
+        date_to: Option<DateTime<Utc>>
+    ) -> Result<Vec<GroupVisit>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM group_visits g
+             WHERE (?1 IS NULL OR g.started_at >= ?1) AND (?2 IS NULL OR g.started_at <= ?2)
+             ORDER BY g.started_at DESC",
+            GROUP_VISIT_COLUMNS
+        ))?;
+
+        let groups = stmt.query_map(
+            params![date_from.map(|d| d.to_rfc3339()), date_to.map(|d| d.to_rfc3339())],
+            row_to_group_visit
+        )?;
+        groups.collect()
+    }
+
+    fn get_open_group_visit(&self, conn: &Connection, faculty_school_id: &str) -> Result<Option<GroupVisit>> {
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM group_visits g
+                 WHERE g.faculty_school_id = ?1 AND g.ended_at IS NULL
+                 ORDER BY g.started_at DESC LIMIT 1",
+                GROUP_VISIT_COLUMNS
+            ),
+            params![faculty_school_id],
+            row_to_group_visit,
+        ).optional()
+    }
+
+    fn get_group_members(&self, conn: &Connection, id: Uuid) -> Result<Vec<Attendance>> {
+        SqliteAttendanceRepository.get_all_matching_attendances(conn, &AttendanceQuery {
+            group_visits: vec![id],
+            sort: AttendanceSort::NameAsc,
+            ..Default::default()
+        })
+    }
+
+    fn get_sections(&self, conn: &Connection) -> Result<Vec<GroupVisitSection>> {
+        let mut stmt = conn.prepare(
+            "SELECT course, NULLIF(year_level, ''), COUNT(*)
+             FROM school_accounts
+             WHERE is_active = 1 AND course IS NOT NULL AND course != ''
+             GROUP BY course, NULLIF(year_level, '')
+             ORDER BY course ASC, year_level ASC"
+        )?;
+
+        let sections = stmt.query_map([], |row| {
+            Ok(GroupVisitSection {
+                course: row.get(0)?,
+                year_level: row.get(1)?,
+                account_count: row.get::<_, i64>(2)? as u64,
+            })
+        })?;
+        sections.collect()
+    }
+
+    fn add_group_member(&self, conn: &Connection, id: Uuid, school_id: &str, actor: &str) -> Result<Attendance> {
+        let group = self.get_open(conn, id)?;
+        in_savepoint(conn, || self.check_in(conn, &group, school_id, actor))
+    }
+
+    fn add_group_section(&self, conn: &Connection, id: Uuid, section: AddGroupSectionRequest, actor: &str) -> Result<GroupSectionResult> {
+        let group = self.get_open(conn, id)?;
+        let course = section.course.trim().to_string();
+        let year_level = section.year_level.map(|y| y.trim().to_string()).filter(|y| !y.is_empty());
+        if course.is_empty() {
+            return Err(rusqlite::Error::InvalidParameterName("Course cannot be empty".to_string()));
+        }
+
+        let school_ids: Vec<String> = {
+            let mut stmt = conn.prepare(
+                "SELECT school_id FROM school_accounts
+                 WHERE is_active = 1 AND course = ?1 AND (?2 IS NULL OR year_level = ?2)
+                 ORDER BY last_name COLLATE NOCASE, first_name COLLATE NOCASE"
+            )?;
+            let rows = stmt.query_map(params![course, year_level], |row| row.get(0))?;
+            rows.collect::<Result<Vec<String>>>()?
+        };
+        if school_ids.is_empty() {
+            return Err(rusqlite::Error::InvalidParameterName("No active accounts in this section".to_string()));
+        }
+
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "UPDATE group_visits SET course = ?1, year_level = ?2 WHERE id = ?3",
+            params![course, year_level, id.to_string()],
+        )?;
+
+        let mut checked_in = Vec::new();
+        let mut skipped = Vec::new();
+        for school_id in school_ids {
+            // The faculty member is already in the group
+            if school_id == group.faculty_school_id {
+                continue;
+            }
+
+            // A skipped student keeps any visit of their own that check_in would have closed
+            match in_savepoint(&tx, || self.check_in(&tx, &group, &school_id, actor)) {
+                Ok(attendance) => checked_in.push(attendance),
+                Err(rusqlite::Error::InvalidParameterName(reason)) => skipped.push(GroupVisitSkip { school_id, reason }),
+                Err(e) => return Err(e),
+            }
+        }
+        tx.commit()?;
+
+        Ok(GroupSectionResult {
+            group_visit: self.get_group_visit(conn, id)?,
+            checked_in,
+            skipped,
+        })
+    }
+
+    fn end_group_visit(&self, conn: &Connection, id: Uuid, actor: &str) -> Result<GroupVisit> {
+        self.get_open(conn, id)?;
+        let attendances = SqliteAttendanceRepository;
+        let now = Utc::now();
+
+        let tx = conn.unchecked_transaction()?;
+        for member in self.get_group_members(&tx, id)? {
+            if member.time_out_date.is_none() {
+                attendances.close_attendance(&tx, member.id, now, false, actor)?;
+            }
+        }
+        tx.execute(
+            "UPDATE group_visits SET ended_at = ?1 WHERE id = ?2",
+            params![now.to_rfc3339(), id.to_string()],
+        )?;
+        tx.commit()?;
+
+        self.get_group_visit(conn, id)
+    }
+}
+
+pub fn create_group_visits_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS group_visits (
+            id TEXT PRIMARY KEY,
+            faculty_school_id TEXT NOT NULL,
+            faculty_name TEXT NOT NULL,
+            course TEXT,
+            year_level TEXT,
+            purpose_label TEXT,
+            location_id TEXT,
+            started_at TEXT NOT NULL,
+            ended_at TEXT,
+            started_by TEXT NOT NULL,
+            device_id TEXT
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_group_visits_started_at ON group_visits(started_at)",
+        [],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+// src/db/institution_time.rs
+//
+// Attendance is stored in UTC, but a "day" is the institution's calendar day. Everything that
+// groups, filters or prints by day goes through here instead of chrono::Local or SQLite's
+// 'localtime', so results don't depend on the timezone of the computer running the app.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use rusqlite::{Connection, Result};
+
+use crate::db::app_settings::{AppSettingsDatabase, INSTITUTION_TIMEZONE};
+
+// Used until the setting is changed, and when the stored name is not a known IANA timezone
+pub const DEFAULT_INSTITUTION_TIMEZONE: Tz = chrono_tz::Asia::Manila;
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+pub fn institution_timezone(conn: &Connection) -> Result<Tz> {
+    AppSettingsDatabase.get_timezone(conn, INSTITUTION_TIMEZONE, DEFAULT_INSTITUTION_TIMEZONE)
+}
+
+// The institution's calendar date at `time`
+pub fn local_date(tz: Tz, time: DateTime<Utc>) -> NaiveDate {
+    time.with_timezone(&tz).date_naive()
+}
+
+// The instant the institution's clocks show `time` on `date`. A repeated hour resolves to its first
+// occurrence; a time skipped by a daylight saving change is read with the offset from before the jump,
+// which lands just after it
+pub fn at_local_time(tz: Tz, date: NaiveDate, time: NaiveTime) -> DateTime<Utc> {
+    let local = date.and_time(time);
+    match tz.from_local_datetime(&local).earliest() {
+        Some(t) => t.with_timezone(&Utc),
+        None => {
+            let before = tz.offset_from_utc_datetime(&(local - Duration::days(1))).fix();
+            Utc.from_utc_datetime(&(local - Duration::seconds(before.local_minus_utc() as i64)))
+        },
+    }
+}
+
+pub fn start_of_day(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    at_local_time(tz, date, NaiveTime::MIN)
+}
+
+// [start, end) of the institution's day `date`. Not always 24 hours where daylight saving applies
+pub fn day_range(tz: Tz, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let next = date.succ_opt().unwrap_or(date);
+    (start_of_day(tz, date), start_of_day(tz, next))
+}
+
+fn offset_seconds(tz: Tz, unix_seconds: i64) -> i64 {
+    let time = DateTime::from_timestamp(unix_seconds, 0).unwrap_or_default();
+    tz.offset_from_utc_datetime(&time.naive_utc()).fix().local_minus_utc() as i64
+}
+
+// SQL expression shifting `seconds_sql`, a Unix time in seconds, to the institution's wall clock, for
+// SQLite's date functions with the 'unixepoch' modifier, e.g. strftime('%H', <expr>, 'unixepoch').
+// SQLite only knows the computer's timezone, so each offset change between `from` and `to` becomes a
+// CASE branch; times outside that range use the offset at the nearest end
+pub fn local_seconds_sql(tz: Tz, seconds_sql: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> String {
+    let from = from.timestamp();
+    let to = to.timestamp().max(from);
+
+    let first_offset = offset_seconds(tz, from);
+    // (first second of the new offset, new offset)
+    let mut changes = Vec::new();
+    let mut offset = first_offset;
+    let mut day_start = from;
+    while day_start < to {
+        let day_end = (day_start + SECONDS_PER_DAY).min(to);
+        let end_offset = offset_seconds(tz, day_end);
+        if end_offset != offset {
+            let (mut before, mut after) = (day_start, day_end);
+            while after - before > 1 {
+                let middle = before + (after - before) / 2;
+                if offset_seconds(tz, middle) == offset {
+                    before = middle;
+                } else {
+                    after = middle;
+                }
+            }
+            changes.push((after, end_offset));
+            offset = end_offset;
+        }
+        day_start = day_end;
+    }
+
+    if changes.is_empty() {
+        return format!("({} + {})", seconds_sql, first_offset);
+    }
+
+    let mut sql = format!("({} + CASE", seconds_sql);
+    let mut offset = first_offset;
+    for (at, next_offset) in changes {
+        sql.push_str(&format!(" WHEN {} < {} THEN {}", seconds_sql, at, offset));
+        offset = next_offset;
+    }
+    sql.push_str(&format!(" ELSE {} END)", offset));
+    sql
+}
@@ -0,0 +1,406 @@
+// src/db/locations.rs
+
+use std::net::IpAddr;
+use chrono::{DateTime, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::db::add_column_if_missing;
+
+// Created on first start so existing single-room installs keep working unchanged
+const DEFAULT_LOCATION_NAME: &str = "Main Library";
+
+const LOCATION_COLUMNS: &str = "id, name, description, is_default, is_active, created_at, capacity";
+const KIOSK_COLUMNS: &str = "kiosk_id, name, location_id, registered_at, last_seen_at, event_id";
+
+// A room, section or entrance that attendance is recorded at
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Location {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    // Where scans from unbound kiosks and admin entries without a location go
+    pub is_default: bool,
+    // Inactive locations stay on old records but can no longer be chosen
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    // Most people allowed inside at once under fire-safety rules, None for no limit
+    pub capacity: Option<u32>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct CreateLocationRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub is_default: Option<bool>,
+    pub is_active: Option<bool>,
+    #[serde(default)]
+    pub capacity: Option<u32>,
+}
+
+// A kiosk identifies itself with an id chosen at setup; the admin binds it to a location
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Kiosk {
+    pub kiosk_id: String,
+    pub name: Option<String>,
+    pub location_id: Option<Uuid>,
+    pub registered_at: DateTime<Utc>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+    // Set while the kiosk is in event mode; its scans are recorded against that event
+    pub event_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RegisterKioskRequest {
+    pub kiosk_id: String,
+    pub name: Option<String>,
+}
+
+fn parse_datetime_column(row: &Row, idx: usize) -> Result<Option<DateTime<Utc>>> {
+    match row.get::<_, Option<String>>(idx)? {
+        Some(value) => DateTime::parse_from_rfc3339(&value)
+            .map(|dt| Some(dt.with_timezone(&Utc)))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))),
+        None => Ok(None),
+    }
+}
+
+fn row_to_location(row: &Row) -> Result<Location> {
+    Ok(Location {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        name: row.get(1)?,
+        description: row.get(2)?,
+        is_default: row.get(3)?,
+        is_active: row.get(4)?,
+        created_at: parse_datetime_column(row, 5)?.unwrap_or_else(Utc::now),
+        capacity: row.get::<_, Option<i64>>(6)?.map(|c| c as u32),
+    })
+}
+
+fn row_to_kiosk(row: &Row) -> Result<Kiosk> {
+    Ok(Kiosk {
+        kiosk_id: row.get(0)?,
+        name: row.get(1)?,
+        location_id: row.get::<_, Option<String>>(2)?
+            .and_then(|id| Uuid::parse_str(&id).ok()),
+        registered_at: parse_datetime_column(row, 3)?.unwrap_or_else(Utc::now),
+        last_seen_at: parse_datetime_column(row, 4)?,
+        event_id: row.get::<_, Option<String>>(5)?
+            .and_then(|id| Uuid::parse_str(&id).ok()),
+    })
+}
+
+fn clean_text(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn validate_name(conn: &Connection, name: &str, except: Option<Uuid>) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(rusqlite::Error::InvalidParameterName("Location name cannot be empty".to_string()));
+    }
+
+    let taken: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM locations WHERE name = ?1 COLLATE NOCASE AND id IS NOT ?2)",
+        params![name, except.map(|id| id.to_string())],
+        |row| row.get(0),
+    )?;
+    if taken {
+        return Err(rusqlite::Error::InvalidParameterName(format!("A location named '{}' already exists", name)));
+    }
+
+    Ok(name.to_string())
+}
+
+fn validate_capacity(capacity: Option<u32>) -> Result<Option<u32>> {
+    if capacity == Some(0) {
+        return Err(rusqlite::Error::InvalidParameterName("Capacity must be at least 1".to_string()));
+    }
+    Ok(capacity)
+}
+
+pub trait LocationRepository: Send + Sync {
+    fn create_location(&self, conn: &Connection, location: CreateLocationRequest) -> Result<Location>;
+    fn get_location(&self, conn: &Connection, id: Uuid) -> Result<Location>;
+    fn update_location(&self, conn: &Connection, id: Uuid, location: CreateLocationRequest) -> Result<Location>;
+    // Only locations no attendance was ever recorded at can be deleted; the rest are deactivated
+    fn delete_location(&self, conn: &Connection, id: Uuid) -> Result<()>;
+    fn get_all_locations(&self, conn: &Connection, include_inactive: bool) -> Result<Vec<Location>>;
+    fn get_default_location(&self, conn: &Connection) -> Result<Option<Location>>;
+    // Admin action: adds the kiosk, or renames it when it already exists
+    fn register_kiosk(&self, conn: &Connection, kiosk: RegisterKioskRequest) -> Result<Kiosk>;
+    fn get_kiosk(&self, conn: &Connection, kiosk_id: &str) -> Result<Option<Kiosk>>;
+    // Kiosk contact over the network: refreshes last_seen_at of a registered kiosk, never adds one
+    fn touch_kiosk(&self, conn: &Connection, kiosk_id: &str) -> Result<Option<Kiosk>>;
+    fn get_all_kiosks(&self, conn: &Connection) -> Result<Vec<Kiosk>>;
+    // None unbinds the kiosk, which then records at the default location
+    fn bind_kiosk(&self, conn: &Connection, kiosk_id: &str, location_id: Option<Uuid>) -> Result<Kiosk>;
+    // Location a scan is recorded at: the kiosk's binding, else the requested one, else the default
+    fn resolve_location(&self, conn: &Connection, location_id: Option<Uuid>, kiosk_id: Option<&str>) -> Result<Option<Uuid>>;
+}
+
+pub struct SqliteLocationRepository;
+
+impl SqliteLocationRepository {
+    fn set_default(&self, conn: &Connection, id: Uuid) -> Result<()> {
+        conn.execute("UPDATE locations SET is_default = (id = ?1)", params![id.to_string()])?;
+        Ok(())
+    }
+}
+
+impl LocationRepository for SqliteLocationRepository {
+    fn create_location(&self, conn: &Connection, location: CreateLocationRequest) -> Result<Location> {
+        let name = validate_name(conn, &location.name, None)?;
+        let capacity = validate_capacity(location.capacity)?;
+        let id = Uuid::new_v4();
+
+        conn.execute(
+            "INSERT INTO locations (id, name, description, is_default, is_active, created_at, capacity)
+             VALUES (?1, ?2, ?3, 0, ?4, ?5, ?6)",
+            params![
+                id.to_string(),
+                name,
+                clean_text(location.description),
+                location.is_active.unwrap_or(true),
+                Utc::now().to_rfc3339(),
+                capacity
+            ],
+        )?;
+
+        if location.is_default.unwrap_or(false) {
+            self.set_default(conn, id)?;
+        }
+
+        info!("Created location {}", name);
+        self.get_location(conn, id)
+    }
+
+    fn get_location(&self, conn: &Connection, id: Uuid) -> Result<Location> {
+        conn.query_row(
+            &format!("SELECT {} FROM locations WHERE id = ?1", LOCATION_COLUMNS),
+            params![id.to_string()],
+            row_to_location,
+        )
+    }
+
+    fn update_location(&self, conn: &Connection, id: Uuid, location: CreateLocationRequest) -> Result<Location> {
+        let existing = self.get_location(conn, id)?;
+        let name = validate_name(conn, &location.name, Some(id))?;
+        let is_active = location.is_active.unwrap_or(existing.is_active);
+        let capacity = validate_capacity(location.capacity)?;
+
+        if existing.is_default && !is_active {
+            return Err(rusqlite::Error::InvalidParameterName("The default location cannot be deactivated".to_string()));
+        }
+
+        conn.execute(
+            "UPDATE locations SET name = ?1, description = ?2, is_active = ?3, capacity = ?4 WHERE id = ?5",
+            params![name, clean_text(location.description), is_active, capacity, id.to_string()],
+        )?;
+
+        // The default can be moved to another location but never cleared
+        if location.is_default.unwrap_or(false) {
+            self.set_default(conn, id)?;
+        }
+
+        self.get_location(conn, id)
+    }
+
+    fn delete_location(&self, conn: &Connection, id: Uuid) -> Result<()> {
+        let location = self.get_location(conn, id)?;
+        if location.is_default {
+            return Err(rusqlite::Error::InvalidParameterName("The default location cannot be deleted".to_string()));
+        }
+
+        let in_use: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM attendance WHERE location_id = ?1)",
+            params![id.to_string()],
+            |row| row.get(0),
+        )?;
+        if in_use {
+            return Err(rusqlite::Error::InvalidParameterName(
+                "Attendance was recorded at this location; deactivate it instead".to_string()
+            ));
+        }
+
+        conn.execute("UPDATE kiosks SET location_id = NULL WHERE location_id = ?1", params![id.to_string()])?;
+        conn.execute("DELETE FROM locations WHERE id = ?1", params![id.to_string()])?;
+        Ok(())
+    }
+
+    fn get_all_locations(&self, conn: &Connection, include_inactive: bool) -> Result<Vec<Location>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM locations WHERE ?1 OR is_active = 1 ORDER BY is_default DESC, name COLLATE NOCASE",
+            LOCATION_COLUMNS
+        ))?;
+
+        let locations = stmt.query_map(params![include_inactive], row_to_location)?;
+        locations.collect()
+    }
+
+    fn get_default_location(&self, conn: &Connection) -> Result<Option<Location>> {
+        conn.query_row(
+            &format!("SELECT {} FROM locations WHERE is_default = 1 LIMIT 1", LOCATION_COLUMNS),
+            [],
+            row_to_location,
+        ).optional()
+    }
+
+    fn register_kiosk(&self, conn: &Connection, kiosk: RegisterKioskRequest) -> Result<Kiosk> {
+        let kiosk_id = kiosk.kiosk_id.trim();
+        if kiosk_id.is_empty() {
+            return Err(rusqlite::Error::InvalidParameterName("Kiosk id cannot be empty".to_string()));
+        }
+
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "INSERT INTO kiosks (kiosk_id, name, location_id, registered_at, last_seen_at)
+             VALUES (?1, ?2, NULL, ?3, ?3)
+             ON CONFLICT(kiosk_id) DO UPDATE SET
+                name = COALESCE(excluded.name, kiosks.name),
+                last_seen_at = excluded.last_seen_at",
+            params![kiosk_id, clean_text(kiosk.name), now],
+        )?;
+
+        self.get_kiosk(conn, kiosk_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    fn get_kiosk(&self, conn: &Connection, kiosk_id: &str) -> Result<Option<Kiosk>> {
+        conn.query_row(
+            &format!("SELECT {} FROM kiosks WHERE kiosk_id = ?1", KIOSK_COLUMNS),
+            params![kiosk_id.trim()],
+            row_to_kiosk,
+        ).optional()
+    }
+
+    fn touch_kiosk(&self, conn: &Connection, kiosk_id: &str) -> Result<Option<Kiosk>> {
+        conn.execute(
+            "UPDATE kiosks SET last_seen_at = ?1 WHERE kiosk_id = ?2",
+            params![Utc::now().to_rfc3339(), kiosk_id.trim()],
+        )?;
+
+        self.get_kiosk(conn, kiosk_id)
+    }
+
+    fn get_all_kiosks(&self, conn: &Connection) -> Result<Vec<Kiosk>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM kiosks ORDER BY last_seen_at DESC",
+            KIOSK_COLUMNS
+        ))?;
+
+        let kiosks = stmt.query_map([], row_to_kiosk)?;
+        kiosks.collect()
+    }
+
+    fn bind_kiosk(&self, conn: &Connection, kiosk_id: &str, location_id: Option<Uuid>) -> Result<Kiosk> {
+        if let Some(location_id) = location_id {
+            if !self.get_location(conn, location_id)?.is_active {
+                return Err(rusqlite::Error::InvalidParameterName("Location is inactive".to_string()));
+            }
+        }
+
+        let updated = conn.execute(
+            "UPDATE kiosks SET location_id = ?1 WHERE kiosk_id = ?2",
+            params![location_id.map(|id| id.to_string()), kiosk_id.trim()],
+        )?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows);
+        }
+
+        info!("Kiosk {} bound to location {:?}", kiosk_id, location_id);
+        self.get_kiosk(conn, kiosk_id)?.ok_or(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    fn resolve_location(&self, conn: &Connection, location_id: Option<Uuid>, kiosk_id: Option<&str>) -> Result<Option<Uuid>> {
+        if let Some(kiosk_id) = kiosk_id.filter(|k| !k.trim().is_empty()) {
+            if let Some(bound) = self.get_kiosk(conn, kiosk_id)?.and_then(|k| k.location_id) {
+                return Ok(Some(bound));
+            }
+        }
+
+        if let Some(location_id) = location_id {
+            let location = self.get_location(conn, location_id).optional()?
+                .ok_or_else(|| rusqlite::Error::InvalidParameterName("Unknown location".to_string()))?;
+            if !location.is_active {
+                return Err(rusqlite::Error::InvalidParameterName(format!("{} is not active", location.name)));
+            }
+            return Ok(Some(location.id));
+        }
+
+        Ok(self.get_default_location(conn)?.map(|l| l.id))
+    }
+}
+
+// Device id recorded on a kiosk scan: the kiosk id when it is registered (refreshing its
+// last_seen_at), otherwise the client's address and user agent
+pub fn identify_device(
+    conn: &Connection,
+    kiosk_id: Option<&str>,
+    remote_ip: Option<IpAddr>,
+    user_agent: Option<&str>
+) -> Result<String> {
+    if let Some(kiosk_id) = kiosk_id.map(str::trim).filter(|k| !k.is_empty()) {
+        let seen = conn.execute(
+            "UPDATE kiosks SET last_seen_at = ?1 WHERE kiosk_id = ?2",
+            params![Utc::now().to_rfc3339(), kiosk_id],
+        )?;
+        if seen > 0 {
+            return Ok(kiosk_id.to_string());
+        }
+    }
+
+    let address = remote_ip.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".to_string());
+    Ok(match user_agent.map(str::trim).filter(|ua| !ua.is_empty()) {
+        Some(user_agent) => format!("{} ({})", address, user_agent),
+        None => address,
+    })
+}
+
+pub fn create_locations_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS locations (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT,
+            is_default INTEGER NOT NULL DEFAULT 0,
+            is_active INTEGER NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            capacity INTEGER
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_locations_name ON locations(name COLLATE NOCASE)",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS kiosks (
+            kiosk_id TEXT PRIMARY KEY,
+            name TEXT,
+            location_id TEXT,
+            registered_at TEXT NOT NULL,
+            last_seen_at TEXT,
+            event_id TEXT
+        )",
+        [],
+    )?;
+
+    add_column_if_missing(conn, "kiosks", "event_id", "TEXT")?;
+    add_column_if_missing(conn, "locations", "capacity", "INTEGER")?;
+
+    let has_locations: bool = conn.query_row("SELECT EXISTS(SELECT 1 FROM locations)", [], |row| row.get(0))?;
+    if !has_locations {
+        conn.execute(
+            "INSERT INTO locations (id, name, description, is_default, is_active, created_at)
+             VALUES (?1, ?2, NULL, 1, 1, ?3)",
+            params![Uuid::new_v4().to_string(), DEFAULT_LOCATION_NAME, Utc::now().to_rfc3339()],
+        )?;
+        info!("Created default location {}", DEFAULT_LOCATION_NAME);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,315 @@
+// src/db/occupancy.rs
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::db::app_settings::{
+    AppSettingsDatabase,
+    OCCUPANCY_ESTIMATED_STAY_MINUTES,
+    OCCUPANCY_WARNING_PERCENT,
+};
+use crate::db::attendance::{epoch_nanos_bound, start_of_local_day};
+use crate::db::institution_time::{institution_timezone, local_seconds_sql};
+
+const DEFAULT_ESTIMATED_STAY_MINUTES: i64 = 180;
+const DEFAULT_WARNING_PERCENT: i64 = 90;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OccupancyLevel {
+    Normal,
+    // At or above the warning percentage of capacity
+    Warning,
+    Full,
+}
+
+impl OccupancyLevel {
+    fn as_str(&self) -> &'static str {
+        match self {
+            OccupancyLevel::Normal => "Normal",
+            OccupancyLevel::Warning => "Warning",
+            OccupancyLevel::Full => "Full",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        match value {
+            "Normal" => Some(OccupancyLevel::Normal),
+            "Warning" => Some(OccupancyLevel::Warning),
+            "Full" => Some(OccupancyLevel::Full),
+            _ => None,
+        }
+    }
+
+    fn for_count(occupancy: u32, capacity: Option<u32>, warning_percent: i64) -> Self {
+        match capacity {
+            Some(capacity) if occupancy >= capacity => OccupancyLevel::Full,
+            Some(capacity) if occupancy as i64 * 100 >= capacity as i64 * warning_percent => OccupancyLevel::Warning,
+            _ => OccupancyLevel::Normal,
+        }
+    }
+}
+
+// People inside a location right now
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocationOccupancy {
+    pub location_id: Uuid,
+    pub location_name: String,
+    // Open visits minus estimated exits
+    pub occupancy: u32,
+    // Visits timed in today without a time out
+    pub open_visits: u32,
+    // Open visits older than the estimated stay, assumed to have left without checking out
+    pub estimated_exits: u32,
+    pub capacity: Option<u32>,
+    pub level: OccupancyLevel,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OccupancySample {
+    pub location_id: Uuid,
+    pub recorded_at: DateTime<Utc>,
+    pub occupancy: u32,
+    pub capacity: Option<u32>,
+    pub level: OccupancyLevel,
+}
+
+// Highest occupancy of a location on one local day
+#[derive(Debug, Serialize, Clone)]
+pub struct PeakOccupancy {
+    pub location_id: Uuid,
+    pub location_name: String,
+    pub date: NaiveDate,
+    pub peak: u32,
+    pub peak_at: DateTime<Utc>,
+    pub capacity: Option<u32>,
+}
+
+// A location whose occupancy changed since its last stored sample
+#[derive(Debug, Clone)]
+pub struct OccupancyChange {
+    pub occupancy: LocationOccupancy,
+    pub previous_level: OccupancyLevel,
+}
+
+impl OccupancyChange {
+    // Warning or Full when the location just rose into that level; alerts are not repeated while it stays there
+    pub fn crossed_into(&self) -> Option<OccupancyLevel> {
+        match self.occupancy.level {
+            OccupancyLevel::Normal => None,
+            level if level > self.previous_level => Some(level),
+            _ => None,
+        }
+    }
+}
+
+fn parse_datetime_column(row: &Row, idx: usize) -> Result<DateTime<Utc>> {
+    let value: String = row.get(idx)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn row_to_sample(row: &Row) -> Result<OccupancySample> {
+    Ok(OccupancySample {
+        location_id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        recorded_at: parse_datetime_column(row, 1)?,
+        occupancy: row.get::<_, i64>(2)? as u32,
+        capacity: row.get::<_, Option<i64>>(3)?.map(|c| c as u32),
+        level: OccupancyLevel::from_str(&row.get::<_, String>(4)?).unwrap_or(OccupancyLevel::Normal),
+    })
+}
+
+pub trait OccupancyRepository: Send + Sync {
+    // Every active location, including empty ones
+    fn get_current_occupancy(&self, conn: &Connection, now: DateTime<Utc>) -> Result<Vec<LocationOccupancy>>;
+    // Stores a sample for each location whose count or capacity changed and returns those locations
+    fn record_occupancy(&self, conn: &Connection, now: DateTime<Utc>) -> Result<Vec<OccupancyChange>>;
+    fn get_occupancy_history(
+        &self,
+        conn: &Connection,
+        location_id: Option<Uuid>,
+        date_from: DateTime<Utc>,
+        date_to: DateTime<Utc>
+    ) -> Result<Vec<OccupancySample>>;
+    // One row per location and local day, oldest first
+    fn get_peak_occupancy(
+        &self,
+        conn: &Connection,
+        date_from: DateTime<Utc>,
+        date_to: DateTime<Utc>
+    ) -> Result<Vec<PeakOccupancy>>;
+}
+
+pub struct SqliteOccupancyRepository;
+
+impl OccupancyRepository for SqliteOccupancyRepository {
+    fn get_current_occupancy(&self, conn: &Connection, now: DateTime<Utc>) -> Result<Vec<LocationOccupancy>> {
+        let settings = AppSettingsDatabase;
+        let estimated_stay = settings.get_i64(conn, OCCUPANCY_ESTIMATED_STAY_MINUTES, DEFAULT_ESTIMATED_STAY_MINUTES)?;
+        let warning_percent = settings.get_i64(conn, OCCUPANCY_WARNING_PERCENT, DEFAULT_WARNING_PERCENT)?;
+        let left_before = now - Duration::minutes(estimated_stay.max(1));
+
+        // Visits recorded before locations existed count toward the default location
+        let mut stmt = conn.prepare(
+            "SELECT l.id, l.name, l.capacity,
+                COUNT(a.id),
+                COALESCE(SUM(CASE WHEN a.time_in_date < ?2 THEN 1 ELSE 0 END), 0)
+             FROM locations l
+             LEFT JOIN attendance a
+                ON COALESCE(a.location_id, (SELECT id FROM locations WHERE is_default = 1)) = l.id
+                AND a.time_out_date IS NULL
+                AND a.time_in_date >= ?1
+             WHERE l.is_active = 1
+             GROUP BY l.id
+             ORDER BY l.is_default DESC, l.name COLLATE NOCASE"
+        )?;
+
+        let locations = stmt.query_map(
+            params![
+                epoch_nanos_bound(start_of_local_day(institution_timezone(conn)?, now)),
+                epoch_nanos_bound(left_before)
+            ],
+            |row| {
+                let capacity = row.get::<_, Option<i64>>(2)?.map(|c| c as u32);
+                let open_visits = row.get::<_, i64>(3)? as u32;
+                let estimated_exits = row.get::<_, i64>(4)? as u32;
+                let occupancy = open_visits - estimated_exits;
+
+                Ok(LocationOccupancy {
+                    location_id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+                    location_name: row.get(1)?,
+                    occupancy,
+                    open_visits,
+                    estimated_exits,
+                    capacity,
+                    level: OccupancyLevel::for_count(occupancy, capacity, warning_percent),
+                })
+            }
+        )?;
+        locations.collect()
+    }
+
+    fn record_occupancy(&self, conn: &Connection, now: DateTime<Utc>) -> Result<Vec<OccupancyChange>> {
+        let tx = conn.unchecked_transaction()?;
+        let mut changes = Vec::new();
+
+        for occupancy in self.get_current_occupancy(&tx, now)? {
+            let last = tx.query_row(
+                "SELECT location_id, recorded_at, occupancy, capacity, level FROM occupancy_history
+                 WHERE location_id = ?1 ORDER BY recorded_at DESC LIMIT 1",
+                params![occupancy.location_id.to_string()],
+                row_to_sample,
+            ).optional()?;
+
+            let unchanged = last.as_ref()
+                .is_some_and(|l| l.occupancy == occupancy.occupancy && l.capacity == occupancy.capacity);
+            if unchanged {
+                continue;
+            }
+
+            tx.execute(
+                "INSERT INTO occupancy_history (location_id, recorded_at, occupancy, capacity, level)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![
+                    occupancy.location_id.to_string(),
+                    now.to_rfc3339(),
+                    occupancy.occupancy,
+                    occupancy.capacity,
+                    occupancy.level.as_str()
+                ],
+            )?;
+
+            changes.push(OccupancyChange {
+                previous_level: last.map(|l| l.level).unwrap_or(OccupancyLevel::Normal),
+                occupancy,
+            });
+        }
+
+        tx.commit()?;
+        Ok(changes)
+    }
+
+    fn get_occupancy_history(
+        &self,
+        conn: &Connection,
+        location_id: Option<Uuid>,
+        date_from: DateTime<Utc>,
+        date_to: DateTime<Utc>
+    ) -> Result<Vec<OccupancySample>> {
+        let mut stmt = conn.prepare(
+            "SELECT location_id, recorded_at, occupancy, capacity, level FROM occupancy_history
+             WHERE (?1 IS NULL OR location_id = ?1) AND recorded_at >= ?2 AND recorded_at <= ?3
+             ORDER BY recorded_at ASC"
+        )?;
+
+        let samples = stmt.query_map(
+            params![location_id.map(|id| id.to_string()), date_from.to_rfc3339(), date_to.to_rfc3339()],
+            row_to_sample
+        )?;
+        samples.collect()
+    }
+
+    fn get_peak_occupancy(
+        &self,
+        conn: &Connection,
+        date_from: DateTime<Utc>,
+        date_to: DateTime<Utc>
+    ) -> Result<Vec<PeakOccupancy>> {
+        // Samples are grouped by the institution's calendar day
+        let recorded_local = local_seconds_sql(
+            institution_timezone(conn)?,
+            "CAST(strftime('%s', h.recorded_at) AS INTEGER)",
+            date_from,
+            date_to
+        );
+
+        // SQLite takes the bare columns from the row that holds the MAX
+        let mut stmt = conn.prepare(&format!(
+            "SELECT h.location_id, l.name, date({}, 'unixepoch') AS day,
+                MAX(h.occupancy), h.recorded_at, h.capacity
+             FROM occupancy_history h
+             JOIN locations l ON l.id = h.location_id
+             WHERE h.recorded_at >= ?1 AND h.recorded_at <= ?2
+             GROUP BY h.location_id, day
+             ORDER BY day ASC, l.name COLLATE NOCASE",
+            recorded_local
+        ))?;
+
+        let peaks = stmt.query_map(params![date_from.to_rfc3339(), date_to.to_rfc3339()], |row| {
+            let day: String = row.get(2)?;
+            Ok(PeakOccupancy {
+                location_id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+                location_name: row.get(1)?,
+                date: NaiveDate::parse_from_str(&day, "%Y-%m-%d")
+                    .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?,
+                peak: row.get::<_, i64>(3)? as u32,
+                peak_at: parse_datetime_column(row, 4)?,
+                capacity: row.get::<_, Option<i64>>(5)?.map(|c| c as u32),
+            })
+        })?;
+        peaks.collect()
+    }
+}
+
+pub fn create_occupancy_history_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS occupancy_history (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            location_id TEXT NOT NULL,
+            recorded_at TEXT NOT NULL,
+            occupancy INTEGER NOT NULL,
+            capacity INTEGER,
+            level TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_occupancy_history_location_recorded ON occupancy_history(location_id, recorded_at)",
+        [],
+    )?;
+
+    Ok(())
+}
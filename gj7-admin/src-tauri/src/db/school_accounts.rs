@@ -7,6 +7,8 @@ use serde::Deserializer;
 use log::{info, error};
 use rusqlite::Result as SqlResult;
 
+use crate::db::search::{highlight_markup, match_expression, SchoolAccountSearchHit, MATCH_END, MATCH_START};
+
 
 // Enum for gender choices
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -104,7 +106,8 @@ pub trait SchoolAccountRepository: Send {
     
     fn get_all_school_accounts(&self, conn: &Connection) -> Result<Vec<SchoolAccount>>;
 
-    fn search_school_accounts(&self, conn: &Connection, query: &str) -> Result<Vec<SchoolAccount>>;
+    // Ranked full-text search over school ID and name, matching each term as a prefix
+    fn search_school_accounts(&self, conn: &Connection, query: &str, limit: usize) -> Result<Vec<SchoolAccountSearchHit>>;
 
     fn get_paginated_school_accounts(
         &self, 
@@ -466,49 +469,71 @@ impl SchoolAccountRepository for SqliteSchoolAccountRepository {
         })
     }
 
-    fn search_school_accounts(&self, conn: &Connection, query: &str) -> Result<Vec<SchoolAccount>> {
-        let sql = "SELECT * FROM school_accounts 
-                   WHERE school_id LIKE ? OR 
-                         first_name LIKE ? OR 
-                         middle_name LIKE ? OR 
-                         last_name LIKE ?";
-        
-        let search_pattern = format!("%{}%", query);
-        
+    fn search_school_accounts(&self, conn: &Connection, query: &str, limit: usize) -> Result<Vec<SchoolAccountSearchHit>> {
+        let sql = "SELECT sa.id, sa.school_id, sa.first_name, sa.middle_name, sa.last_name, sa.gender,
+                       sa.course, sa.department, sa.position, sa.major, sa.year_level, sa.is_active,
+                       sa.last_updated_semester_id,
+                       highlight(school_accounts_fts, 0, ?2, ?3),
+                       highlight(school_accounts_fts, 1, ?2, ?3),
+                       highlight(school_accounts_fts, 2, ?2, ?3),
+                       highlight(school_accounts_fts, 3, ?2, ?3),
+                       bm25(school_accounts_fts, 10.0, 5.0, 2.0, 5.0) AS score
+                   FROM school_accounts_fts
+                   JOIN school_accounts sa ON sa.rowid = school_accounts_fts.rowid
+                   WHERE school_accounts_fts MATCH ?1
+                   ORDER BY score ASC, sa.last_name COLLATE NOCASE
+                   LIMIT ?4";
+
         let mut stmt = conn.prepare(sql)?;
-        let account_iter = stmt.query_map(params![
-            &search_pattern, 
-            &search_pattern, 
-            &search_pattern, 
-            &search_pattern
-        ], |row| {
-            Ok(SchoolAccount {
-                id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
-                school_id: row.get(1)?,
-                first_name: row.get(2)?,
-                middle_name: row.get(3)?,
-                last_name: row.get(4)?,
-                gender: row.get::<_, Option<i32>>(5)?.map(|g| match g {
-                    0 => Gender::Male,
-                    1 => Gender::Female,
-                    _ => Gender::Other,
-                }),
-                course: row.get(6)?,
-                department: row.get(7)?,
-                position: row.get(8)?,
-                major: row.get(9)?,
-                year_level: row.get(10)?,
-                is_active: row.get(11)?,
-                last_updated_semester_id: row.get::<_, Option<String>>(12)?.map(|id| Uuid::parse_str(&id).unwrap()),
-            })
-        })?;
-    
-        let mut accounts = Vec::new();
-        for account in account_iter {
-            accounts.push(account?);
+        let mut run = |expression: String| -> Result<Vec<SchoolAccountSearchHit>> {
+            let hits = stmt.query_map(params![expression, MATCH_START, MATCH_END, limit as i64], |row| {
+                let full_name_highlight = (14..=16)
+                    .map(|idx| row.get::<_, Option<String>>(idx))
+                    .collect::<Result<Vec<Option<String>>>>()?
+                    .into_iter()
+                    .flatten()
+                    .filter(|part| !part.trim().is_empty())
+                    .map(|part| highlight_markup(&part))
+                    .collect::<Vec<String>>()
+                    .join(" ");
+
+                Ok(SchoolAccountSearchHit {
+                    account: SchoolAccount {
+                        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+                        school_id: row.get(1)?,
+                        first_name: row.get(2)?,
+                        middle_name: row.get(3)?,
+                        last_name: row.get(4)?,
+                        gender: row.get::<_, Option<i32>>(5)?.map(|g| match g {
+                            0 => Gender::Male,
+                            1 => Gender::Female,
+                            _ => Gender::Other,
+                        }),
+                        course: row.get(6)?,
+                        department: row.get(7)?,
+                        position: row.get(8)?,
+                        major: row.get(9)?,
+                        year_level: row.get(10)?,
+                        is_active: row.get(11)?,
+                        last_updated_semester_id: row.get::<_, Option<String>>(12)?.map(|id| Uuid::parse_str(&id).unwrap()),
+                    },
+                    school_id_highlight: highlight_markup(&row.get::<_, String>(13)?),
+                    full_name_highlight,
+                    score: row.get(17)?,
+                })
+            })?;
+            hits.collect()
+        };
+
+        // When no account has every term, fall back to accounts with any of them, best matches first
+        let Some(expression) = match_expression(query, false) else {
+            return Ok(Vec::new());
+        };
+        let hits = run(expression)?;
+        match match_expression(query, true) {
+            Some(any_term) if hits.is_empty() && query.split_whitespace().count() > 1 => run(any_term),
+            _ => Ok(hits),
         }
-    
-        Ok(accounts)
     }
 
     fn get_school_account(&self, conn: &Connection, id: Uuid) -> Result<SchoolAccount> {
@@ -0,0 +1,159 @@
+// src/db/search.rs
+
+use rusqlite::{Connection, Result};
+use serde::Serialize;
+
+use crate::db::attendance::Attendance;
+use crate::db::school_accounts::SchoolAccount;
+
+// Results returned when the caller does not ask for a limit
+pub const DEFAULT_SEARCH_LIMIT: usize = 50;
+
+// highlight() and snippet() wrap matches in these private-use characters. They are swapped for
+// <mark> tags only after the text around them has been escaped, so stored names can't inject markup
+pub(crate) const MATCH_START: &str = "\u{E000}";
+pub(crate) const MATCH_END: &str = "\u{E001}";
+
+// Diacritics are folded and case ignored, so "pena" finds "Peña" and "Ñino" finds "nino"
+const FTS_OPTIONS: &str = "tokenize = 'unicode61 remove_diacritics 2', prefix = '2 3'";
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AttendanceSearchHit {
+    pub attendance: Attendance,
+    // Column values with the matched terms wrapped in <mark>, HTML-escaped
+    pub school_id_highlight: String,
+    pub full_name_highlight: String,
+    // Best matching fragment of any searched column
+    pub snippet: String,
+    // bm25 score, lower is a better match
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SchoolAccountSearchHit {
+    pub account: SchoolAccount,
+    pub school_id_highlight: String,
+    // First, middle and last name, each highlighted
+    pub full_name_highlight: String,
+    pub score: f64,
+}
+
+// Builds an FTS5 query matching every term as a prefix, in any order. Each term is quoted so
+// punctuation in IDs such as "2021-00123" is matched as a phrase rather than parsed as syntax.
+// With any_term set a row only needs one of the terms, which still finds a name with one misspelt part
+pub(crate) fn match_expression(query: &str, any_term: bool) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .filter(|term| term.chars().any(|c| c.is_alphanumeric()))
+        .map(|term| format!("\"{}\"*", term.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(if any_term { " OR " } else { " " }))
+    }
+}
+
+// Escapes highlighted text for HTML and turns the match markers into <mark> tags
+pub(crate) fn highlight_markup(text: &str) -> String {
+    let mut markup = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => markup.push_str("&amp;"),
+            '<' => markup.push_str("&lt;"),
+            '>' => markup.push_str("&gt;"),
+            '"' => markup.push_str("&quot;"),
+            '\'' => markup.push_str("&#39;"),
+            c => markup.push(c),
+        }
+    }
+    markup.replace(MATCH_START, "<mark>").replace(MATCH_END, "</mark>")
+}
+
+fn table_exists(conn: &Connection, name: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE name = ?1)",
+        [name],
+        |row| row.get(0),
+    )
+}
+
+// External-content indexes over attendance and school_accounts, keyed by rowid and kept in sync
+// by triggers. Must run after both tables are created
+pub fn create_search_tables(conn: &Connection) -> Result<()> {
+    let attendance_indexed = table_exists(conn, "attendance_fts")?;
+    conn.execute(
+        &format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS attendance_fts USING fts5(
+                school_id, full_name, purpose_label,
+                content = 'attendance', content_rowid = 'rowid', {}
+            )",
+            FTS_OPTIONS
+        ),
+        [],
+    )?;
+
+    // Checkouts and other edits that leave the searched columns alone don't touch the index
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS attendance_fts_insert AFTER INSERT ON attendance BEGIN
+            INSERT INTO attendance_fts (rowid, school_id, full_name, purpose_label)
+            VALUES (new.rowid, new.school_id, new.full_name, new.purpose_label);
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS attendance_fts_delete AFTER DELETE ON attendance BEGIN
+            INSERT INTO attendance_fts (attendance_fts, rowid, school_id, full_name, purpose_label)
+            VALUES ('delete', old.rowid, old.school_id, old.full_name, old.purpose_label);
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS attendance_fts_update
+         AFTER UPDATE OF school_id, full_name, purpose_label ON attendance BEGIN
+            INSERT INTO attendance_fts (attendance_fts, rowid, school_id, full_name, purpose_label)
+            VALUES ('delete', old.rowid, old.school_id, old.full_name, old.purpose_label);
+            INSERT INTO attendance_fts (rowid, school_id, full_name, purpose_label)
+            VALUES (new.rowid, new.school_id, new.full_name, new.purpose_label);
+         END;"
+    )?;
+
+    let accounts_indexed = table_exists(conn, "school_accounts_fts")?;
+    conn.execute(
+        &format!(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS school_accounts_fts USING fts5(
+                school_id, first_name, middle_name, last_name,
+                content = 'school_accounts', content_rowid = 'rowid', {}
+            )",
+            FTS_OPTIONS
+        ),
+        [],
+    )?;
+
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS school_accounts_fts_insert AFTER INSERT ON school_accounts BEGIN
+            INSERT INTO school_accounts_fts (rowid, school_id, first_name, middle_name, last_name)
+            VALUES (new.rowid, new.school_id, new.first_name, new.middle_name, new.last_name);
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS school_accounts_fts_delete AFTER DELETE ON school_accounts BEGIN
+            INSERT INTO school_accounts_fts (school_accounts_fts, rowid, school_id, first_name, middle_name, last_name)
+            VALUES ('delete', old.rowid, old.school_id, old.first_name, old.middle_name, old.last_name);
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS school_accounts_fts_update
+         AFTER UPDATE OF school_id, first_name, middle_name, last_name ON school_accounts BEGIN
+            INSERT INTO school_accounts_fts (school_accounts_fts, rowid, school_id, first_name, middle_name, last_name)
+            VALUES ('delete', old.rowid, old.school_id, old.first_name, old.middle_name, old.last_name);
+            INSERT INTO school_accounts_fts (rowid, school_id, first_name, middle_name, last_name)
+            VALUES (new.rowid, new.school_id, new.first_name, new.middle_name, new.last_name);
+         END;"
+    )?;
+
+    // Rows that existed before the indexes did are indexed once, when each index is created
+    if !attendance_indexed {
+        conn.execute("INSERT INTO attendance_fts (attendance_fts) VALUES ('rebuild')", [])?;
+    }
+    if !accounts_indexed {
+        conn.execute("INSERT INTO school_accounts_fts (school_accounts_fts) VALUES ('rebuild')", [])?;
+    }
+
+    Ok(())
+}
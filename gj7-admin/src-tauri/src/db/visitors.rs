@@ -0,0 +1,313 @@
+// src/db/visitors.rs
+
+use chrono::{DateTime, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result, Row};
+use rusqlite::types::Value;
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+
+use crate::db::attendance::{epoch_nanos_column, AttendanceQuery, build_query_conditions};
+
+// Classification given to every visit linked to a visitor
+pub const VISITOR_CLASSIFICATION: &str = "Visitor";
+// Error message for kiosk scans rejected by the require_visitor_registration setting
+pub const VISITOR_REGISTRATION_REQUIRED: &str = "Visitor registration required";
+
+// Visitors without an ID of their own get a generated kiosk code with this prefix
+const VISITOR_CODE_PREFIX: &str = "V-";
+
+const VISITOR_COLUMNS: &str = "
+    v.id, v.visitor_code, v.full_name, v.affiliation, v.contact,
+    v.id_document_type, v.id_document_number, v.first_visit_at, v.last_visit_at, v.created_at,
+    (SELECT COUNT(*) FROM attendance WHERE visitor_id = v.id) AS visit_count
+";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Visitor {
+    pub id: Uuid,
+    // What the visitor enters at the kiosk in place of a school ID
+    pub visitor_code: String,
+    pub full_name: String,
+    pub affiliation: Option<String>,
+    pub contact: Option<String>,
+    // e.g. "Driver's License"; only a reference is kept, never a scan of the document
+    pub id_document_type: Option<String>,
+    pub id_document_number: Option<String>,
+    pub first_visit_at: Option<DateTime<Utc>>,
+    pub last_visit_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub visit_count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateVisitorRequest {
+    // Left empty to generate one; on update, empty keeps the current code
+    pub visitor_code: Option<String>,
+    pub full_name: String,
+    pub affiliation: Option<String>,
+    pub contact: Option<String>,
+    pub id_document_type: Option<String>,
+    pub id_document_number: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepeatVisitor {
+    pub visitor: Visitor,
+    // Counted within the query's filters, unlike visitor.visit_count
+    pub visits: u64,
+    pub first_visit_at: DateTime<Utc>,
+    pub last_visit_at: DateTime<Utc>,
+}
+
+fn parse_datetime_column(row: &Row, idx: usize) -> Result<Option<DateTime<Utc>>> {
+    match row.get::<_, Option<String>>(idx)? {
+        Some(value) => DateTime::parse_from_rfc3339(&value)
+            .map(|dt| Some(dt.with_timezone(&Utc)))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e))),
+        None => Ok(None),
+    }
+}
+
+fn row_to_visitor(row: &Row) -> Result<Visitor> {
+    Ok(Visitor {
+        id: Uuid::parse_str(&row.get::<_, String>(0)?).unwrap(),
+        visitor_code: row.get(1)?,
+        full_name: row.get(2)?,
+        affiliation: row.get(3)?,
+        contact: row.get(4)?,
+        id_document_type: row.get(5)?,
+        id_document_number: row.get(6)?,
+        first_visit_at: parse_datetime_column(row, 7)?,
+        last_visit_at: parse_datetime_column(row, 8)?,
+        created_at: parse_datetime_column(row, 9)?.unwrap_or_else(Utc::now),
+        visit_count: row.get(10)?,
+    })
+}
+
+// Trims and turns blank strings into None so optional fields are stored as NULL
+fn clean(value: Option<String>) -> Option<String> {
+    value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+}
+
+fn code_in_use(conn: &Connection, code: &str, exclude: Option<Uuid>) -> Result<bool> {
+    let is_account: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM school_accounts WHERE school_id = ?1)",
+        params![code],
+        |row| row.get(0),
+    )?;
+    if is_account {
+        return Ok(true);
+    }
+
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM visitors WHERE visitor_code = ?1 COLLATE NOCASE AND id != ?2)",
+        params![code, exclude.map(|id| id.to_string()).unwrap_or_default()],
+        |row| row.get(0),
+    )
+}
+
+fn generate_visitor_code(conn: &Connection) -> Result<String> {
+    loop {
+        let suffix = Uuid::new_v4().simple().to_string()[..8].to_uppercase();
+        let code = format!("{}{}", VISITOR_CODE_PREFIX, suffix);
+        if !code_in_use(conn, &code, None)? {
+            return Ok(code);
+        }
+    }
+}
+
+pub trait VisitorRepository: Send + Sync {
+    fn create_visitor(&self, conn: &Connection, visitor: CreateVisitorRequest) -> Result<Visitor>;
+    fn get_visitor(&self, conn: &Connection, id: Uuid) -> Result<Visitor>;
+    fn get_visitor_by_code(&self, conn: &Connection, visitor_code: &str) -> Result<Option<Visitor>>;
+    fn update_visitor(&self, conn: &Connection, id: Uuid, visitor: CreateVisitorRequest) -> Result<Visitor>;
+    // Matches name, code, affiliation or ID document number
+    fn get_all_visitors(&self, conn: &Connection, search: Option<String>) -> Result<Vec<Visitor>>;
+    // Widens first/last visit to include a visit at the given time
+    fn record_visit(&self, conn: &Connection, id: Uuid, visited_at: DateTime<Utc>) -> Result<()>;
+    fn get_repeat_visitors(&self, conn: &Connection, query: &AttendanceQuery, min_visits: u64) -> Result<Vec<RepeatVisitor>>;
+}
+
+pub struct SqliteVisitorRepository;
+
+impl VisitorRepository for SqliteVisitorRepository {
+    fn create_visitor(&self, conn: &Connection, visitor: CreateVisitorRequest) -> Result<Visitor> {
+        let full_name = visitor.full_name.trim().to_string();
+        if full_name.is_empty() {
+            return Err(rusqlite::Error::InvalidParameterName("Visitor name cannot be empty".to_string()));
+        }
+
+        let visitor_code = match clean(visitor.visitor_code) {
+            Some(code) => {
+                if code_in_use(conn, &code, None)? {
+                    return Err(rusqlite::Error::InvalidParameterName(
+                        format!("ID {} already belongs to a school account or visitor", code)
+                    ));
+                }
+                code
+            },
+            None => generate_visitor_code(conn)?,
+        };
+
+        let id = Uuid::new_v4();
+        conn.execute(
+            "INSERT INTO visitors (
+                id, visitor_code, full_name, affiliation, contact,
+                id_document_type, id_document_number, created_at
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                id.to_string(),
+                visitor_code,
+                full_name,
+                clean(visitor.affiliation),
+                clean(visitor.contact),
+                clean(visitor.id_document_type),
+                clean(visitor.id_document_number),
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+
+        info!("Registered visitor {} ({})", full_name, visitor_code);
+        self.get_visitor(conn, id)
+    }
+
+    fn get_visitor(&self, conn: &Connection, id: Uuid) -> Result<Visitor> {
+        conn.query_row(
+            &format!("SELECT {} FROM visitors v WHERE v.id = ?1", VISITOR_COLUMNS),
+            params![id.to_string()],
+            row_to_visitor,
+        )
+    }
+
+    fn get_visitor_by_code(&self, conn: &Connection, visitor_code: &str) -> Result<Option<Visitor>> {
+        conn.query_row(
+            &format!("SELECT {} FROM visitors v WHERE v.visitor_code = ?1 COLLATE NOCASE", VISITOR_COLUMNS),
+            params![visitor_code.trim()],
+            row_to_visitor,
+        ).optional()
+    }
+
+    fn update_visitor(&self, conn: &Connection, id: Uuid, visitor: CreateVisitorRequest) -> Result<Visitor> {
+        let existing = self.get_visitor(conn, id)?;
+
+        let full_name = visitor.full_name.trim().to_string();
+        if full_name.is_empty() {
+            return Err(rusqlite::Error::InvalidParameterName("Visitor name cannot be empty".to_string()));
+        }
+
+        let visitor_code = match clean(visitor.visitor_code) {
+            Some(code) if !code.eq_ignore_ascii_case(&existing.visitor_code) => {
+                if code_in_use(conn, &code, Some(id))? {
+                    return Err(rusqlite::Error::InvalidParameterName(
+                        format!("ID {} already belongs to a school account or visitor", code)
+                    ));
+                }
+                code
+            },
+            _ => existing.visitor_code,
+        };
+
+        conn.execute(
+            "UPDATE visitors SET
+                visitor_code = ?1, full_name = ?2, affiliation = ?3, contact = ?4,
+                id_document_type = ?5, id_document_number = ?6
+             WHERE id = ?7",
+            params![
+                visitor_code,
+                full_name,
+                clean(visitor.affiliation),
+                clean(visitor.contact),
+                clean(visitor.id_document_type),
+                clean(visitor.id_document_number),
+                id.to_string()
+            ],
+        )?;
+
+        self.get_visitor(conn, id)
+    }
+
+    fn get_all_visitors(&self, conn: &Connection, search: Option<String>) -> Result<Vec<Visitor>> {
+        let pattern = clean(search).map(|s| format!("%{}%", s));
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM visitors v
+             WHERE ?1 IS NULL
+                OR v.full_name LIKE ?1
+                OR v.visitor_code LIKE ?1
+                OR v.affiliation LIKE ?1
+                OR v.id_document_number LIKE ?1
+             ORDER BY v.last_visit_at IS NULL, v.last_visit_at DESC, v.full_name COLLATE NOCASE",
+            VISITOR_COLUMNS
+        ))?;
+
+        let visitors = stmt.query_map(params![pattern], row_to_visitor)?;
+        visitors.collect()
+    }
+
+    fn record_visit(&self, conn: &Connection, id: Uuid, visited_at: DateTime<Utc>) -> Result<()> {
+        let visited_at = visited_at.to_rfc3339();
+        conn.execute(
+            "UPDATE visitors SET
+                first_visit_at = CASE WHEN first_visit_at IS NULL OR first_visit_at > ?1 THEN ?1 ELSE first_visit_at END,
+                last_visit_at = CASE WHEN last_visit_at IS NULL OR last_visit_at < ?1 THEN ?1 ELSE last_visit_at END
+             WHERE id = ?2",
+            params![visited_at, id.to_string()],
+        )?;
+        Ok(())
+    }
+
+    fn get_repeat_visitors(&self, conn: &Connection, query: &AttendanceQuery, min_visits: u64) -> Result<Vec<RepeatVisitor>> {
+        let (where_clause, mut param_values) = build_query_conditions(query);
+        param_values.push(Value::Integer(min_visits.max(1) as i64));
+
+        let sql = format!(
+            "SELECT {}, COUNT(a.id) AS visits, MIN(a.time_in_date), MAX(a.time_in_date)
+             FROM attendance a
+             JOIN visitors v ON v.id = a.visitor_id
+             LEFT JOIN school_accounts sa ON a.school_id = sa.school_id
+             {}
+             GROUP BY v.id
+             HAVING COUNT(a.id) >= ?
+             ORDER BY visits DESC, MAX(a.time_in_date) DESC",
+            VISITOR_COLUMNS,
+            where_clause
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let visitors = stmt.query_map(rusqlite::params_from_iter(param_values.iter()), |row| {
+            Ok(RepeatVisitor {
+                visitor: row_to_visitor(row)?,
+                visits: row.get(11)?,
+                first_visit_at: epoch_nanos_column(row, 12)?,
+                last_visit_at: epoch_nanos_column(row, 13)?,
+            })
+        })?;
+        visitors.collect()
+    }
+}
+
+pub fn create_visitors_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS visitors (
+            id TEXT PRIMARY KEY,
+            visitor_code TEXT NOT NULL,
+            full_name TEXT NOT NULL,
+            affiliation TEXT,
+            contact TEXT,
+            id_document_type TEXT,
+            id_document_number TEXT,
+            first_visit_at TEXT,
+            last_visit_at TEXT,
+            created_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS idx_visitors_visitor_code
+         ON visitors(visitor_code COLLATE NOCASE)",
+        [],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,167 @@
+// src/event_commands.rs
+
+use tauri::State;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::DbState;
+use crate::db::attendance::Attendance;
+use crate::db::events::{CreateEventRequest, Event};
+use crate::db::locations::Kiosk;
+
+#[tauri::command]
+pub async fn get_events(
+    state: State<'_, DbState>,
+    include_ended: Option<bool>
+) -> Result<Vec<Event>, String> {
+    let db = state.0.clone();
+    let event_repo = Arc::clone(&db.event_repository);
+
+    db.with_connection(move |conn| {
+        event_repo.get_events(conn, include_ended.unwrap_or(false))
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_event(
+    state: State<'_, DbState>,
+    id: Uuid
+) -> Result<Event, String> {
+    let db = state.0.clone();
+    let event_repo = Arc::clone(&db.event_repository);
+
+    db.with_connection(move |conn| {
+        event_repo.get_event(conn, id)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_event(
+    state: State<'_, DbState>,
+    event: CreateEventRequest,
+    username: String,
+    password: String
+) -> Result<Event, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let event_repo = Arc::clone(&db.event_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            event_repo.create_event(conn, event)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_event(
+    state: State<'_, DbState>,
+    id: Uuid,
+    event: CreateEventRequest,
+    username: String,
+    password: String
+) -> Result<Event, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let event_repo = Arc::clone(&db.event_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            event_repo.update_event(conn, id, event)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_event(
+    state: State<'_, DbState>,
+    id: Uuid,
+    username: String,
+    password: String
+) -> Result<(), String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let event_repo = Arc::clone(&db.event_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            event_repo.delete_event(conn, id)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_event_registrations(
+    state: State<'_, DbState>,
+    id: Uuid
+) -> Result<Vec<String>, String> {
+    let db = state.0.clone();
+    let event_repo = Arc::clone(&db.event_repository);
+
+    db.with_connection(move |conn| {
+        event_repo.get_event_registrations(conn, id)
+    }).await.map_err(|e| e.to_string())
+}
+
+// Replaces the whole pre-registration list; an empty list opens the event to its allowed classifications
+#[tauri::command]
+pub async fn set_event_registrations(
+    state: State<'_, DbState>,
+    id: Uuid,
+    school_ids: Vec<String>,
+    username: String,
+    password: String
+) -> Result<Event, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let event_repo = Arc::clone(&db.event_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            event_repo.set_event_registrations(conn, id, school_ids)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+// Per-event attendance list; exports use the regular export commands with an `events` filter
+#[tauri::command]
+pub async fn get_event_attendances(
+    state: State<'_, DbState>,
+    id: Uuid
+) -> Result<Vec<Attendance>, String> {
+    let db = state.0.clone();
+    let event_repo = Arc::clone(&db.event_repository);
+
+    db.with_connection(move |conn| {
+        event_repo.get_event_attendances(conn, id)
+    }).await.map_err(|e| e.to_string())
+}
+
+// An event_id of None returns the kiosk to normal scanning
+#[tauri::command]
+pub async fn set_kiosk_event_mode(
+    state: State<'_, DbState>,
+    kiosk_id: String,
+    event_id: Option<Uuid>,
+    username: String,
+    password: String
+) -> Result<Kiosk, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let event_repo = Arc::clone(&db.event_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            event_repo.set_kiosk_event(conn, &kiosk_id, event_id)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
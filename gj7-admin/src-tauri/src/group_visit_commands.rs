@@ -0,0 +1,136 @@
+// src/group_visit_commands.rs
+
+use tauri::State;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::DbState;
+use crate::db::attendance::Attendance;
+use crate::db::group_visits::{
+    AddGroupSectionRequest,
+    GroupSectionResult,
+    GroupVisit,
+    GroupVisitSection,
+    StartGroupVisitRequest,
+};
+
+#[tauri::command]
+pub async fn get_group_visits(
+    state: State<'_, DbState>,
+    date_from: Option<DateTime<Utc>>,
+    date_to: Option<DateTime<Utc>>
+) -> Result<Vec<GroupVisit>, String> {
+    let db = state.0.clone();
+    let group_visit_repo = Arc::clone(&db.group_visit_repository);
+
+    db.with_connection(move |conn| {
+        group_visit_repo.get_group_visits(conn, date_from, date_to)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_group_visit_members(
+    state: State<'_, DbState>,
+    id: Uuid
+) -> Result<Vec<Attendance>, String> {
+    let db = state.0.clone();
+    let group_visit_repo = Arc::clone(&db.group_visit_repository);
+
+    db.with_connection(move |conn| {
+        group_visit_repo.get_group_members(conn, id)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_group_visit_sections(
+    state: State<'_, DbState>
+) -> Result<Vec<GroupVisitSection>, String> {
+    let db = state.0.clone();
+    let group_visit_repo = Arc::clone(&db.group_visit_repository);
+
+    db.with_connection(move |conn| {
+        group_visit_repo.get_sections(conn)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn start_group_visit(
+    state: State<'_, DbState>,
+    request: StartGroupVisitRequest,
+    username: String,
+    password: String
+) -> Result<GroupVisit, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let group_visit_repo = Arc::clone(&db.group_visit_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            group_visit_repo.start_group_visit(conn, request, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn add_group_visit_member(
+    state: State<'_, DbState>,
+    id: Uuid,
+    school_id: String,
+    username: String,
+    password: String
+) -> Result<Attendance, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let group_visit_repo = Arc::clone(&db.group_visit_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            group_visit_repo.add_group_member(conn, id, &school_id, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn add_group_visit_section(
+    state: State<'_, DbState>,
+    id: Uuid,
+    section: AddGroupSectionRequest,
+    username: String,
+    password: String
+) -> Result<GroupSectionResult, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let group_visit_repo = Arc::clone(&db.group_visit_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            group_visit_repo.add_group_section(conn, id, section, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn end_group_visit(
+    state: State<'_, DbState>,
+    id: Uuid,
+    username: String,
+    password: String
+) -> Result<GroupVisit, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let group_visit_repo = Arc::clone(&db.group_visit_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            group_visit_repo.end_group_visit(conn, id, &username)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
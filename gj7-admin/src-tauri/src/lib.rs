@@ -18,6 +18,21 @@ mod logger;
 mod parallel_csv_processor;
 mod parallel_csv_validator;
 mod redis_csv_processor;
+mod app_settings_commands;
+mod attendance_auto_close;
+mod attendance_export_scheduler;
+mod attendance_analytics_commands;
+mod xlsx_export;
+mod pdf_report;
+mod visitor_commands;
+mod location_commands;
+mod group_visit_commands;
+mod event_commands;
+mod clearance_commands;
+mod occupancy_commands;
+mod occupancy_monitor;
+mod attendance_retention;
+mod archive_commands;
 
 use tauri::Manager;
 use tauri::Emitter;
@@ -28,6 +43,9 @@ use rusqlite::Result;
 use network::check_network;
 use first_launch::handle_first_launch;
 use network_server::start_network_server;
+use attendance_auto_close::start_auto_close_task;
+use attendance_retention::start_retention_task;
+use attendance_export_scheduler::start_export_scheduler_task;
 use log::error;
 use storage::AppStorage;
 use std::time::Duration;
@@ -211,6 +229,15 @@ pub fn run() {
                     // Manage database state
                     app_handle.manage(DbState(db.clone()));
 
+                    // Close visits left open past closing time
+                    tauri::async_runtime::spawn(start_auto_close_task(db.clone()));
+
+                    // Export the day's attendance and the weekly/monthly rollups on schedule
+                    tauri::async_runtime::spawn(start_export_scheduler_task(db.clone()));
+
+                    // Move attendance past the retention settings into the archive database
+                    tauri::async_runtime::spawn(start_retention_task(db.clone()));
+
                     // Start network server
                     if let Err(e) = start_network_server(db).await {
                         error!("Failed to start network server: {}", e);
@@ -238,6 +265,12 @@ pub fn run() {
 
                 // School account commands
                 school_account_commands::get_all_school_accounts,
+                school_account_commands::search_school_accounts,
+                school_account_commands::export_school_accounts_to_xlsx,
+                school_account_commands::get_account_status,
+                school_account_commands::get_account_restrictions,
+                school_account_commands::set_account_restriction,
+                school_account_commands::clear_account_restriction,
                 school_account_commands::get_paginated_school_accounts,
                 school_account_commands::get_school_account_with_semester,
                 school_account_commands::update_school_account_semester,
@@ -249,6 +282,8 @@ pub fn run() {
                 csv_commands::import_csv_file,
                 csv_commands::import_csv_file_parallel,
                 csv_commands::check_existing_accounts,
+                csv_commands::validate_attendance_csv,
+                csv_commands::import_attendance_csv,
 
                 // Semester commands
                 semester_commands::create_semester,
@@ -272,13 +307,99 @@ pub fn run() {
                 attendance_commands::create_attendance,
                 attendance_commands::get_all_attendances,
                 attendance_commands::get_attendance,
+                attendance_commands::search_attendances,
                 attendance_commands::update_attendance,
                 attendance_commands::delete_attendance,
+                attendance_commands::restore_attendance,
+                attendance_commands::get_attendance_history,
+                attendance_commands::get_recent_attendance_audit,
+                attendance_commands::get_deleted_attendances,
+                attendance_commands::verify_attendance_chain,
+                attendance_commands::verify_export_digest,
+                attendance_commands::verify_export_file,
                 attendance_commands::get_attendances_by_semester,
                 attendance_commands::get_attendances_by_school_account,
                 attendance_commands::get_filtered_attendances,
+                attendance_commands::query_attendances,
                 attendance_commands::get_all_courses,
+                attendance_commands::get_attendance_devices,
+                attendance_commands::record_manual_attendances,
                 attendance_commands::export_attendances_to_csv,
+                attendance_commands::export_attendances_to_xlsx,
+                attendance_commands::generate_attendance_report_pdf,
+                attendance_commands::check_out_attendance,
+                attendance_commands::auto_close_open_attendances,
+                attendance_commands::get_export_job_history,
+                attendance_commands::run_attendance_export,
+                attendance_commands::get_export_templates,
+                attendance_commands::create_export_template,
+                attendance_commands::update_export_template,
+                attendance_commands::delete_export_template,
+
+                // Attendance analytics commands
+                attendance_analytics_commands::get_attendance_heatmap,
+                attendance_analytics_commands::get_attendance_time_series,
+                attendance_analytics_commands::get_attendance_breakdown,
+                attendance_analytics_commands::get_attendance_summary,
+
+                // Visitor commands
+                visitor_commands::create_visitor,
+                visitor_commands::update_visitor,
+                visitor_commands::get_visitor,
+                visitor_commands::get_all_visitors,
+                visitor_commands::get_repeat_visitors,
+
+                // Location and kiosk commands
+                location_commands::get_locations,
+                location_commands::create_location,
+                location_commands::update_location,
+                location_commands::delete_location,
+                location_commands::get_kiosks,
+                location_commands::register_kiosk,
+                location_commands::bind_kiosk_location,
+
+                // Group visit commands
+                group_visit_commands::get_group_visits,
+                group_visit_commands::get_group_visit_members,
+                group_visit_commands::get_group_visit_sections,
+                group_visit_commands::start_group_visit,
+                group_visit_commands::add_group_visit_member,
+                group_visit_commands::add_group_visit_section,
+                group_visit_commands::end_group_visit,
+
+                // Event commands
+                event_commands::get_events,
+                event_commands::get_event,
+                event_commands::create_event,
+                event_commands::update_event,
+                event_commands::delete_event,
+                event_commands::get_event_registrations,
+                event_commands::set_event_registrations,
+                event_commands::get_event_attendances,
+                event_commands::set_kiosk_event_mode,
+
+                // Clearance commands
+                clearance_commands::get_clearance,
+                clearance_commands::get_clearances,
+                clearance_commands::get_clearance_report,
+                clearance_commands::export_clearance_report_to_xlsx,
+                clearance_commands::place_clearance_hold,
+                clearance_commands::resolve_clearance_hold,
+                clearance_commands::sign_off_clearances,
+
+                // Occupancy commands
+                occupancy_commands::get_current_occupancy,
+                occupancy_commands::get_occupancy_history,
+                occupancy_commands::get_peak_occupancy,
+
+                // Archive commands
+                archive_commands::get_archive_status,
+                archive_commands::run_retention_now,
+
+                // App settings commands
+                app_settings_commands::get_all_app_settings,
+                app_settings_commands::get_app_setting,
+                app_settings_commands::update_app_setting,
 
                 // Settings Styles commands
                 settings_styles_commands::create_settings_style,
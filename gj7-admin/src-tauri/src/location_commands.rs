@@ -0,0 +1,136 @@
+// src/location_commands.rs
+
+use tauri::State;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::DbState;
+use crate::db::locations::{CreateLocationRequest, Kiosk, Location, RegisterKioskRequest};
+
+#[tauri::command]
+pub async fn get_locations(
+    state: State<'_, DbState>,
+    include_inactive: Option<bool>
+) -> Result<Vec<Location>, String> {
+    let db = state.0.clone();
+    let location_repo = Arc::clone(&db.location_repository);
+
+    db.with_connection(move |conn| {
+        location_repo.get_all_locations(conn, include_inactive.unwrap_or(false))
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_location(
+    state: State<'_, DbState>,
+    location: CreateLocationRequest,
+    username: String,
+    password: String
+) -> Result<Location, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let location_repo = Arc::clone(&db.location_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            location_repo.create_location(conn, location)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_location(
+    state: State<'_, DbState>,
+    id: Uuid,
+    location: CreateLocationRequest,
+    username: String,
+    password: String
+) -> Result<Location, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let location_repo = Arc::clone(&db.location_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            location_repo.update_location(conn, id, location)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_location(
+    state: State<'_, DbState>,
+    id: Uuid,
+    username: String,
+    password: String
+) -> Result<(), String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let location_repo = Arc::clone(&db.location_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            location_repo.delete_location(conn, id)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_kiosks(
+    state: State<'_, DbState>
+) -> Result<Vec<Kiosk>, String> {
+    let db = state.0.clone();
+    let location_repo = Arc::clone(&db.location_repository);
+
+    db.with_connection(move |conn| {
+        location_repo.get_all_kiosks(conn)
+    }).await.map_err(|e| e.to_string())
+}
+
+// Kiosks have to be registered here before they can look up their binding over the network
+#[tauri::command]
+pub async fn register_kiosk(
+    state: State<'_, DbState>,
+    kiosk: RegisterKioskRequest,
+    username: String,
+    password: String
+) -> Result<Kiosk, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let location_repo = Arc::clone(&db.location_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            location_repo.register_kiosk(conn, kiosk)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+// A location_id of None unbinds the kiosk
+#[tauri::command]
+pub async fn bind_kiosk_location(
+    state: State<'_, DbState>,
+    kiosk_id: String,
+    location_id: Option<Uuid>,
+    username: String,
+    password: String
+) -> Result<Kiosk, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let location_repo = Arc::clone(&db.location_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            location_repo.bind_kiosk(conn, &kiosk_id, location_id)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
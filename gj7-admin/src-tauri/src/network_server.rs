@@ -3,15 +3,17 @@
 use axum::{
     routing::{get, post},
     Router,
-    extract::{State, Path},
+    extract::{ConnectInfo, State, Path},
     Json,
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
 };
 use rusqlite::{Connection, params};
 use tokio::net::TcpListener;
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use serde::{Serialize, Deserialize};
 use tower_http::cors::CorsLayer;
+use uuid::Uuid;
 use crate::Database;
 
 // Use the DatabaseAccessor from websocket module
@@ -19,16 +21,31 @@ use crate::websocket::{
     websocket_handler, 
     WebSocketState, 
     AppState, 
+    ClientInfo,
     DatabaseAccessor
 };
 
-// Existing structs remain the same
+// What the kiosk should do with a looked up ID
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum LookupStatus {
+    Account,
+    Visitor,
+    // Not a school account or known visitor; the kiosk registers them before logging the visit
+    VisitorRegistrationRequired,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SchoolIdLookupResponse {
     pub school_id: String,
     pub full_name: String,
     pub purposes: HashMap<String, PurposeLookup>,
     pub classification: String,
+    pub status: LookupStatus,
+    pub visitor_id: Option<Uuid>,
+    // Only for accounts; the kiosk shows its message when the policy is Warn or Refuse
+    pub account_status: Option<AccountStatusInfo>,
+    // Active semester clearance, shown by the kiosk when the Clearance purpose is chosen
+    pub clearance: Option<Clearance>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -39,16 +56,55 @@ pub struct PurposeLookup {
 
 use crate::db::attendance::{
     Attendance, 
+    AttendanceScanResult,
     CreateAttendanceRequest, 
+    CheckOutAttendanceRequest,
     SqliteAttendanceRepository, 
     AttendanceRepository
 };
+use crate::db::attendance_audit::ACTOR_KIOSK;
+use crate::db::account_status::{
+    AccountStatusInfo,
+    AccountStatusRepository,
+    SqliteAccountStatusRepository,
+};
+use crate::db::locations::{
+    Kiosk,
+    Location,
+    LocationRepository,
+    SqliteLocationRepository,
+};
+use crate::db::clearance::{Clearance, ClearanceRepository, SqliteClearanceRepository, CLEARANCE_PURPOSE};
+use crate::db::semester::{SemesterRepository, SqliteSemesterRepository};
+use crate::db::occupancy::{LocationOccupancy, OccupancyRepository, SqliteOccupancyRepository};
+use crate::occupancy_monitor::{publish_occupancy, start_occupancy_monitor};
+use crate::db::events::{Event, EventRepository, SqliteEventRepository};
+use crate::db::group_visits::{
+    AddGroupMemberRequest,
+    AddGroupSectionRequest,
+    GroupSectionResult,
+    GroupVisit,
+    GroupVisitRepository,
+    GroupVisitSection,
+    SqliteGroupVisitRepository,
+    StartGroupVisitRequest,
+};
+use crate::db::visitors::{
+    CreateVisitorRequest,
+    SqliteVisitorRepository,
+    Visitor,
+    VisitorRepository,
+    VISITOR_CLASSIFICATION,
+};
 
 async fn create_attendance_handler(
     State(state): State<AppState>,
-    Json(attendance_req): Json<CreateAttendanceRequest>
-) -> Result<Json<Attendance>, (StatusCode, String)> {
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(mut attendance_req): Json<CreateAttendanceRequest>
+) -> Result<Json<AttendanceScanResult>, (StatusCode, String)> {
     let db_accessor = state.db_accessor.clone();
+    let client = ClientInfo::new(connect_info, &headers);
     
     // Wrap the entire handler logic in a blocking task
     let result = tokio::task::spawn_blocking(move || {
@@ -58,16 +114,55 @@ async fn create_attendance_handler(
             Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
         };
 
-        // Use the repository to create attendance
+        client.identify(&conn, &mut attendance_req)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        // Time in, or time out when this is the second scan of an open visit
         let repo = SqliteAttendanceRepository;
-        repo.create_attendance(&conn, attendance_req)
-            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        repo.record_scan(&conn, attendance_req)
+            .map_err(|e| match e {
+                rusqlite::Error::InvalidParameterName(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+                e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            })
     })
     .await
     .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
+    let scan = result?;
+    if !scan.already_logged {
+        publish_occupancy(&state.ws_state, &state.db_accessor).await;
+    }
+
     // Unwrap the result and wrap it in Json
-    Ok(Json(result?))
+    Ok(Json(scan))
+}
+
+async fn check_out_attendance_handler(
+    State(state): State<AppState>,
+    Json(check_out_req): Json<CheckOutAttendanceRequest>
+) -> Result<Json<Attendance>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        let repo = SqliteAttendanceRepository;
+        repo.check_out_attendance(&conn, &check_out_req.school_id, ACTOR_KIOSK)
+            .map_err(|e| match e {
+                rusqlite::Error::QueryReturnedNoRows => (StatusCode::NOT_FOUND, "No open visit for this School ID today".to_string()),
+                e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            })
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let checked_out = result?;
+    publish_occupancy(&state.ws_state, &state.db_accessor).await;
+
+    Ok(Json(checked_out))
 }
 
 async fn school_id_lookup_handler(
@@ -84,7 +179,7 @@ async fn school_id_lookup_handler(
         };
 
         // Updated query to include classification logic
-        let (full_name, classification) = match conn.query_row(
+        let (full_name, classification, status, visitor_id) = match conn.query_row(
             "SELECT 
                 COALESCE(
                     CASE 
@@ -106,8 +201,31 @@ async fn school_id_lookup_handler(
             params![school_id],
             |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
         ) {
-            Ok((name, class)) => (name, class),
-            Err(_) => return Err((StatusCode::NOT_FOUND, "School ID not found".to_string())),
+            Ok((name, class)) => (name, class, LookupStatus::Account, None),
+            Err(rusqlite::Error::QueryReturnedNoRows) => {
+                match SqliteVisitorRepository.get_visitor_by_code(&conn, &school_id) {
+                    Ok(Some(visitor)) => (
+                        visitor.full_name,
+                        VISITOR_CLASSIFICATION.to_string(),
+                        LookupStatus::Visitor,
+                        Some(visitor.id),
+                    ),
+                    Ok(None) => (
+                        String::new(),
+                        VISITOR_CLASSIFICATION.to_string(),
+                        LookupStatus::VisitorRegistrationRequired,
+                        None,
+                    ),
+                    Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+                }
+            },
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        let account_status = match status {
+            LookupStatus::Account => SqliteAccountStatusRepository.get_account_status(&conn, &school_id)
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+            _ => None,
         };
 
         // Prepare purposes statement (unchanged)
@@ -143,12 +261,31 @@ async fn school_id_lookup_handler(
             }
         }
 
+        let clearance = match status {
+            LookupStatus::Account if purposes.contains_key(CLEARANCE_PURPOSE) => {
+                let active_semester = SqliteSemesterRepository.get_active_semester(&conn)
+                    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                match active_semester {
+                    Some(semester) => Some(
+                        SqliteClearanceRepository.get_clearance(&conn, &school_id, semester.id)
+                            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+                    ),
+                    None => None,
+                }
+            },
+            _ => None,
+        };
+
         // Updated response construction to include classification
         Ok(SchoolIdLookupResponse {
             school_id,
             full_name,
             purposes,
             classification,  // Added this field
+            status,
+            visitor_id,
+            account_status,
+            clearance,
         })
     })
     .await
@@ -158,6 +295,255 @@ async fn school_id_lookup_handler(
     result.map(Json)
 }
 
+// Kiosk registration of a visitor whose ID lookup returned VisitorRegistrationRequired
+async fn register_visitor_handler(
+    State(state): State<AppState>,
+    Json(visitor_req): Json<CreateVisitorRequest>
+) -> Result<Json<Visitor>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        SqliteVisitorRepository.create_visitor(&conn, visitor_req)
+            .map_err(|e| match e {
+                rusqlite::Error::InvalidParameterName(msg) => (StatusCode::BAD_REQUEST, msg),
+                e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+            })
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(result?))
+}
+
+// Active locations, for kiosks that let the operator pick where they are
+async fn locations_handler(
+    State(state): State<AppState>
+) -> Result<Json<Vec<Location>>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        SqliteLocationRepository.get_all_locations(&conn, false)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(result?))
+}
+
+// Current occupancy of every active location, for kiosks that show a counter on start
+async fn occupancy_handler(
+    State(state): State<AppState>
+) -> Result<Json<Vec<LocationOccupancy>>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        SqliteOccupancyRepository.get_current_occupancy(&conn, chrono::Utc::now())
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(result?))
+}
+
+// Events that have not ended yet, so a kiosk in event mode can show what it is recording
+async fn events_handler(
+    State(state): State<AppState>
+) -> Result<Json<Vec<Event>>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        SqliteEventRepository.get_events(&conn, false)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(result?))
+}
+
+// Called by a kiosk on start; returns the location the admin bound it to. Kiosks are registered
+// and bound from the admin app, so an unknown id is not found rather than added
+async fn kiosk_handler(
+    State(state): State<AppState>,
+    Path(kiosk_id): Path<String>
+) -> Result<Json<Kiosk>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        SqliteLocationRepository.touch_kiosk(&conn, &kiosk_id)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Kiosk {} is not registered", kiosk_id)))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(result?))
+}
+
+fn group_visit_error(e: rusqlite::Error) -> (StatusCode, String) {
+    match e {
+        rusqlite::Error::InvalidParameterName(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
+        rusqlite::Error::QueryReturnedNoRows => (StatusCode::NOT_FOUND, "Group visit not found".to_string()),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+// Course and year level combinations a faculty member can pick their section from
+async fn group_visit_sections_handler(
+    State(state): State<AppState>
+) -> Result<Json<Vec<GroupVisitSection>>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        SqliteGroupVisitRepository.get_sections(&conn)
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(result?))
+}
+
+// A faculty member scans in to start a group visit
+async fn start_group_visit_handler(
+    State(state): State<AppState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
+    Json(mut group_req): Json<StartGroupVisitRequest>
+) -> Result<Json<GroupVisit>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+    let client = ClientInfo::new(connect_info, &headers);
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        group_req.device_id = Some(client.device_id(&conn, group_req.kiosk_id.as_deref())
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?);
+
+        SqliteGroupVisitRepository.start_group_visit(&conn, group_req, ACTOR_KIOSK)
+            .map_err(group_visit_error)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let group_visit = result?;
+    // The faculty member is now inside, which counts toward the location
+    publish_occupancy(&state.ws_state, &state.db_accessor).await;
+
+    Ok(Json(group_visit))
+}
+
+// Students scanned in quick succession after the faculty member
+async fn add_group_member_handler(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(member_req): Json<AddGroupMemberRequest>
+) -> Result<Json<Attendance>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        SqliteGroupVisitRepository.add_group_member(&conn, id, &member_req.school_id, ACTOR_KIOSK)
+            .map_err(group_visit_error)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let attendance = result?;
+    publish_occupancy(&state.ws_state, &state.db_accessor).await;
+
+    Ok(Json(attendance))
+}
+
+async fn add_group_section_handler(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Json(section_req): Json<AddGroupSectionRequest>
+) -> Result<Json<GroupSectionResult>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        SqliteGroupVisitRepository.add_group_section(&conn, id, section_req, ACTOR_KIOSK)
+            .map_err(group_visit_error)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let section_result = result?;
+    // A whole section can fill a location at once
+    publish_occupancy(&state.ws_state, &state.db_accessor).await;
+
+    Ok(Json(section_result))
+}
+
+async fn end_group_visit_handler(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>
+) -> Result<Json<GroupVisit>, (StatusCode, String)> {
+    let db_accessor = state.db_accessor.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let conn = match Connection::open(&db_accessor.db_path) {
+            Ok(conn) => conn,
+            Err(e) => return Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+        };
+
+        SqliteGroupVisitRepository.end_group_visit(&conn, id, ACTOR_KIOSK)
+            .map_err(group_visit_error)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let group_visit = result?;
+    publish_occupancy(&state.ws_state, &state.db_accessor).await;
+
+    Ok(Json(group_visit))
+}
+
 // Network server setup
 pub async fn start_network_server(db: Database) -> Result<(), Box<dyn std::error::Error>> {
     // Configure CORS
@@ -170,6 +556,10 @@ pub async fn start_network_server(db: Database) -> Result<(), Box<dyn std::error
     let db_accessor = DatabaseAccessor::new(db.get_db_path().clone());
 
     let ws_state = WebSocketState::new(&db_accessor);
+
+    // Keeps the occupancy counter and its history current between scans
+    tokio::spawn(start_occupancy_monitor(ws_state.clone(), db_accessor.clone()));
+
     let app_state = AppState {
         ws_state,
         db_accessor: db_accessor.clone(),
@@ -178,6 +568,17 @@ pub async fn start_network_server(db: Database) -> Result<(), Box<dyn std::error
     let app = Router::new()
         .route("/school_id/:school_id", get(school_id_lookup_handler))
         .route("/attendance", post(create_attendance_handler))
+        .route("/attendance/checkout", post(check_out_attendance_handler))
+        .route("/visitors", post(register_visitor_handler))
+        .route("/locations", get(locations_handler))
+        .route("/occupancy", get(occupancy_handler))
+        .route("/kiosks/:kiosk_id", get(kiosk_handler))
+        .route("/events", get(events_handler))
+        .route("/group_visits", post(start_group_visit_handler))
+        .route("/group_visits/sections", get(group_visit_sections_handler))
+        .route("/group_visits/:id/members", post(add_group_member_handler))
+        .route("/group_visits/:id/section", post(add_group_section_handler))
+        .route("/group_visits/:id/end", post(end_group_visit_handler))
         .route("/ws", get(websocket_handler))
         .layer(cors)
         .with_state(app_state);
@@ -191,7 +592,8 @@ pub async fn start_network_server(db: Database) -> Result<(), Box<dyn std::error
     println!("Network server started on 0.0.0.0:8080");
 
     // Serve the application
-    axum::serve(listener, app)
+    // Peer addresses identify kiosks that never registered an id
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .map_err(|e| -> Box<dyn std::error::Error> {
             format!("Server error: {}", e).into()
@@ -0,0 +1,50 @@
+// src/occupancy_commands.rs
+
+use tauri::State;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::DbState;
+use crate::db::occupancy::{LocationOccupancy, OccupancySample, PeakOccupancy};
+
+#[tauri::command]
+pub async fn get_current_occupancy(
+    state: State<'_, DbState>
+) -> Result<Vec<LocationOccupancy>, String> {
+    let db = state.0.clone();
+    let occupancy_repo = Arc::clone(&db.occupancy_repository);
+
+    db.with_connection(move |conn| {
+        occupancy_repo.get_current_occupancy(conn, Utc::now())
+    }).await.map_err(|e| e.to_string())
+}
+
+// A location_id of None returns the samples of every location
+#[tauri::command]
+pub async fn get_occupancy_history(
+    state: State<'_, DbState>,
+    location_id: Option<Uuid>,
+    date_from: DateTime<Utc>,
+    date_to: DateTime<Utc>
+) -> Result<Vec<OccupancySample>, String> {
+    let db = state.0.clone();
+    let occupancy_repo = Arc::clone(&db.occupancy_repository);
+
+    db.with_connection(move |conn| {
+        occupancy_repo.get_occupancy_history(conn, location_id, date_from, date_to)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_peak_occupancy(
+    state: State<'_, DbState>,
+    date_from: DateTime<Utc>,
+    date_to: DateTime<Utc>
+) -> Result<Vec<PeakOccupancy>, String> {
+    let db = state.0.clone();
+    let occupancy_repo = Arc::clone(&db.occupancy_repository);
+
+    db.with_connection(move |conn| {
+        occupancy_repo.get_peak_occupancy(conn, date_from, date_to)
+    }).await.map_err(|e| e.to_string())
+}
@@ -0,0 +1,60 @@
+// src/occupancy_monitor.rs
+
+use std::time::Duration;
+use chrono::Utc;
+use log::{info, error};
+use crate::db::occupancy::{OccupancyChange, OccupancyLevel, OccupancyRepository, SqliteOccupancyRepository};
+use crate::websocket::{AttendanceEvent, DatabaseAccessor, WebSocketState};
+
+// Estimated exits and visits recorded outside the kiosks only show up on the next check
+const OCCUPANCY_INTERVAL: Duration = Duration::from_secs(60);
+
+// Records the current occupancy and pushes the changes to every connected kiosk and admin window
+pub async fn publish_occupancy(ws_state: &WebSocketState, db_accessor: &DatabaseAccessor) {
+    let db_accessor = db_accessor.clone();
+    let changes = tokio::task::spawn_blocking(move || -> Result<Vec<OccupancyChange>, String> {
+        let conn = db_accessor.get_connection().map_err(|e| e.to_string())?;
+        SqliteOccupancyRepository.record_occupancy(&conn, Utc::now()).map_err(|e| e.to_string())
+    }).await;
+
+    let changes = match changes {
+        Ok(Ok(changes)) => changes,
+        Ok(Err(e)) => {
+            error!("Failed to record occupancy: {}", e);
+            return;
+        },
+        Err(e) => {
+            error!("Failed to record occupancy: {}", e);
+            return;
+        },
+    };
+
+    for change in changes {
+        let alert = match change.crossed_into() {
+            Some(OccupancyLevel::Full) => Some(AttendanceEvent::OccupancyFull(change.occupancy.clone())),
+            Some(OccupancyLevel::Warning) => Some(AttendanceEvent::OccupancyWarning(change.occupancy.clone())),
+            _ => None,
+        };
+
+        if let Some(alert) = alert {
+            info!(
+                "{} is at {:?} occupancy ({} of {:?})",
+                change.occupancy.location_name,
+                change.occupancy.level,
+                change.occupancy.occupancy,
+                change.occupancy.capacity
+            );
+            // No client is excluded from occupancy events
+            let _ = ws_state.sender_tx.send((String::new(), alert)).await;
+        }
+
+        let _ = ws_state.sender_tx.send((String::new(), AttendanceEvent::OccupancyChanged(change.occupancy))).await;
+    }
+}
+
+pub async fn start_occupancy_monitor(ws_state: WebSocketState, db_accessor: DatabaseAccessor) {
+    loop {
+        publish_occupancy(&ws_state, &db_accessor).await;
+        tokio::time::sleep(OCCUPANCY_INTERVAL).await;
+    }
+}
@@ -0,0 +1,295 @@
+// src/pdf_report.rs
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Cursor};
+use std::path::Path;
+use printpdf::{
+    BuiltinFont, Image, ImageTransform, IndirectFontRef, Line, Mm, PdfDocument,
+    PdfDocumentReference, PdfLayerReference, Point,
+};
+use chrono::Utc;
+use chrono_tz::Tz;
+use printpdf::image_crate::codecs::png::PngDecoder;
+use uuid::Uuid;
+
+use crate::db::attendance::Attendance;
+
+// Same banner the records page shows, bundled so reports work offline
+const HEADER_IMAGE: &[u8] = include_bytes!("../../public/attendance_records_header.png");
+
+// A4 portrait
+const PAGE_WIDTH: f32 = 210.0;
+const PAGE_HEIGHT: f32 = 297.0;
+const MARGIN: f32 = 10.0;
+const CONTENT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+const FOOTER_HEIGHT: f32 = 12.0;
+const ROW_HEIGHT: f32 = 6.0;
+const TABLE_FONT_SIZE: f32 = 8.0;
+// The signed digest is long, so it is printed in lines of this many characters
+const DIGEST_LINE_CHARS: usize = 120;
+
+// (header, width in mm), widths add up to CONTENT_WIDTH
+const COLUMNS: [(&str, f32); 7] = [
+    ("School ID", 26.0),
+    ("Full Name", 52.0),
+    ("Date", 22.0),
+    ("Time In", 18.0),
+    ("Time Out", 18.0),
+    ("Classification", 27.0),
+    ("Purpose", 27.0),
+];
+
+pub struct AttendanceReport {
+    pub title: String,
+    // Human readable description of the filters used, printed under the title
+    pub subtitle: Option<String>,
+    pub attendances: Vec<Attendance>,
+    // location_id -> name, for the By Location totals
+    pub location_names: HashMap<Uuid, String>,
+    // group_id -> label, for the By Group Visit totals
+    pub group_visit_labels: HashMap<Uuid, String>,
+    // event_id -> name, for the By Event totals
+    pub event_names: HashMap<Uuid, String>,
+    // Signed integrity digest, printed after the summary
+    pub digest: Option<String>,
+    // Institution timezone the dates and times are printed in
+    pub timezone: Tz,
+}
+
+struct Fonts {
+    regular: IndirectFontRef,
+    bold: IndirectFontRef,
+}
+
+// Built-in Helvetica has no metrics available here, so approximate its average glyph width
+fn fit_text(text: &str, width_mm: f32, font_size: f32) -> String {
+    let char_width = font_size * 0.5 * 0.3528;
+    let max_chars = ((width_mm - 1.5) / char_width).max(1.0) as usize;
+
+    if text.chars().count() <= max_chars {
+        text.to_string()
+    } else {
+        let truncated: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+        format!("{}...", truncated)
+    }
+}
+
+fn draw_rule(layer: &PdfLayerReference, y: f32) {
+    layer.set_outline_thickness(0.5);
+    layer.add_line(Line {
+        points: vec![
+            (Point::new(Mm(MARGIN), Mm(y)), false),
+            (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(y)), false),
+        ],
+        is_closed: false,
+    });
+}
+
+fn draw_row(layer: &PdfLayerReference, cells: &[String], y: f32, font: &IndirectFontRef) {
+    let mut x = MARGIN;
+    for (cell, (_, width)) in cells.iter().zip(COLUMNS.iter()) {
+        layer.use_text(fit_text(cell, *width, TABLE_FONT_SIZE), TABLE_FONT_SIZE, Mm(x + 0.5), Mm(y), font);
+        x += width;
+    }
+}
+
+fn draw_table_header(layer: &PdfLayerReference, y: f32, fonts: &Fonts) -> f32 {
+    let headers: Vec<String> = COLUMNS.iter().map(|(header, _)| header.to_string()).collect();
+    draw_row(layer, &headers, y, &fonts.bold);
+    draw_rule(layer, y - 2.0);
+    y - ROW_HEIGHT
+}
+
+// Scales the banner to the content width and returns the y just below it
+fn draw_header_image(layer: &PdfLayerReference, top: f32) -> Result<f32, Box<dyn Error>> {
+    let decoder = PngDecoder::new(Cursor::new(HEADER_IMAGE))?;
+    let image = Image::try_from(decoder)?;
+
+    let width_px = image.image.width.0 as f32;
+    let height_px = image.image.height.0 as f32;
+    let dpi = width_px / (CONTENT_WIDTH / 25.4);
+    let height_mm = height_px / dpi * 25.4;
+
+    image.add_to_layer(layer.clone(), ImageTransform {
+        translate_x: Some(Mm(MARGIN)),
+        translate_y: Some(Mm(top - height_mm)),
+        dpi: Some(dpi),
+        ..Default::default()
+    });
+
+    Ok(top - height_mm - 4.0)
+}
+
+fn attendance_cells(attendance: &Attendance, tz: Tz) -> Vec<String> {
+    let time_in = attendance.time_in_date.with_timezone(&tz);
+    let time_out = attendance.time_out_date
+        .map(|t| t.with_timezone(&tz).format("%I:%M %p").to_string())
+        .unwrap_or_default();
+
+    vec![
+        attendance.school_id.clone(),
+        attendance.full_name.clone(),
+        time_in.format("%Y-%m-%d").to_string(),
+        time_in.format("%I:%M %p").to_string(),
+        time_out,
+        attendance.classification.clone(),
+        attendance.purpose_label.clone().unwrap_or_default(),
+    ]
+}
+
+fn count_by<F>(attendances: &[Attendance], key: F) -> BTreeMap<String, usize>
+where
+    F: Fn(&Attendance) -> Option<String>,
+{
+    let mut counts = BTreeMap::new();
+    for attendance in attendances {
+        let label = key(attendance)
+            .filter(|label| !label.is_empty())
+            .unwrap_or_else(|| "Unspecified".to_string());
+        *counts.entry(label).or_insert(0) += 1;
+    }
+    counts
+}
+
+struct ReportWriter {
+    doc: PdfDocumentReference,
+    layers: Vec<PdfLayerReference>,
+    fonts: Fonts,
+    y: f32,
+}
+
+impl ReportWriter {
+    fn current_layer(&self) -> PdfLayerReference {
+        self.layers.last().expect("report always has a page").clone()
+    }
+
+    fn new_page(&mut self) {
+        let (page, layer) = self.doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+        self.layers.push(self.doc.get_page(page).get_layer(layer));
+        self.y = PAGE_HEIGHT - MARGIN - 4.0;
+    }
+
+    // Starts a new page when fewer than `height` mm are left above the footer
+    fn ensure_space(&mut self, height: f32) -> bool {
+        if self.y - height < MARGIN + FOOTER_HEIGHT {
+            self.new_page();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn write_line(&mut self, text: &str, font_size: f32, bold: bool) {
+        let font = if bold { &self.fonts.bold } else { &self.fonts.regular };
+        self.current_layer().use_text(text, font_size, Mm(MARGIN), Mm(self.y), font);
+        self.y -= font_size * 0.3528 + 2.5;
+    }
+
+    fn write_counts(&mut self, heading: &str, counts: &BTreeMap<String, usize>) {
+        self.ensure_space(ROW_HEIGHT * 2.0);
+        self.write_line(heading, 10.0, true);
+
+        for (label, count) in counts {
+            self.ensure_space(ROW_HEIGHT);
+            let layer = self.current_layer();
+            layer.use_text(fit_text(label, 80.0, 9.0), 9.0, Mm(MARGIN + 4.0), Mm(self.y), &self.fonts.regular);
+            layer.use_text(count.to_string(), 9.0, Mm(MARGIN + 90.0), Mm(self.y), &self.fonts.regular);
+            self.y -= ROW_HEIGHT - 1.0;
+        }
+        self.y -= 3.0;
+    }
+
+    // Page numbers are drawn last, once the total page count is known
+    fn write_footers(&self, generated_on: &str) {
+        let total = self.layers.len();
+        for (idx, layer) in self.layers.iter().enumerate() {
+            draw_rule(layer, MARGIN + FOOTER_HEIGHT - 4.0);
+            layer.use_text(generated_on, 8.0, Mm(MARGIN), Mm(MARGIN + 2.0), &self.fonts.regular);
+            layer.use_text(
+                format!("Page {} of {}", idx + 1, total),
+                8.0,
+                Mm(PAGE_WIDTH - MARGIN - 25.0),
+                Mm(MARGIN + 2.0),
+                &self.fonts.regular,
+            );
+        }
+    }
+}
+
+pub fn write_attendance_report(path: &Path, report: &AttendanceReport) -> Result<(), Box<dyn Error>> {
+    let (doc, page, layer) = PdfDocument::new(&report.title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Layer 1");
+    let fonts = Fonts {
+        regular: doc.add_builtin_font(BuiltinFont::Helvetica)?,
+        bold: doc.add_builtin_font(BuiltinFont::HelveticaBold)?,
+    };
+    let first_layer = doc.get_page(page).get_layer(layer);
+
+    let mut writer = ReportWriter {
+        doc,
+        layers: vec![first_layer],
+        fonts,
+        y: PAGE_HEIGHT - MARGIN,
+    };
+
+    writer.y = draw_header_image(&writer.current_layer(), writer.y)?;
+    writer.write_line(&report.title, 14.0, true);
+    if let Some(subtitle) = &report.subtitle {
+        writer.write_line(subtitle, 9.0, false);
+    }
+    writer.write_line(&format!("Total records: {}", report.attendances.len()), 9.0, false);
+    writer.y -= 2.0;
+
+    // Attendance table, repeating the column headers on every page
+    writer.y = draw_table_header(&writer.current_layer(), writer.y, &writer.fonts);
+    for attendance in &report.attendances {
+        if writer.ensure_space(ROW_HEIGHT) {
+            writer.y = draw_table_header(&writer.current_layer(), writer.y, &writer.fonts);
+        }
+        draw_row(&writer.current_layer(), &attendance_cells(attendance, report.timezone), writer.y, &writer.fonts.regular);
+        writer.y -= ROW_HEIGHT;
+    }
+    if report.attendances.is_empty() {
+        writer.write_line("No attendance records match the selected filters.", 9.0, false);
+    }
+
+    // Summary totals
+    writer.y -= 4.0;
+    writer.ensure_space(ROW_HEIGHT * 3.0);
+    writer.write_line("Summary", 12.0, true);
+    writer.write_counts("By Purpose", &count_by(&report.attendances, |a| a.purpose_label.clone()));
+    writer.write_counts("By Classification", &count_by(&report.attendances, |a| Some(a.classification.clone())));
+    writer.write_counts("By Location", &count_by(&report.attendances, |a| {
+        a.location_id.and_then(|id| report.location_names.get(&id).cloned())
+    }));
+    if report.attendances.iter().any(|a| a.group_id.is_some()) {
+        writer.write_counts("By Group Visit", &count_by(&report.attendances, |a| match a.group_id {
+            Some(id) => report.group_visit_labels.get(&id).cloned(),
+            None => Some("Individual".to_string()),
+        }));
+    }
+    if report.attendances.iter().any(|a| a.event_id.is_some()) {
+        writer.write_counts("By Event", &count_by(&report.attendances, |a| match a.event_id {
+            Some(id) => report.event_names.get(&id).cloned(),
+            None => Some("No Event".to_string()),
+        }));
+    }
+
+    if let Some(digest) = &report.digest {
+        let chars: Vec<char> = digest.chars().collect();
+        writer.ensure_space(ROW_HEIGHT * 2.0);
+        writer.write_line("Integrity digest", 9.0, true);
+        for chunk in chars.chunks(DIGEST_LINE_CHARS) {
+            writer.ensure_space(ROW_HEIGHT);
+            writer.write_line(&chunk.iter().collect::<String>(), 7.0, false);
+        }
+    }
+
+    let generated_on = format!("Generated on {}", Utc::now().with_timezone(&report.timezone).format("%Y-%m-%d %I:%M %p"));
+    writer.write_footers(&generated_on);
+
+    let mut file = BufWriter::new(File::create(path)?);
+    writer.doc.save(&mut file)?;
+    Ok(())
+}
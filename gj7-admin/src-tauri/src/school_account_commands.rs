@@ -5,7 +5,11 @@ use std::fmt;
 use tauri::State;
 use crate::DbState;
 use crate::db::school_accounts::{PaginatedSchoolAccounts, SchoolAccount, UpdateSchoolAccountRequest, AccountStatusCounts};
+use crate::db::search::{SchoolAccountSearchHit, DEFAULT_SEARCH_LIMIT};
 use crate::db::semester::Semester;
+use crate::db::account_status::{AccountRestriction, AccountStatusInfo, SetAccountRestrictionRequest};
+use crate::storage::get_downloads_dir;
+use crate::xlsx_export::{school_account_sheets, write_workbook, XlsxSheetGrouping};
 use uuid::Uuid;
 use rusqlite::{Result, Error as RusqliteError};
 use serde::{Serialize, Deserialize};
@@ -110,6 +114,42 @@ pub async fn get_all_school_accounts(
     }).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn search_school_accounts(
+    state: State<'_, DbState>,
+    query: String,
+    limit: Option<usize>
+) -> Result<Vec<SchoolAccountSearchHit>, String> {
+    let db = state.0.clone();
+    let school_accounts = db.school_accounts.clone();
+
+    db.with_connection(move |conn| {
+        school_accounts.search_school_accounts(conn, &query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT))
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_school_accounts_to_xlsx(
+    state: State<'_, DbState>,
+    grouping: Option<XlsxSheetGrouping>
+) -> Result<String, String> {
+    let downloads_dir = get_downloads_dir()?;
+    let db = state.0.clone();
+    let school_accounts = db.school_accounts.clone();
+
+    let accounts = db.with_connection(move |conn| {
+        school_accounts.get_all_school_accounts(conn)
+    }).await.map_err(|e| e.to_string())?;
+
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    let file_path = downloads_dir.join(format!("school_accounts_{}.xlsx", timestamp));
+
+    let sheets = school_account_sheets(accounts, grouping.unwrap_or_default());
+    write_workbook(&file_path, &sheets).map_err(|e| format!("XLSX Error: {}", e))?;
+
+    Ok(file_path.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 pub async fn get_dashboard_stats(
     state: State<'_, DbState>,
@@ -219,4 +259,70 @@ pub async fn update_school_account_semester(
         school_accounts.update_school_account(conn, account_id, update)
             .map_err(|_| RusqliteError::InvalidQuery)
     }).await.map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+#[tauri::command]
+pub async fn get_account_status(
+    state: State<'_, DbState>,
+    school_id: String
+) -> Result<Option<AccountStatusInfo>, String> {
+    let db = state.0.clone();
+    let status_repo = db.account_status_repository.clone();
+
+    db.with_connection(move |conn| {
+        status_repo.get_account_status(conn, &school_id)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_account_restrictions(
+    state: State<'_, DbState>
+) -> Result<Vec<AccountRestriction>, String> {
+    let db = state.0.clone();
+    let status_repo = db.account_status_repository.clone();
+
+    db.with_connection(move |conn| {
+        status_repo.get_restrictions(conn)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_account_restriction(
+    state: State<'_, DbState>,
+    school_id: String,
+    restriction: SetAccountRestrictionRequest,
+    username: String,
+    password: String
+) -> Result<AccountRestriction, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let status_repo = db.account_status_repository.clone();
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            status_repo.set_restriction(conn, &school_id, restriction, &username)
+        } else {
+            Err(RusqliteError::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn clear_account_restriction(
+    state: State<'_, DbState>,
+    school_id: String,
+    username: String,
+    password: String
+) -> Result<(), String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let status_repo = db.account_status_repository.clone();
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            status_repo.clear_restriction(conn, &school_id)
+        } else {
+            Err(RusqliteError::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
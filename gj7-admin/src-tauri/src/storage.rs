@@ -4,6 +4,7 @@ use std::path::PathBuf;
 use directories::{ProjectDirs, UserDirs};
 use log::info;
 use std::fs;
+use std::env;
 
 const QUALIFIER: &str = "com";
 const ORGANIZATION: &str = "yourorg";
@@ -51,4 +52,19 @@ impl AppStorage {
         self.public_storage.join("config.xml")
     }
 
+}
+
+// User's Downloads folder, where on-demand exports are written
+pub fn get_downloads_dir() -> Result<PathBuf, String> {
+    let home_var = if cfg!(target_os = "windows") { "USERPROFILE" } else { "HOME" };
+
+    let downloads_dir = env::var_os(home_var)
+        .map(|home| PathBuf::from(home).join("Downloads"))
+        .ok_or_else(|| "Could not find Downloads directory".to_string())?;
+
+    if !downloads_dir.exists() {
+        return Err("Downloads directory does not exist".to_string());
+    }
+
+    Ok(downloads_dir)
 }
\ No newline at end of file
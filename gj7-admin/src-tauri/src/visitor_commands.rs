@@ -0,0 +1,89 @@
+// src/visitor_commands.rs
+
+use tauri::State;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::DbState;
+use crate::db::attendance::AttendanceQuery;
+use crate::db::visitors::{CreateVisitorRequest, RepeatVisitor, Visitor};
+
+#[tauri::command]
+pub async fn create_visitor(
+    state: State<'_, DbState>,
+    visitor: CreateVisitorRequest,
+    username: String,
+    password: String
+) -> Result<Visitor, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let visitor_repo = Arc::clone(&db.visitor_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            visitor_repo.create_visitor(conn, visitor)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_visitor(
+    state: State<'_, DbState>,
+    id: Uuid,
+    visitor: CreateVisitorRequest,
+    username: String,
+    password: String
+) -> Result<Visitor, String> {
+    let db = state.0.clone();
+    let auth = db.auth.clone();
+    let visitor_repo = Arc::clone(&db.visitor_repository);
+
+    db.with_connection(move |conn| {
+        if auth.authenticate(conn, &username, &password)? {
+            visitor_repo.update_visitor(conn, id, visitor)
+        } else {
+            Err(rusqlite::Error::QueryReturnedNoRows)
+        }
+    }).await.map_err(|e| format!("Authentication failed: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_visitor(
+    state: State<'_, DbState>,
+    id: Uuid
+) -> Result<Visitor, String> {
+    let db = state.0.clone();
+    let visitor_repo = Arc::clone(&db.visitor_repository);
+
+    db.with_connection(move |conn| {
+        visitor_repo.get_visitor(conn, id)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_all_visitors(
+    state: State<'_, DbState>,
+    search: Option<String>
+) -> Result<Vec<Visitor>, String> {
+    let db = state.0.clone();
+    let visitor_repo = Arc::clone(&db.visitor_repository);
+
+    db.with_connection(move |conn| {
+        visitor_repo.get_all_visitors(conn, search)
+    }).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_repeat_visitors(
+    state: State<'_, DbState>,
+    query: AttendanceQuery,
+    min_visits: Option<u64>
+) -> Result<Vec<RepeatVisitor>, String> {
+    let db = state.0.clone();
+    let visitor_repo = Arc::clone(&db.visitor_repository);
+
+    db.with_connection(move |conn| {
+        visitor_repo.get_repeat_visitors(conn, &query, min_visits.unwrap_or(2))
+    }).await.map_err(|e| e.to_string())
+}
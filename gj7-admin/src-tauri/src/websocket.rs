@@ -3,25 +3,33 @@
 use axum::{
     extract::{
         ws::{WebSocket, WebSocketUpgrade},
+        ConnectInfo,
         State,
     },
+    http::{header, HeaderMap},
     response::Response,
     routing::get,
     Router,
 };
 use futures::{sink::SinkExt, stream::StreamExt};
 use tokio::sync::{mpsc, Mutex};
-use std::{collections::HashMap, sync::Arc, path::PathBuf};
+use std::{collections::HashMap, sync::Arc, path::PathBuf, net::{IpAddr, SocketAddr}};
 use serde::{Serialize, Deserialize};
 use serde_json::json;
 use rusqlite::Connection;
 
 use crate::db::attendance::{
     Attendance,
+    AttendanceScanResult,
     CreateAttendanceRequest,
+    CheckOutAttendanceRequest,
     SqliteAttendanceRepository,
     AttendanceRepository
 };
+use crate::db::attendance_audit::ACTOR_KIOSK;
+use crate::db::locations::identify_device;
+use crate::db::occupancy::LocationOccupancy;
+use crate::occupancy_monitor::publish_occupancy;
 
 #[derive(Clone)]
 pub struct DatabaseAccessor {
@@ -38,6 +46,34 @@ impl DatabaseAccessor {
     }
 }
 
+// Where a kiosk request came from, for kiosks that never registered an id
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub remote_ip: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientInfo {
+    pub fn new(connect_info: Option<ConnectInfo<SocketAddr>>, headers: &HeaderMap) -> Self {
+        ClientInfo {
+            remote_ip: connect_info.map(|ConnectInfo(addr)| addr.ip()),
+            user_agent: headers.get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string()),
+        }
+    }
+
+    pub fn device_id(&self, conn: &Connection, kiosk_id: Option<&str>) -> Result<String, rusqlite::Error> {
+        identify_device(conn, kiosk_id, self.remote_ip, self.user_agent.as_deref())
+    }
+
+    // Stamps the request with the device it was scanned on before it is recorded
+    pub fn identify(&self, conn: &Connection, attendance_req: &mut CreateAttendanceRequest) -> Result<(), rusqlite::Error> {
+        attendance_req.device_id = Some(self.device_id(conn, attendance_req.kiosk_id.as_deref())?);
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WebSocketError {
     DatabaseError(String),
@@ -61,7 +97,16 @@ pub struct AppState {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum AttendanceEvent {
     NewAttendance(CreateAttendanceRequest),
+    AttendanceCheckedOut(Attendance),
+    DuplicateAttendance(Attendance),
     AttendanceList(Vec<Attendance>),
+    // Sent to every client whenever a location's count changes
+    OccupancyChanged(LocationOccupancy),
+    // Sent once when a location rises to the warning threshold or to capacity
+    OccupancyWarning(LocationOccupancy),
+    OccupancyFull(LocationOccupancy),
+    // Sent only to the scanning kiosk when the account's status policy is Warn
+    EntryWarning(AttendanceScanResult),
     Error(WebSocketError),
 }
 
@@ -112,14 +157,18 @@ fn get_last_n_attendances(
 
 async fn create_attendance(
     db_accessor: DatabaseAccessor,
-    attendance_req: CreateAttendanceRequest,
-) -> Result<Attendance, WebSocketError> {
+    client: ClientInfo,
+    mut attendance_req: CreateAttendanceRequest,
+) -> Result<AttendanceScanResult, WebSocketError> {
     let result = tokio::task::spawn_blocking(move || {
         let conn = db_accessor.get_connection()
             .map_err(|e| WebSocketError::DatabaseError(e.to_string()))?;
-        
+
+        client.identify(&conn, &mut attendance_req)
+            .map_err(|e| WebSocketError::DatabaseError(e.to_string()))?;
+
         let repo = SqliteAttendanceRepository;
-        repo.create_attendance(&conn, attendance_req.clone())
+        repo.record_scan(&conn, attendance_req)
             .map_err(|e| WebSocketError::DatabaseError(e.to_string()))
     })
     .await
@@ -128,15 +177,50 @@ async fn create_attendance(
     result
 }
 
+async fn check_out_attendance(
+    db_accessor: DatabaseAccessor,
+    check_out_req: CheckOutAttendanceRequest,
+) -> Result<Attendance, WebSocketError> {
+    tokio::task::spawn_blocking(move || {
+        let conn = db_accessor.get_connection()
+            .map_err(|e| WebSocketError::DatabaseError(e.to_string()))?;
+
+        let repo = SqliteAttendanceRepository;
+        repo.check_out_attendance(&conn, &check_out_req.school_id, ACTOR_KIOSK)
+            .map_err(|e| WebSocketError::DatabaseError(e.to_string()))
+    })
+    .await
+    .map_err(|e| WebSocketError::DatabaseError(e.to_string()))?
+}
+
+// Delivers an event only to the client that triggered it
+async fn send_to_client(ws_state: &WebSocketState, client_id: &str, event: AttendanceEvent) {
+    let connections = ws_state.connections.lock().await;
+    if let Some(client_tx) = connections.get(client_id) {
+        let _ = client_tx.send(event).await;
+    }
+}
+
+// Replaces the cached copy of a visit that was just checked out
+async fn update_recent_attendance(ws_state: &WebSocketState, attendance: &Attendance) {
+    let mut recent_attendances = ws_state.recent_attendances.lock().await;
+    if let Some(existing) = recent_attendances.iter_mut().find(|a| a.id == attendance.id) {
+        *existing = attendance.clone();
+    }
+}
+
 #[axum::debug_handler]
 pub async fn websocket_handler(
     ws: WebSocketUpgrade,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: HeaderMap,
     State(state): State<AppState>,
 ) -> Response {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    let client = ClientInfo::new(connect_info, &headers);
+    ws.on_upgrade(|socket| handle_socket(socket, state, client))
 }
 
-async fn handle_socket(socket: WebSocket, state: AppState) {
+async fn handle_socket(socket: WebSocket, state: AppState, client: ClientInfo) {
     let (mut sender, mut receiver) = socket.split();
     let client_id = uuid::Uuid::new_v4().to_string();
     let (client_tx, mut client_rx) = mpsc::channel(100);
@@ -164,10 +248,34 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
                         let msg = json!({ "NewAttendance": attendance });
                         let _ = sender.send(axum::extract::ws::Message::Text(msg.to_string())).await;
                     },
+                    AttendanceEvent::AttendanceCheckedOut(attendance) => {
+                        let msg = json!({ "AttendanceCheckedOut": attendance });
+                        let _ = sender.send(axum::extract::ws::Message::Text(msg.to_string())).await;
+                    },
+                    AttendanceEvent::DuplicateAttendance(attendance) => {
+                        let msg = json!({ "DuplicateAttendance": attendance });
+                        let _ = sender.send(axum::extract::ws::Message::Text(msg.to_string())).await;
+                    },
                     AttendanceEvent::AttendanceList(attendances) => {
                         let msg = json!({ "AttendanceList": attendances });
                         let _ = sender.send(axum::extract::ws::Message::Text(msg.to_string())).await;
                     },
+                    AttendanceEvent::OccupancyChanged(occupancy) => {
+                        let msg = json!({ "OccupancyChanged": occupancy });
+                        let _ = sender.send(axum::extract::ws::Message::Text(msg.to_string())).await;
+                    },
+                    AttendanceEvent::OccupancyWarning(occupancy) => {
+                        let msg = json!({ "OccupancyWarning": occupancy });
+                        let _ = sender.send(axum::extract::ws::Message::Text(msg.to_string())).await;
+                    },
+                    AttendanceEvent::OccupancyFull(occupancy) => {
+                        let msg = json!({ "OccupancyFull": occupancy });
+                        let _ = sender.send(axum::extract::ws::Message::Text(msg.to_string())).await;
+                    },
+                    AttendanceEvent::EntryWarning(scan) => {
+                        let msg = json!({ "EntryWarning": scan });
+                        let _ = sender.send(axum::extract::ws::Message::Text(msg.to_string())).await;
+                    },
                     AttendanceEvent::Error(error) => {
                         let msg = json!({ "Error": error });
                         let _ = sender.send(axum::extract::ws::Message::Text(msg.to_string())).await;
@@ -194,27 +302,78 @@ async fn handle_socket(socket: WebSocket, state: AppState) {
                                 match (msg_type, data) {
                                     (Some("NewAttendance"), Some(data)) => {
                                         if let Ok(attendance_req) = serde_json::from_value::<CreateAttendanceRequest>(data.clone()) {
-                                            match create_attendance(db_accessor.clone(), attendance_req.clone()).await {
-                                                Ok(created_attendance) => {
+                                            match create_attendance(db_accessor.clone(), client.clone(), attendance_req.clone()).await {
+                                                Ok(scan) if scan.already_logged => {
+                                                    // Nothing new was written, so only the scanning kiosk needs to know
+                                                    send_to_client(
+                                                        &ws_state,
+                                                        &client_id_clone,
+                                                        AttendanceEvent::DuplicateAttendance(scan.attendance)
+                                                    ).await;
+                                                },
+                                                Ok(scan) if scan.attendance.time_out_date.is_some() => {
+                                                    // Second scan of an open visit closed it instead; the
+                                                    // broadcast skips the scanning kiosk, so it is told directly
+                                                    update_recent_attendance(&ws_state, &scan.attendance).await;
+
+                                                    send_to_client(
+                                                        &ws_state,
+                                                        &client_id_clone,
+                                                        AttendanceEvent::AttendanceCheckedOut(scan.attendance.clone())
+                                                    ).await;
+                                                    let _ = ws_state.sender_tx.send((
+                                                        client_id_clone.clone(),
+                                                        AttendanceEvent::AttendanceCheckedOut(scan.attendance)
+                                                    )).await;
+                                                    publish_occupancy(&ws_state, &db_accessor).await;
+                                                },
+                                                Ok(scan) => {
                                                     // Update recent attendances
                                                     {
                                                         let mut recent_attendances = ws_state.recent_attendances.lock().await;
-                                                        recent_attendances.insert(0, created_attendance);
+                                                        recent_attendances.insert(0, scan.attendance.clone());
                                                         if recent_attendances.len() > 100 {
                                                             recent_attendances.pop();
                                                         }
                                                     }
 
+                                                    if scan.warning.is_some() {
+                                                        send_to_client(&ws_state, &client_id_clone, AttendanceEvent::EntryWarning(scan)).await;
+                                                    }
+
                                                     let _ = ws_state.sender_tx.send((
                                                         client_id_clone.clone(),
                                                         AttendanceEvent::NewAttendance(attendance_req)
                                                     )).await;
+                                                    publish_occupancy(&ws_state, &db_accessor).await;
                                                 },
+                                                // A refusal is for the kiosk that scanned, not for everyone else
                                                 Err(e) => {
+                                                    send_to_client(&ws_state, &client_id_clone, AttendanceEvent::Error(e)).await;
+                                                }
+                                            }
+                                        }
+                                    },
+                                    (Some("CheckOutAttendance"), Some(data)) => {
+                                        if let Ok(check_out_req) = serde_json::from_value::<CheckOutAttendanceRequest>(data.clone()) {
+                                            match check_out_attendance(db_accessor.clone(), check_out_req).await {
+                                                Ok(checked_out) => {
+                                                    update_recent_attendance(&ws_state, &checked_out).await;
+
+                                                    send_to_client(
+                                                        &ws_state,
+                                                        &client_id_clone,
+                                                        AttendanceEvent::AttendanceCheckedOut(checked_out.clone())
+                                                    ).await;
                                                     let _ = ws_state.sender_tx.send((
                                                         client_id_clone.clone(),
-                                                        AttendanceEvent::Error(e)
+                                                        AttendanceEvent::AttendanceCheckedOut(checked_out)
                                                     )).await;
+                                                    publish_occupancy(&ws_state, &db_accessor).await;
+                                                },
+                                                // Only the kiosk that asked can act on a failed check out
+                                                Err(e) => {
+                                                    send_to_client(&ws_state, &client_id_clone, AttendanceEvent::Error(e)).await;
                                                 }
                                             }
                                         }
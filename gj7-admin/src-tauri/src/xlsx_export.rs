@@ -0,0 +1,269 @@
+// src/xlsx_export.rs
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use chrono::{NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+use serde::{Serialize, Deserialize};
+
+use crate::db::attendance::Attendance;
+use crate::db::clearance::{Clearance, CourseClearanceSummary};
+use crate::db::export_templates::{ExportLookups, ExportTemplate, ExportValue};
+use crate::db::school_accounts::{Gender, SchoolAccount};
+
+// Excel rejects sheet names longer than 31 characters
+const MAX_SHEET_NAME_LEN: usize = 31;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub enum XlsxSheetGrouping {
+    #[default]
+    Single,
+    PerCourse,
+    PerDay,
+    PerLocation,
+}
+
+// Typed cell so Excel never has to guess (school IDs stay text, dates stay dates)
+pub enum XlsxCell {
+    Text(String),
+    Number(f64),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    Empty,
+}
+
+pub struct XlsxSheet {
+    pub name: String,
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<XlsxCell>>,
+}
+
+fn sanitize_sheet_name(name: &str, used: &mut HashSet<String>) -> String {
+    let cleaned: String = name.chars()
+        .map(|c| if matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\') { '-' } else { c })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('\'');
+    let base: String = if cleaned.is_empty() { "Sheet".to_string() } else { cleaned.chars().take(MAX_SHEET_NAME_LEN).collect() };
+
+    // Sheet names are case-insensitively unique within a workbook
+    let mut candidate = base.clone();
+    let mut suffix = 2;
+    while used.contains(&candidate.to_lowercase()) {
+        let tag = format!(" ({})", suffix);
+        let keep = MAX_SHEET_NAME_LEN.saturating_sub(tag.len());
+        candidate = format!("{}{}", base.chars().take(keep).collect::<String>(), tag);
+        suffix += 1;
+    }
+
+    used.insert(candidate.to_lowercase());
+    candidate
+}
+
+pub fn write_workbook(path: &Path, sheets: &[XlsxSheet]) -> Result<(), XlsxError> {
+    let mut workbook = Workbook::new();
+
+    let header_format = Format::new().set_bold();
+    let date_format = Format::new().set_num_format("yyyy-mm-dd");
+    let time_format = Format::new().set_num_format("hh:mm AM/PM");
+
+    let mut used_names = HashSet::new();
+
+    for sheet in sheets {
+        let worksheet = workbook.add_worksheet();
+        worksheet.set_name(sanitize_sheet_name(&sheet.name, &mut used_names))?;
+
+        for (col, header) in sheet.headers.iter().enumerate() {
+            worksheet.write_string_with_format(0, col as u16, header, &header_format)?;
+        }
+
+        for (idx, row) in sheet.rows.iter().enumerate() {
+            let row_num = (idx + 1) as u32;
+            for (col, cell) in row.iter().enumerate() {
+                let col = col as u16;
+                match cell {
+                    XlsxCell::Text(value) => { worksheet.write_string(row_num, col, value)?; },
+                    XlsxCell::Number(value) => { worksheet.write_number(row_num, col, *value)?; },
+                    XlsxCell::Date(value) => { worksheet.write_date_with_format(row_num, col, value, &date_format)?; },
+                    XlsxCell::Time(value) => { worksheet.write_time_with_format(row_num, col, value, &time_format)?; },
+                    XlsxCell::Empty => {},
+                }
+            }
+        }
+
+        // Keep the header visible while scrolling and size columns to their content
+        worksheet.set_freeze_panes(1, 0)?;
+        worksheet.autofit();
+    }
+
+    // An empty export still needs one sheet to be a valid workbook
+    if sheets.is_empty() {
+        workbook.add_worksheet();
+    }
+
+    workbook.save(path)
+}
+
+fn optional_text(value: Option<String>) -> XlsxCell {
+    match value {
+        Some(value) if !value.is_empty() => XlsxCell::Text(value),
+        _ => XlsxCell::Empty,
+    }
+}
+
+fn attendance_row(attendance: &Attendance, template: &ExportTemplate, lookups: &ExportLookups) -> Vec<XlsxCell> {
+    template.values(attendance, lookups).into_iter().map(|value| match value {
+        ExportValue::Text(value) => XlsxCell::Text(value),
+        ExportValue::Number(value) => XlsxCell::Number(value),
+        ExportValue::Date(value) => XlsxCell::Date(value.date()),
+        ExportValue::Time(value) => XlsxCell::Time(value.time()),
+        ExportValue::Empty => XlsxCell::Empty,
+    }).collect()
+}
+
+// Last sheet of attendance exports, holding the signed digest auditors check against the log
+pub fn integrity_sheet(digest: &str) -> XlsxSheet {
+    XlsxSheet {
+        name: "Integrity".to_string(),
+        headers: vec!["Integrity digest".to_string()],
+        rows: vec![vec![XlsxCell::Text(digest.to_string())]],
+    }
+}
+
+// See lookups_for_template for what `lookups` holds
+pub fn attendance_sheets(
+    attendances: Vec<Attendance>,
+    template: &ExportTemplate,
+    lookups: &ExportLookups,
+    grouping: XlsxSheetGrouping
+) -> Vec<XlsxSheet> {
+    let headers = template.headers();
+    let mut groups: BTreeMap<String, Vec<Vec<XlsxCell>>> = BTreeMap::new();
+
+    for attendance in attendances {
+        let key = match grouping {
+            XlsxSheetGrouping::Single => "Attendance".to_string(),
+            XlsxSheetGrouping::PerCourse => lookups.account(&attendance)
+                .and_then(|a| a.course.clone())
+                .filter(|c| !c.is_empty())
+                .unwrap_or_else(|| "No Course".to_string()),
+            XlsxSheetGrouping::PerDay => template.local_date(attendance.time_in_date)
+                .format("%Y-%m-%d")
+                .to_string(),
+            XlsxSheetGrouping::PerLocation => lookups.location_name(&attendance)
+                .unwrap_or_else(|| "No Location".to_string()),
+        };
+
+        groups.entry(key).or_default().push(attendance_row(&attendance, template, lookups));
+    }
+
+    groups.into_iter()
+        .map(|(name, rows)| XlsxSheet { name, headers: headers.clone(), rows })
+        .collect()
+}
+
+const SCHOOL_ACCOUNT_HEADERS: [&str; 11] = [
+    "School ID",
+    "First Name",
+    "Middle Name",
+    "Last Name",
+    "Gender",
+    "Course",
+    "Department",
+    "Position",
+    "Major",
+    "Year Level",
+    "Active",
+];
+
+fn school_account_row(account: SchoolAccount) -> Vec<XlsxCell> {
+    let gender = account.gender.map(|g| match g {
+        Gender::Male => "Male".to_string(),
+        Gender::Female => "Female".to_string(),
+        Gender::Other => "Other".to_string(),
+    });
+
+    vec![
+        XlsxCell::Text(account.school_id),
+        optional_text(account.first_name),
+        optional_text(account.middle_name),
+        optional_text(account.last_name),
+        optional_text(gender),
+        optional_text(account.course),
+        optional_text(account.department),
+        optional_text(account.position),
+        optional_text(account.major),
+        optional_text(account.year_level),
+        XlsxCell::Text(if account.is_active { "Yes" } else { "No" }.to_string()),
+    ]
+}
+
+// Accounts have no date of their own, so PerDay falls back to a single sheet
+pub fn school_account_sheets(accounts: Vec<SchoolAccount>, grouping: XlsxSheetGrouping) -> Vec<XlsxSheet> {
+    let mut groups: BTreeMap<String, Vec<Vec<XlsxCell>>> = BTreeMap::new();
+
+    for account in accounts {
+        let key = match grouping {
+            XlsxSheetGrouping::PerCourse => account.course.clone()
+                .filter(|c| !c.is_empty())
+                .unwrap_or_else(|| "No Course".to_string()),
+            _ => "School Accounts".to_string(),
+        };
+
+        groups.entry(key).or_default().push(school_account_row(account));
+    }
+
+    groups.into_iter()
+        .map(|(name, rows)| XlsxSheet { name, headers: SCHOOL_ACCOUNT_HEADERS.iter().map(|h| h.to_string()).collect(), rows })
+        .collect()
+}
+
+const CLEARANCE_SUMMARY_HEADERS: [&str; 5] = ["Course", "Students", "Pending", "Cleared", "On Hold"];
+const CLEARANCE_HEADERS: [&str; 7] = ["School ID", "Name", "Year Level", "Status", "Holds", "Cleared At", "Cleared By"];
+
+// A summary sheet of the per-course counts followed by one sheet per course
+pub fn clearance_sheets(tz: Tz, summaries: Vec<CourseClearanceSummary>, clearances: Vec<Clearance>) -> Vec<XlsxSheet> {
+    let summary_rows = summaries.into_iter()
+        .map(|s| vec![
+            XlsxCell::Text(s.course),
+            XlsxCell::Number(s.total as f64),
+            XlsxCell::Number(s.pending as f64),
+            XlsxCell::Number(s.cleared as f64),
+            XlsxCell::Number(s.on_hold as f64),
+        ])
+        .collect();
+
+    let mut groups: BTreeMap<String, Vec<Vec<XlsxCell>>> = BTreeMap::new();
+    for clearance in clearances {
+        let key = clearance.course.clone()
+            .filter(|c| !c.is_empty())
+            .unwrap_or_else(|| "No Course".to_string());
+        let holds = clearance.holds.iter()
+            .map(|h| match &h.details {
+                Some(details) => format!("{}: {}", h.reason.label(), details),
+                None => h.reason.label().to_string(),
+            })
+            .collect::<Vec<String>>()
+            .join("; ");
+
+        groups.entry(key).or_default().push(vec![
+            XlsxCell::Text(clearance.school_id),
+            XlsxCell::Text(clearance.full_name),
+            optional_text(clearance.year_level),
+            XlsxCell::Text(clearance.status.label().to_string()),
+            optional_text(Some(holds).filter(|h| !h.is_empty())),
+            optional_text(clearance.cleared_at
+                .map(|d| d.with_timezone(&tz).format("%Y-%m-%d %I:%M %p").to_string())),
+            optional_text(clearance.cleared_by),
+        ]);
+    }
+
+    let mut sheets = vec![XlsxSheet {
+        name: "Summary".to_string(),
+        headers: CLEARANCE_SUMMARY_HEADERS.iter().map(|h| h.to_string()).collect(),
+        rows: summary_rows,
+    }];
+    sheets.extend(groups.into_iter()
+        .map(|(name, rows)| XlsxSheet { name, headers: CLEARANCE_HEADERS.iter().map(|h| h.to_string()).collect(), rows }));
+    sheets
+}
@@ -0,0 +1,69 @@
+// tests/export_templates.rs
+//
+// Stored export templates. Exports format naive local times, so formats that need an offset
+// have to be refused when the template is saved rather than when an export runs.
+//
+//     cargo test --test export_templates
+
+use rusqlite::{Connection, Result};
+
+use sample2_lib::db::export_templates::{
+    create_export_templates_table,
+    CreateExportTemplateRequest,
+    ExportColumn,
+    ExportTemplateColumn,
+    ExportTemplateRepository,
+    SqliteExportTemplateRepository,
+};
+
+fn open_database() -> Result<Connection> {
+    let conn = Connection::open_in_memory()?;
+    create_export_templates_table(&conn)?;
+    Ok(conn)
+}
+
+fn request(date_format: &str, time_format: &str) -> CreateExportTemplateRequest {
+    CreateExportTemplateRequest {
+        name: "Registrar".to_string(),
+        columns: vec![
+            ExportTemplateColumn { column: ExportColumn::Date, header: None },
+            ExportTemplateColumn { column: ExportColumn::TimeIn, header: None },
+        ],
+        date_format: date_format.to_string(),
+        time_format: time_format.to_string(),
+        delimiter: ",".to_string(),
+        timezone: None,
+    }
+}
+
+#[test]
+fn plain_formats_are_saved() -> Result<()> {
+    let conn = open_database()?;
+
+    let template = SqliteExportTemplateRepository.create_template(&conn, request("%d/%m/%Y", "%I:%M %p"))?;
+    assert_eq!(template.date_format, "%d/%m/%Y");
+    assert_eq!(template.time_format, "%I:%M %p");
+    Ok(())
+}
+
+#[test]
+fn timezone_formats_are_refused() -> Result<()> {
+    let conn = open_database()?;
+    let repo = SqliteExportTemplateRepository;
+
+    for format in ["%Z", "%z", "%:z", "%+", "%H:%M %Z"] {
+        assert!(repo.create_template(&conn, request("%Y-%m-%d", format)).is_err(), "time format {}", format);
+        assert!(repo.create_template(&conn, request(format, "%H:%M")).is_err(), "date format {}", format);
+    }
+    assert!(repo.get_all_templates(&conn)?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn bad_specifiers_are_refused() -> Result<()> {
+    let conn = open_database()?;
+
+    assert!(SqliteExportTemplateRepository.create_template(&conn, request("%Y-%m-%Q", "%H:%M")).is_err());
+    assert!(SqliteExportTemplateRepository.create_template(&conn, request("  ", "%H:%M")).is_err());
+    Ok(())
+}
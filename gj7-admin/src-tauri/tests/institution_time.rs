@@ -0,0 +1,188 @@
+// tests/institution_time.rs
+//
+// Day boundaries in the institution's timezone. Manila is UTC+8, so a scan at 16:30 UTC already
+// belongs to the next day there.
+//
+//     cargo test --test institution_time
+
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use rusqlite::{params, Connection, Result};
+use uuid::Uuid;
+
+use sample2_lib::db::app_settings::{AppSettingsDatabase, INSTITUTION_TIMEZONE};
+use sample2_lib::db::attendance::{
+    create_attendance_table,
+    AttendanceQuery,
+    AttendanceRepository,
+    SqliteAttendanceRepository,
+};
+use sample2_lib::db::attendance_analytics::{
+    AttendanceAnalyticsRepository,
+    SqliteAttendanceAnalyticsRepository,
+    TimeBucket,
+};
+use sample2_lib::db::events::create_events_tables;
+use sample2_lib::db::institution_time::{
+    at_local_time,
+    day_range,
+    institution_timezone,
+    local_date,
+    local_seconds_sql,
+    DEFAULT_INSTITUTION_TIMEZONE,
+};
+use sample2_lib::db::locations::create_locations_tables;
+use sample2_lib::db::school_accounts::create_school_accounts_table;
+use sample2_lib::db::semester::create_semesters_table;
+
+fn utc(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+}
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+fn open_database() -> Result<Connection> {
+    let conn = Connection::open_in_memory()?;
+    AppSettingsDatabase::init(&conn)?;
+    create_school_accounts_table(&conn)?;
+    create_semesters_table(&conn)?;
+    create_locations_tables(&conn)?;
+    create_attendance_table(&conn)?;
+    create_events_tables(&conn)?;
+    Ok(conn)
+}
+
+fn record_visit(conn: &Connection, school_id: &str, time_in: DateTime<Utc>) -> Result<()> {
+    conn.execute(
+        "INSERT INTO attendance (id, school_id, full_name, time_in_date, classification)
+         VALUES (?1, ?2, 'Juan Dela Cruz', ?3, 'Student')",
+        params![Uuid::new_v4().to_string(), school_id, time_in.timestamp_nanos_opt().unwrap()],
+    )?;
+    Ok(())
+}
+
+fn set_timezone(conn: &Connection, name: &str) -> Result<()> {
+    AppSettingsDatabase.set_setting(conn, INSTITUTION_TIMEZONE, name)?;
+    Ok(())
+}
+
+// Runs the generated expression on a Unix time the way the aggregates do
+fn local_wall_clock(conn: &Connection, tz: Tz, time: DateTime<Utc>, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<String> {
+    let sql = format!(
+        "SELECT strftime('%Y-%m-%d %H:%M', {}, 'unixepoch')",
+        local_seconds_sql(tz, "?1", from, to)
+    );
+    conn.query_row(&sql, params![time.timestamp()], |row| row.get(0))
+}
+
+#[test]
+fn late_afternoon_utc_is_the_next_day_in_manila() {
+    let tz = DEFAULT_INSTITUTION_TIMEZONE;
+    assert_eq!(local_date(tz, utc(2025, 3, 10, 15, 59)), date(2025, 3, 10));
+    assert_eq!(local_date(tz, utc(2025, 3, 10, 16, 0)), date(2025, 3, 11));
+    assert_eq!(local_date(tz, utc(2025, 3, 10, 16, 30)), date(2025, 3, 11));
+}
+
+#[test]
+fn manila_day_starts_at_four_pm_utc() {
+    let (start, end) = day_range(DEFAULT_INSTITUTION_TIMEZONE, date(2025, 3, 11));
+    assert_eq!(start, utc(2025, 3, 10, 16, 0));
+    assert_eq!(end, utc(2025, 3, 11, 16, 0));
+}
+
+#[test]
+fn daylight_saving_days_are_not_24_hours() {
+    let tz = chrono_tz::America::New_York;
+
+    let (start, end) = day_range(tz, date(2025, 3, 9));
+    assert_eq!(start, utc(2025, 3, 9, 5, 0));
+    assert_eq!(end, utc(2025, 3, 10, 4, 0));
+
+    let (start, end) = day_range(tz, date(2025, 11, 2));
+    assert_eq!(start, utc(2025, 11, 2, 4, 0));
+    assert_eq!(end, utc(2025, 11, 3, 5, 0));
+
+    // 02:30 never happens on the spring-forward day; it resolves to just after the jump
+    let skipped = at_local_time(tz, date(2025, 3, 9), NaiveTime::from_hms_opt(2, 30, 0).unwrap());
+    assert_eq!(skipped, utc(2025, 3, 9, 7, 30));
+}
+
+#[test]
+fn sql_wall_clock_matches_chrono() -> Result<()> {
+    let conn = Connection::open_in_memory()?;
+
+    let tz = DEFAULT_INSTITUTION_TIMEZONE;
+    let (from, to) = (utc(2025, 1, 1, 0, 0), utc(2025, 12, 31, 0, 0));
+    assert_eq!(local_wall_clock(&conn, tz, utc(2025, 3, 10, 16, 30), from, to)?, "2025-03-11 00:30");
+
+    // Both offsets of a year with daylight saving, on either side of each change
+    let tz = chrono_tz::America::New_York;
+    for time in [
+        utc(2025, 3, 9, 6, 59),
+        utc(2025, 3, 9, 7, 0),
+        utc(2025, 7, 1, 3, 30),
+        utc(2025, 11, 2, 5, 59),
+        utc(2025, 11, 2, 6, 0),
+    ] {
+        let expected = time.with_timezone(&tz).format("%Y-%m-%d %H:%M").to_string();
+        assert_eq!(local_wall_clock(&conn, tz, time, from, to)?, expected);
+    }
+    Ok(())
+}
+
+#[test]
+fn invalid_timezone_setting_falls_back_to_the_default() -> Result<()> {
+    let conn = open_database()?;
+    assert_eq!(institution_timezone(&conn)?, DEFAULT_INSTITUTION_TIMEZONE);
+
+    set_timezone(&conn, "Mars/Olympus_Mons")?;
+    assert_eq!(institution_timezone(&conn)?, DEFAULT_INSTITUTION_TIMEZONE);
+
+    set_timezone(&conn, "Asia/Tokyo")?;
+    assert_eq!(institution_timezone(&conn)?, chrono_tz::Asia::Tokyo);
+    Ok(())
+}
+
+#[test]
+fn filtered_attendances_use_the_institution_day() -> Result<()> {
+    let conn = open_database()?;
+    let repo = SqliteAttendanceRepository;
+    record_visit(&conn, "2024-00001", utc(2025, 3, 10, 15, 59))?;
+    record_visit(&conn, "2024-00002", utc(2025, 3, 10, 16, 30))?;
+
+    // Midday in Manila on the 10th, and just after midnight on the 11th
+    let tenth = repo.get_filtered_attendances(&conn, None, Some(utc(2025, 3, 10, 4, 0)))?;
+    let eleventh = repo.get_filtered_attendances(&conn, None, Some(utc(2025, 3, 10, 16, 5)))?;
+    assert_eq!(tenth.iter().map(|a| a.school_id.as_str()).collect::<Vec<_>>(), ["2024-00001"]);
+    assert_eq!(eleventh.iter().map(|a| a.school_id.as_str()).collect::<Vec<_>>(), ["2024-00002"]);
+
+    // The same visits share a day in UTC
+    set_timezone(&conn, "UTC")?;
+    assert_eq!(repo.get_filtered_attendances(&conn, None, Some(utc(2025, 3, 10, 4, 0)))?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn daily_time_series_buckets_on_the_institution_day() -> Result<()> {
+    let conn = open_database()?;
+    let analytics = SqliteAttendanceAnalyticsRepository;
+    record_visit(&conn, "2024-00001", utc(2025, 3, 10, 15, 59))?;
+    record_visit(&conn, "2024-00002", utc(2025, 3, 10, 16, 30))?;
+
+    let buckets = analytics.get_time_series(&conn, &AttendanceQuery::default(), TimeBucket::Day)?;
+    let days: Vec<(&str, u64)> = buckets.iter().map(|b| (b.bucket.as_str(), b.count)).collect();
+    assert_eq!(days, [("2025-03-10", 1), ("2025-03-11", 1)]);
+
+    let heatmap = analytics.get_hourly_heatmap(&conn, &AttendanceQuery::default())?;
+    // Monday 23:59 and Tuesday 00:30 in Manila
+    let cells: Vec<(u32, u32)> = heatmap.iter().map(|cell| (cell.weekday, cell.hour)).collect();
+    assert_eq!(cells, [(1, 23), (2, 0)]);
+
+    set_timezone(&conn, "UTC")?;
+    let buckets = analytics.get_time_series(&conn, &AttendanceQuery::default(), TimeBucket::Day)?;
+    let days: Vec<(&str, u64)> = buckets.iter().map(|b| (b.bucket.as_str(), b.count)).collect();
+    assert_eq!(days, [("2025-03-10", 2)]);
+    Ok(())
+}